@@ -0,0 +1,80 @@
+use extism_pdk::{log, var, LogLevel};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CachedResponse {
+    pub etag: String,
+    pub body: Vec<u8>,
+}
+
+fn cache_key(url: &str) -> String {
+    format!("etag:{url}")
+}
+
+/// Reads the cached response for `url` from the host var store.
+/// Hosts without var support simply yield no cache entry.
+pub fn load_cached_response(url: &str) -> Option<CachedResponse> {
+    match var::get::<Vec<u8>>(cache_key(url)) {
+        Ok(Some(bytes)) => serde_json::from_slice(&bytes).ok(),
+        Ok(None) => None,
+        Err(e) => {
+            log!(LogLevel::Debug, "OpenLibrary cache unavailable: {}", e);
+            None
+        }
+    }
+}
+
+pub fn store_cached_response(url: &str, etag: String, body: Vec<u8>) {
+    let entry = CachedResponse { etag, body };
+    let Ok(bytes) = serde_json::to_vec(&entry) else {
+        return;
+    };
+    if let Err(e) = var::set(cache_key(url), bytes) {
+        log!(LogLevel::Debug, "OpenLibrary cache store failed: {}", e);
+    }
+}
+
+/// Picks the body to parse: the cached body on a 304, the fresh body otherwise.
+pub fn resolve_response_body(
+    status: u16,
+    body: Vec<u8>,
+    cached: Option<CachedResponse>,
+) -> Option<Vec<u8>> {
+    if status == 304 {
+        cached.map(|cached| cached.body)
+    } else {
+        Some(body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reuses_cached_body_on_not_modified() {
+        let cached = CachedResponse {
+            etag: "W/\"42\"".to_string(),
+            body: br#"{"title":"The Hobbit"}"#.to_vec(),
+        };
+
+        let body = resolve_response_body(304, vec![], Some(cached.clone()));
+        assert_eq!(body, Some(cached.body));
+    }
+
+    #[test]
+    fn uses_fresh_body_on_success() {
+        let cached = CachedResponse {
+            etag: "W/\"42\"".to_string(),
+            body: b"old".to_vec(),
+        };
+
+        let body = resolve_response_body(200, b"new".to_vec(), Some(cached));
+        assert_eq!(body, Some(b"new".to_vec()));
+    }
+
+    #[test]
+    fn not_modified_without_cache_has_no_body() {
+        assert_eq!(resolve_response_body(304, vec![], None), None);
+    }
+}
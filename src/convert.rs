@@ -12,12 +12,23 @@ use rs_plugin_common_interfaces::{
     RsRequest,
 };
 use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
 
-use crate::openlibrary::{
-    build_cover_url_from_id, build_cover_url_from_olid, OpenLibraryBookRecord,
+use crate::{
+    isbn::hyphenate_isbn13,
+    openlibrary::{
+        build_author_photo_url, build_borrow_url, build_cover_url_from_lccn,
+        build_cover_url_from_oclc, build_cover_url_from_olid, build_read_url,
+        build_sized_cover_url_from_id, build_work_page_url, openlibrary_subject_key,
+        parse_datetime_millis, EbookAccess, OpenLibraryBookRecord, OpenLibraryDataCover,
+    },
+    options::{CanonicalPreference, CoverSize, IsbnOnlyCanonical, LookupOptions},
 };
 
-fn canonical_rs_id(record: &OpenLibraryBookRecord) -> Option<String> {
+fn canonical_rs_id(
+    record: &OpenLibraryBookRecord,
+    preference: CanonicalPreference,
+) -> Option<String> {
     let ids = RsIds {
         isbn13: record.isbn13.clone(),
         openlibrary_edition_id: record.edition_id.clone(),
@@ -25,9 +36,15 @@ fn canonical_rs_id(record: &OpenLibraryBookRecord) -> Option<String> {
         ..Default::default()
     };
 
-    ids.as_isbn13()
-        .or(ids.as_openlibrary_edition_id())
-        .or(ids.as_openlibrary_work_id())
+    let isbn13 = || ids.as_isbn13();
+    let edition = || ids.as_openlibrary_edition_id();
+    let work = || ids.as_openlibrary_work_id();
+
+    match preference {
+        CanonicalPreference::Isbn => isbn13().or_else(edition).or_else(work),
+        CanonicalPreference::Edition => edition().or_else(isbn13).or_else(work),
+        CanonicalPreference::Work => work().or_else(isbn13).or_else(edition),
+    }
 }
 
 fn fallback_local_id(title: &str) -> String {
@@ -98,50 +115,79 @@ fn relation_key(value: &str) -> String {
     }
 }
 
-fn build_images(record: &OpenLibraryBookRecord) -> Vec<ExternalImage> {
-    let mut cover_urls: Vec<String> = Vec::new();
+/// The primary cover first, then the remaining covers in OpenLibrary's order, deduped.
+fn ordered_cover_ids(record: &OpenLibraryBookRecord) -> Vec<u64> {
+    let mut cover_ids: Vec<u64> = Vec::new();
     for cover_id in record
-        .cover_ids
-        .iter()
-        .copied()
-        .chain(record.cover_id.into_iter())
+        .cover_id
+        .into_iter()
+        .chain(record.cover_ids.iter().copied())
     {
-        let url = build_cover_url_from_id(cover_id);
-        if !cover_urls.contains(&url) {
-            cover_urls.push(url);
+        if !cover_ids.contains(&cover_id) {
+            cover_ids.push(cover_id);
+        }
+    }
+    cover_ids
+}
+
+fn build_images(record: &OpenLibraryBookRecord, options: &LookupOptions) -> Vec<ExternalImage> {
+    let mut cover_ids = ordered_cover_ids(record);
+    if let Some(index) = options.cover_index {
+        if let Some(cover_id) = cover_ids.get(index).or(cover_ids.first()).copied() {
+            cover_ids = vec![cover_id];
         }
     }
 
+    let size = options.cover_size;
+    let cover_urls: Vec<String> = cover_ids
+        .into_iter()
+        .map(|cover_id| build_sized_cover_url_from_id(cover_id, size))
+        .collect();
     if !cover_urls.is_empty() {
         return cover_urls
             .into_iter()
             .map(|url| ExternalImage {
                 kind: Some(ImageType::Poster),
                 url: RsRequest {
-                    url,
+                    url: options.cover_scheme.apply(url),
                     ..Default::default()
                 },
                 ..Default::default()
             })
             .collect();
     }
+    if record.no_cover || !options.olid_cover_fallback() {
+        return vec![];
+    }
 
     let image_url = record
         .edition_id
         .as_ref()
-        .map(|edition_id| build_cover_url_from_olid(edition_id))
+        .map(|edition_id| build_cover_url_from_olid(edition_id, size))
         .or_else(|| {
             record
                 .work_id
                 .as_ref()
-                .map(|work_id| build_cover_url_from_olid(work_id))
+                .map(|work_id| build_cover_url_from_olid(work_id, size))
+        })
+        .or_else(|| {
+            record
+                .lccn
+                .as_deref()
+                .map(|lccn| build_cover_url_from_lccn(lccn, size))
+        })
+        .or_else(|| {
+            record
+                .oclc
+                .as_deref()
+                .map(|oclc| build_cover_url_from_oclc(oclc, size))
         });
 
     match image_url {
         Some(url) => vec![ExternalImage {
             kind: Some(ImageType::Poster),
             url: RsRequest {
-                url,
+                url: options.cover_scheme.apply(url),
                 ..Default::default()
             },
             ..Default::default()
@@ -150,9 +196,230 @@ fn build_images(record: &OpenLibraryBookRecord) -> Vec<ExternalImage> {
     }
 }
 
-fn build_people_details(record: &OpenLibraryBookRecord) -> Option<Vec<Person>> {
+/// The image of a books data cover at the configured size, else the largest size listed.
+/// `None` when every size is blank.
+pub fn data_cover_to_image(
+    cover: &OpenLibraryDataCover,
+    options: &LookupOptions,
+) -> Option<ExternalImage> {
+    let sized = |size: CoverSize| match size {
+        CoverSize::Small => cover.small.as_deref(),
+        CoverSize::Medium => cover.medium.as_deref(),
+        CoverSize::Large => cover.large.as_deref(),
+    };
+
+    let url = [
+        options.cover_size,
+        CoverSize::Large,
+        CoverSize::Medium,
+        CoverSize::Small,
+    ]
+    .into_iter()
+    .find_map(|size| sized(size).map(str::trim).filter(|url| !url.is_empty()))?;
+
+    Some(ExternalImage {
+        kind: Some(ImageType::Poster),
+        url: RsRequest {
+            url: options.cover_scheme.apply(url.to_string()),
+            ..Default::default()
+        },
+        ..Default::default()
+    })
+}
+
+const NAME_SUFFIXES: &[&str] = &["jr", "jr.", "sr", "sr.", "ii", "iii", "iv", "phd", "ph.d."];
+
+/// Words that rarely appear in a person's name but often in a corporate body's.
+const ORGANIZATION_WORDS: &[&str] = &[
+    "association",
+    "bureau",
+    "commission",
+    "committee",
+    "company",
+    "congress",
+    "corp",
+    "corporation",
+    "council",
+    "dept",
+    "department",
+    "foundation",
+    "inc",
+    "institute",
+    "library",
+    "llc",
+    "ltd",
+    "ministry",
+    "museum",
+    "office",
+    "press",
+    "society",
+    "university",
+];
+
+/// Conservative check for corporate authors: an institutional word, or a catalogue
+/// hierarchy such as `United States. Congress.` (period-separated multi-letter parts, so
+/// `J. R. R. Tolkien.` stays a person).
+fn is_organization_name(name: &str) -> bool {
+    let has_institutional_word = name
+        .split(|c: char| !c.is_alphanumeric())
+        .any(|word| ORGANIZATION_WORDS.contains(&word.to_lowercase().as_str()));
+    if has_institutional_word {
+        return true;
+    }
+
+    let Some(stripped) = name.trim().strip_suffix('.') else {
+        return false;
+    };
+    let segments: Vec<&str> = stripped.split(". ").map(str::trim).collect();
+    segments.len() > 1 && segments.iter().all(|segment| segment.chars().count() > 2)
+}
+
+/// Splits glued initials (`J.R.R.`) into `J. R. R.`; other tokens are left untouched.
+fn space_initials(token: &str) -> String {
+    let segments: Vec<&str> = token.split_inclusive('.').collect();
+    let all_initials = segments.len() > 1
+        && segments.iter().all(|segment| {
+            let mut chars = segment.chars();
+            matches!(
+                (chars.next(), chars.next(), chars.next()),
+                (Some(letter), Some('.'), None) if letter.is_uppercase()
+            )
+        });
+
+    if all_initials {
+        segments.join(" ")
+    } else {
+        token.to_string()
+    }
+}
+
+/// Reorders `Last, First` into `First Last` and normalizes initials spacing. Single token
+/// names, several commas and trailing suffixes (`King, Jr.`) are kept in their order.
+fn normalize_author_name(name: &str) -> String {
+    let reordered = match name.split_once(',') {
+        Some((last, first))
+            if !first.contains(',')
+                && !last.trim().is_empty()
+                && !first.trim().is_empty()
+                && !NAME_SUFFIXES.contains(&first.trim().to_lowercase().as_str()) =>
+        {
+            format!("{} {}", first.trim(), last.trim())
+        }
+        _ => name.to_string(),
+    };
+
+    reordered
+        .split_whitespace()
+        .map(space_initials)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// `J.R.R. Tolkien` as `Tolkien, J.R.R.`; names already in `Last, First` form, single
+/// tokens and names ending in a suffix (`Martin Luther King Jr.`) are kept as is.
+fn citation_author_name(name: &str) -> String {
+    let name = name.trim();
+    if name.contains(',') {
+        return name.to_string();
+    }
+    match name.rsplit_once(char::is_whitespace) {
+        Some((first, last)) if !NAME_SUFFIXES.contains(&last.to_lowercase().as_str()) => {
+            format!("{}, {}", last, first.trim())
+        }
+        _ => name.to_string(),
+    }
+}
+
+fn with_period(segment: &str) -> String {
+    if segment.ends_with('.') {
+        segment.to_string()
+    } else {
+        format!("{segment}.")
+    }
+}
+
+/// `Author. Title. Publisher, Year.` with the first author inverted (`et al.` for more);
+/// missing segments are omitted.
+fn build_citation(record: &OpenLibraryBookRecord) -> String {
+    let mut segments = Vec::new();
+
+    let authors: Vec<&str> = record
+        .authors
+        .iter()
+        .map(|author| author.trim())
+        .filter(|author| !author.is_empty())
+        .collect();
+    if let Some(first) = authors.first() {
+        let mut author = citation_author_name(first);
+        if authors.len() > 1 {
+            author.push_str(", et al");
+        }
+        segments.push(with_period(&author));
+    }
+
+    let title = record.title.trim();
+    if !title.is_empty() {
+        segments.push(with_period(title));
+    }
+
+    let publisher = record
+        .publishers
+        .iter()
+        .map(|publisher| publisher.trim())
+        .find(|publisher| !publisher.is_empty());
+    let imprint = match (publisher, record.publish_year) {
+        (Some(publisher), Some(year)) => Some(format!("{publisher}, {year}")),
+        (Some(publisher), None) => Some(publisher.to_string()),
+        (None, Some(year)) => Some(year.to_string()),
+        (None, None) => None,
+    };
+    if let Some(imprint) = imprint {
+        segments.push(with_period(&imprint));
+    }
+
+    segments.join(" ")
+}
+
+/// Display name for a person plus the raw name when `normalize_authors` changed it.
+fn person_display_name(name: &str, options: &LookupOptions) -> (String, Option<String>) {
+    if !options.normalize_authors {
+        return (name.to_string(), None);
+    }
+    let normalized = normalize_author_name(name);
+    if normalized == name {
+        (normalized, None)
+    } else {
+        (normalized, Some(name.to_string()))
+    }
+}
+
+/// Records an extra role on an already emitted person under `params.roles`.
+fn merge_person_role(person: &mut Person, role: &str) {
+    let primary_role = person.kind.clone().unwrap_or_default();
+    let params = person.params.get_or_insert_with(|| json!({}));
+    let Some(params) = params.as_object_mut() else {
+        return;
+    };
+    let roles = params
+        .entry("roles")
+        .or_insert_with(|| json!([primary_role]));
+    if let Some(roles) = roles.as_array_mut() {
+        if !roles.iter().any(|existing| existing == role) {
+            roles.push(json!(role));
+        }
+    }
+}
+
+/// Authors sharing an OpenLibrary author key become one person (other spellings go to
+/// `alt`), and contributors whose name matches an author only add their role to it.
+fn build_people_details(
+    record: &OpenLibraryBookRecord,
+    options: &LookupOptions,
+) -> Option<Vec<Person>> {
     let mut people: Vec<Person> = Vec::new();
     let mut seen_ids: Vec<String> = Vec::new();
+    let mut author_indexes: HashMap<String, usize> = HashMap::new();
+    let mut name_indexes: HashMap<String, usize> = HashMap::new();
 
     for (index, name) in record.authors.iter().enumerate() {
         let name = name.trim();
@@ -167,6 +434,18 @@ fn build_people_details(record: &OpenLibraryBookRecord) -> Option<Vec<Person>> {
             .filter(|key| !key.is_empty())
             .map(relation_key);
 
+        if let Some(&index) = person_key.as_ref().and_then(|key| author_indexes.get(key)) {
+            let person = &mut people[index];
+            if person.name != name && !person.alt.iter().flatten().any(|alt| alt == name) {
+                person
+                    .alt
+                    .get_or_insert_with(Vec::new)
+                    .push(name.to_string());
+            }
+            name_indexes.entry(slugify(name)).or_insert(index);
+            continue;
+        }
+
         let base_key = slugify(name);
         let relation_key = person_key
             .as_ref()
@@ -179,15 +458,56 @@ fn build_people_details(record: &OpenLibraryBookRecord) -> Option<Vec<Person>> {
         }
         seen_ids.push(other_id.clone());
 
+        let details = record.author_keys.get(index).and_then(|key| {
+            record
+                .author_details
+                .iter()
+                .find(|details| details.key == *key)
+        });
+
+        if let Some(author_key) = &person_key {
+            author_indexes.insert(author_key.clone(), people.len());
+        }
+        name_indexes.entry(slugify(name)).or_insert(people.len());
+
         let mut params = serde_json::Map::new();
         if let Some(author_key) = person_key {
             params.insert("openlibraryAuthorId".to_string(), json!(author_key));
         }
+        if let Some(birth_date) = details.and_then(|details| details.birth_date.as_ref()) {
+            params.insert("birthDate".to_string(), json!(birth_date));
+        }
+        if let Some(death_date) = details.and_then(|details| details.death_date.as_ref()) {
+            params.insert("deathDate".to_string(), json!(death_date));
+        }
+        if let Some(birth_year) = details.and_then(|details| details.birth_year) {
+            params.insert("birthYear".to_string(), json!(birth_year));
+        }
+        if let Some(death_year) = details.and_then(|details| details.death_year) {
+            params.insert("deathYear".to_string(), json!(death_year));
+        }
+        let (display_name, raw_name) = person_display_name(name, options);
+        if let Some(raw_name) = raw_name {
+            params.insert("rawName".to_string(), json!(raw_name));
+        }
 
+        let kind = if options.exclude_org_authors && is_organization_name(name) {
+            "organization"
+        } else {
+            "author"
+        };
         people.push(Person {
             id: other_id.clone(),
-            name: name.to_string(),
-            kind: Some("author".to_string()),
+            name: display_name,
+            kind: Some(kind.to_string()),
+            alt: details
+                .map(|details| details.alternate_names.clone())
+                .filter(|names| !names.is_empty()),
+            bio: details.and_then(|details| details.bio.clone()),
+            portrait: details
+                .and_then(|details| details.photo_id)
+                .map(build_author_photo_url)
+                .map(|url| options.cover_scheme.apply(url)),
             params: if params.is_empty() {
                 None
             } else {
@@ -199,6 +519,40 @@ fn build_people_details(record: &OpenLibraryBookRecord) -> Option<Vec<Person>> {
         });
     }
 
+    for contributor in &record.contributors {
+        let name = contributor.name.trim();
+        if name.is_empty() {
+            continue;
+        }
+
+        if let Some(&index) = name_indexes.get(&slugify(name)) {
+            merge_person_role(&mut people[index], &contributor.role);
+            continue;
+        }
+
+        let relation_key = format!("{}-{}", slugify(name), slugify(&contributor.role));
+        let other_id = format!("openlib-person:{relation_key}");
+
+        if seen_ids.contains(&other_id) {
+            continue;
+        }
+        seen_ids.push(other_id.clone());
+
+        let (display_name, raw_name) = person_display_name(name, options);
+        people.push(Person {
+            id: other_id.clone(),
+            name: display_name,
+            kind: Some(contributor.role.clone()),
+            params: raw_name.map(|raw_name| json!({ "rawName": raw_name })),
+            generated: true,
+            otherids: Some(OtherIds(vec![other_id])),
+            ..Default::default()
+        });
+    }
+
+    if let Some(max_people) = options.max_people {
+        people.truncate(max_people);
+    }
     if people.is_empty() {
         None
     } else {
@@ -206,7 +560,43 @@ fn build_people_details(record: &OpenLibraryBookRecord) -> Option<Vec<Person>> {
     }
 }
 
-fn build_tags_details(record: &OpenLibraryBookRecord) -> Option<Vec<Tag>> {
+/// English subject names with their French display names.
+const SUBJECT_TRANSLATIONS_FR: &[(&str, &str)] = &[
+    ("adventure", "Aventure"),
+    ("biography", "Biographie"),
+    ("children's fiction", "Littérature jeunesse"),
+    ("comics", "Bande dessinée"),
+    ("detective and mystery stories", "Romans policiers"),
+    ("fantasy", "Fantaisie"),
+    ("fiction", "Fiction"),
+    ("history", "Histoire"),
+    ("horror", "Horreur"),
+    ("humor", "Humour"),
+    ("mystery", "Mystère"),
+    ("philosophy", "Philosophie"),
+    ("poetry", "Poésie"),
+    ("romance", "Romance"),
+    ("science fiction", "Science-fiction"),
+    ("thriller", "Thriller"),
+    ("young adult fiction", "Jeunesse"),
+];
+
+/// Display name for a subject in `locale`, falling back to the English name.
+fn localize_subject(name: &str, locale: Option<&str>) -> String {
+    let table = match locale {
+        Some("fr") => SUBJECT_TRANSLATIONS_FR,
+        _ => return name.to_string(),
+    };
+
+    let key = name.to_lowercase();
+    table
+        .iter()
+        .find(|(english, _)| *english == key)
+        .map(|(_, localized)| localized.to_string())
+        .unwrap_or_else(|| name.to_string())
+}
+
+fn build_tags_details(record: &OpenLibraryBookRecord, options: &LookupOptions) -> Option<Vec<Tag>> {
     let mut tags: Vec<Tag> = Vec::new();
     let mut seen_ids: Vec<String> = Vec::new();
 
@@ -216,7 +606,13 @@ fn build_tags_details(record: &OpenLibraryBookRecord) -> Option<Vec<Tag>> {
             continue;
         }
 
-        let key = relation_key(name);
+        let canonical_key = openlibrary_subject_key(name);
+        let work_count = record.subject_work_counts.get(&canonical_key).copied();
+        let key = if record.subject_keys.contains(&canonical_key) {
+            canonical_key
+        } else {
+            relation_key(name)
+        };
         let other_id = format!("openlib-tag:{key}");
 
         if seen_ids.contains(&other_id) {
@@ -226,12 +622,17 @@ fn build_tags_details(record: &OpenLibraryBookRecord) -> Option<Vec<Tag>> {
 
         tags.push(Tag {
             id: other_id.clone(),
-            name: name.to_string(),
+            name: localize_subject(name, options.locale.as_deref()),
             parent: None,
             kind: Some("subject".to_string()),
             alt: None,
             thumb: None,
-            params: Some(json!({ "openlibraryTagKey": key })),
+            params: Some(match work_count {
+                Some(work_count) => {
+                    json!({ "openlibraryTagKey": key, "subjectWorkCount": work_count })
+                }
+                None => json!({ "openlibraryTagKey": key }),
+            }),
             modified: 0,
             added: 0,
             generated: true,
@@ -240,6 +641,9 @@ fn build_tags_details(record: &OpenLibraryBookRecord) -> Option<Vec<Tag>> {
         });
     }
 
+    if let Some(max_subjects) = options.max_subjects {
+        tags.truncate(max_subjects);
+    }
     if tags.is_empty() {
         None
     } else {
@@ -247,37 +651,387 @@ fn build_tags_details(record: &OpenLibraryBookRecord) -> Option<Vec<Tag>> {
     }
 }
 
-fn build_params(record: &OpenLibraryBookRecord) -> serde_json::Value {
-    let mut params = serde_json::Map::new();
+fn build_slug(record: &OpenLibraryBookRecord) -> String {
+    let mut parts = vec![record.title.trim().to_string()];
+    if let Some(author) = record
+        .authors
+        .iter()
+        .map(|author| author.trim())
+        .find(|author| !author.is_empty())
+    {
+        parts.push(author.to_string());
+    }
+    if let Some(year) = record.publish_year {
+        parts.push(year.to_string());
+    }
+
+    slugify(&parts.join(" "))
+}
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// FNV-1a over the normalized title, canonical ID, publish year and primary cover, as 16
+/// hex digits. Hand-rolled because std's hasher is not guaranteed stable across builds.
+fn build_content_hash(record: &OpenLibraryBookRecord, preference: CanonicalPreference) -> String {
+    let title = record
+        .title
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase();
+    let fields = [
+        title,
+        canonical_rs_id(record, preference).unwrap_or_default(),
+        record
+            .publish_year
+            .map(|year| year.to_string())
+            .unwrap_or_default(),
+        record
+            .cover_id
+            .map(|cover_id| cover_id.to_string())
+            .unwrap_or_default(),
+    ];
+
+    let mut hash = FNV_OFFSET_BASIS;
+    for (index, field) in fields.iter().enumerate() {
+        if index > 0 {
+            // Unit separator keeps `ab|c` and `a|bc` apart.
+            hash = (hash ^ 0x1f).wrapping_mul(FNV_PRIME);
+        }
+        for byte in field.bytes() {
+            hash = (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME);
+        }
+    }
+    format!("{hash:016x}")
+}
+
+const SOURCE_LIST_LIMIT: usize = 10;
+const SOURCE_TEXT_CHARS: usize = 200;
+
+/// Compact, size-bounded JSON view of a record for `debug_sources`: lists keep their
+/// first `SOURCE_LIST_LIMIT` entries and the description is cut at `SOURCE_TEXT_CHARS`.
+pub fn record_source_summary(record: &OpenLibraryBookRecord) -> serde_json::Value {
+    let list = |values: &[String]| -> Vec<String> {
+        values.iter().take(SOURCE_LIST_LIMIT).cloned().collect()
+    };
+    let mut summary: BTreeMap<String, serde_json::Value> = BTreeMap::new();
+    summary.insert("title".to_string(), json!(record.title));
+    if let Some(edition_id) = &record.edition_id {
+        summary.insert("editionId".to_string(), json!(edition_id));
+    }
+    if let Some(work_id) = &record.work_id {
+        summary.insert("workId".to_string(), json!(work_id));
+    }
+    if let Some(isbn13) = &record.isbn13 {
+        summary.insert("isbn13".to_string(), json!(isbn13));
+    }
+    if let Some(year) = record.publish_year {
+        summary.insert("year".to_string(), json!(year));
+    }
+    if let Some(pages) = record.pages {
+        summary.insert("pages".to_string(), json!(pages));
+    }
+    if let Some(language) = &record.language {
+        summary.insert("language".to_string(), json!(language));
+    }
+    if !record.cover_ids.is_empty() {
+        let covers: Vec<u64> = record
+            .cover_ids
+            .iter()
+            .take(SOURCE_LIST_LIMIT)
+            .copied()
+            .collect();
+        summary.insert("coverIds".to_string(), json!(covers));
+    }
+    if !record.authors.is_empty() {
+        summary.insert("authors".to_string(), json!(list(&record.authors)));
+    }
+    if !record.subjects.is_empty() {
+        summary.insert("subjects".to_string(), json!(list(&record.subjects)));
+    }
+    if !record.publishers.is_empty() {
+        summary.insert("publishers".to_string(), json!(list(&record.publishers)));
+    }
+    if let Some(description) = &record.description {
+        let description =
+            truncate_at_word(description, SOURCE_TEXT_CHARS).unwrap_or_else(|| description.clone());
+        summary.insert("description".to_string(), json!(description));
+    }
+    serde_json::Value::Object(summary.into_iter().collect())
+}
+
+/// Cuts `text` to at most `max_chars` characters at the last word boundary and appends
+/// an ellipsis. Returns `None` when the text already fits.
+fn truncate_at_word(text: &str, max_chars: usize) -> Option<String> {
+    if text.chars().count() <= max_chars {
+        return None;
+    }
+
+    let cut = text
+        .char_indices()
+        .nth(max_chars)
+        .map(|(index, _)| index)
+        .unwrap_or(text.len());
+    let head = &text[..cut];
+    // Keep the whole head when the next character already starts a new word.
+    let head = if text[cut..].starts_with(char::is_whitespace) {
+        head
+    } else {
+        head.rfind(char::is_whitespace)
+            .map(|index| &head[..index])
+            .filter(|head| !head.trim().is_empty())
+            .unwrap_or(head)
+    };
+
+    let head = head.trim_end_matches(|c: char| c.is_whitespace() || matches!(c, ',' | ';' | ':'));
+    Some(format!("{head}…"))
+}
+
+/// Splits trailing ` (...)` segments off a title, e.g. `"The Hobbit (Middle-earth Universe)"`.
+/// Parentheses glued to a word, unbalanced ones, or a title that is nothing but a
+/// parenthetical are left alone so the real title is never damaged.
+fn split_title_note(title: &str) -> (String, Option<String>) {
+    let mut remaining = title.trim_end();
+    let mut notes = Vec::new();
+
+    while let Some(without_close) = remaining.strip_suffix(')') {
+        let mut depth = 0usize;
+        let mut open_index = None;
+        for (index, character) in without_close.char_indices().rev() {
+            match character {
+                ')' => depth += 1,
+                '(' if depth == 0 => {
+                    open_index = Some(index);
+                    break;
+                }
+                '(' => depth -= 1,
+                _ => {}
+            }
+        }
+        let Some(open_index) = open_index else {
+            break;
+        };
+
+        let head = &without_close[..open_index];
+        if !head.ends_with(char::is_whitespace) || head.trim().is_empty() {
+            break;
+        }
+        let note = without_close[open_index + 1..].trim();
+        if !note.is_empty() {
+            notes.push(note.to_string());
+        }
+        remaining = head.trim_end();
+    }
+
+    if notes.is_empty() {
+        return (title.to_string(), None);
+    }
+    notes.reverse();
+    (remaining.to_string(), Some(notes.join("; ")))
+}
+
+fn build_params(record: &OpenLibraryBookRecord, options: &LookupOptions) -> serde_json::Value {
+    // Sorted regardless of serde_json's `preserve_order` feature, so the output is stable
+    // whatever order the keys are inserted in.
+    let mut params: BTreeMap<String, serde_json::Value> = BTreeMap::new();
 
     if !record.authors.is_empty() {
         params.insert("authors".to_string(), json!(record.authors));
     }
+    // Work lookups list the work's authors first, so its first author leads.
+    if let Some(primary) = record
+        .authors
+        .iter()
+        .map(|author| author.trim())
+        .find(|author| !author.is_empty())
+    {
+        let (name, _) = person_display_name(primary, options);
+        params.insert("primaryAuthor".to_string(), json!(name));
+    }
     if !record.subjects.is_empty() {
         params.insert("subjects".to_string(), json!(record.subjects));
     }
     if !record.publishers.is_empty() {
         params.insert("publishers".to_string(), json!(record.publishers));
     }
+    if !record.publish_places.is_empty() {
+        if record.publish_places.len() == record.publishers.len() {
+            let detailed: Vec<serde_json::Value> = record
+                .publishers
+                .iter()
+                .zip(&record.publish_places)
+                .map(|(name, place)| json!({ "name": name, "place": place }))
+                .collect();
+            params.insert("publishersDetailed".to_string(), json!(detailed));
+        } else {
+            params.insert("publishPlaces".to_string(), json!(record.publish_places));
+        }
+    }
     if let Some(edition_id) = &record.edition_id {
         params.insert("openlibraryEditionId".to_string(), json!(edition_id));
     }
     if let Some(work_id) = &record.work_id {
         params.insert("openlibraryWorkId".to_string(), json!(work_id));
     }
+    if let Some(isbn13_display) = record.isbn13.as_deref().and_then(hyphenate_isbn13) {
+        params.insert("isbn13Display".to_string(), json!(isbn13_display));
+    }
+    if !record.work_ids.is_empty() {
+        params.insert("workIds".to_string(), json!(record.work_ids));
+    }
+    if !record.other_titles.is_empty() {
+        params.insert("otherTitles".to_string(), json!(record.other_titles));
+    }
+    if let Some(rating) = record.edition_rating {
+        params.insert("editionRating".to_string(), json!(rating));
+    }
+    if let Some(rating) = record.work_rating {
+        params.insert("workRating".to_string(), json!(rating));
+    }
+    if let Some(series) = &record.series {
+        params.insert("series".to_string(), json!(series.name));
+        if let Some(position) = series.position {
+            params.insert("seriesPosition".to_string(), json!(position));
+        }
+    }
+    if let Some(year) = record.historical_year {
+        params.insert("signedYear".to_string(), json!(year));
+        params.insert(
+            "era".to_string(),
+            json!(if year < 0 { "BCE" } else { "CE" }),
+        );
+    }
+    if let Some(publish_date_raw) = &record.publish_date_raw {
+        params.insert("publishDateRaw".to_string(), json!(publish_date_raw));
+    }
+    if let Some(first_publish_year) = record.first_publish_year {
+        params.insert("firstPublishYear".to_string(), json!(first_publish_year));
+    }
+    if let Some(edition_count) = record.edition_count {
+        params.insert("editionCount".to_string(), json!(edition_count));
+    }
+    if options.isbn_only_canonical == IsbnOnlyCanonical::Flag && record.isbn13.is_none() {
+        params.insert("missingIsbn".to_string(), json!(true));
+    }
+    if let Some(subtitle) = &record.subtitle {
+        params.insert("subtitle".to_string(), json!(subtitle));
+    }
+    if let Some(pagination) = &record.pagination {
+        params.insert("pagination".to_string(), json!(pagination));
+    }
+    if let Some(last_modified) = &record.last_modified {
+        params.insert("lastModified".to_string(), json!(last_modified));
+    }
+    if let Some(revision) = record.revision {
+        params.insert("revision".to_string(), json!(revision));
+    }
+    if let Some(goodreads_id) = &record.goodreads_id {
+        params.insert("goodreadsId".to_string(), json!(goodreads_id));
+    }
+    if let Some(ocaid) = &record.ocaid {
+        match record.ebook_access {
+            Some(EbookAccess::Public) => {
+                params.insert("readUrl".to_string(), json!(build_read_url(ocaid)));
+            }
+            Some(EbookAccess::Borrowable) => {
+                params.insert("borrowUrl".to_string(), json!(build_borrow_url(ocaid)));
+            }
+            _ => {}
+        }
+    }
+    if let Some(snippet) = &record.match_snippet {
+        params.insert("matchSnippet".to_string(), json!(snippet));
+    }
+    if let Some(confidence) = record.confidence {
+        params.insert("confidence".to_string(), json!(confidence));
+    }
+    if let Some(cover_id) = record.cover_id.or(record.cover_ids.first().copied()) {
+        let sized = |size| {
+            options
+                .cover_scheme
+                .apply(build_sized_cover_url_from_id(cover_id, size))
+        };
+        params.insert(
+            "cover".to_string(),
+            json!({
+                "id": cover_id,
+                "small": sized(CoverSize::Small),
+                "medium": sized(CoverSize::Medium),
+                "large": sized(CoverSize::Large),
+            }),
+        );
+    }
+    if let Some(sources) = &record.debug_sources {
+        params.insert("_sources".to_string(), sources.clone());
+    }
+    if let Some(resolved_by) = record.resolved_by {
+        params.insert("resolvedBy".to_string(), json!(resolved_by.as_str()));
+    }
+    if options.include_work_link_for_editions && record.edition_id.is_some() {
+        if let Some(work_id) = &record.work_id {
+            params.insert("parentWorkId".to_string(), json!(work_id));
+            params.insert(
+                "parentWorkUrl".to_string(),
+                json!(build_work_page_url(work_id)),
+            );
+        }
+    }
+    params.insert("slug".to_string(), json!(build_slug(record)));
+    params.insert("citation".to_string(), json!(build_citation(record)));
+    params.insert(
+        "contentHash".to_string(),
+        json!(build_content_hash(record, options.canonical_preference())),
+    );
+    if let Some(cover_source) = &record.cover_source {
+        params.insert("coverSource".to_string(), json!(cover_source));
+    }
+    // Every known cover, whatever `cover_index` picks; an id-less record counts its
+    // OLID/LCCN/OCLC fallback cover.
+    let cover_count = match ordered_cover_ids(record).len() {
+        0 => build_images(record, options).len(),
+        count => count,
+    };
+    params.insert("coverCount".to_string(), json!(cover_count));
+    if options.keep_full_description {
+        if let (Some(description), Some(max_chars)) =
+            (&record.description, options.max_description_chars)
+        {
+            if truncate_at_word(description, max_chars).is_some() {
+                params.insert("descriptionFull".to_string(), json!(description));
+            }
+        }
+    }
+    if options.strip_parentheticals {
+        if let (_, Some(note)) = split_title_note(&record.title) {
+            params.insert("titleNote".to_string(), json!(note));
+        }
+    }
+    if options.include_toc && !record.table_of_contents.is_empty() {
+        params.insert(
+            "tableOfContents".to_string(),
+            json!(record.table_of_contents),
+        );
+    }
+    if let Some(fields) = &options.result_fields {
+        params.retain(|key, _| fields.contains(key));
+    }
 
-    serde_json::Value::Object(params)
+    serde_json::Value::Object(params.into_iter().collect())
 }
 
-pub fn openlibrary_book_to_result(record: OpenLibraryBookRecord) -> RsLookupMetadataResultWrapper {
-    let images = build_images(&record);
+pub fn openlibrary_book_to_result(
+    record: OpenLibraryBookRecord,
+    options: &LookupOptions,
+) -> RsLookupMetadataResultWrapper {
+    let images = build_images(&record, options);
     let ext_images = if images.is_empty() {
         None
     } else {
         Some(images)
     };
-    let people_details = build_people_details(&record);
-    let tags_details = build_tags_details(&record);
+    let people_details = build_people_details(&record, options);
+    let tags_details = build_tags_details(&record, options);
 
     let relations = if ext_images.is_some() || people_details.is_some() || tags_details.is_some() {
         Some(Relations {
@@ -289,27 +1043,43 @@ pub fn openlibrary_book_to_result(record: OpenLibraryBookRecord) -> RsLookupMeta
     } else {
         None
     };
-    let params = build_params(&record);
+    let params = build_params(&record, options);
+    let name = if options.strip_parentheticals {
+        split_title_note(&record.title).0
+    } else {
+        record.title.clone()
+    };
 
     let book = Book {
-        id: canonical_rs_id(&record).unwrap_or_else(|| fallback_local_id(&record.title)),
-        name: record.title,
+        id: canonical_rs_id(&record, options.canonical_preference())
+            .unwrap_or_else(|| fallback_local_id(&record.title)),
+        name,
         kind: Some("book".to_string()),
-        serie_ref: None,
-        volume: None,
+        serie_ref: record.series.as_ref().map(|series| series.name.clone()),
+        volume: record.series.as_ref().and_then(|series| series.position),
         chapter: None,
         year: record.publish_year,
         airdate: None,
-        overview: record.description,
-        pages: record.pages,
+        overview: match (record.description, options.max_description_chars) {
+            (Some(description), Some(max_chars)) => {
+                Some(truncate_at_word(&description, max_chars).unwrap_or(description))
+            }
+            (description, _) => description,
+        },
+        pages: record.pages.filter(|pages| *pages <= options.max_pages()),
         params: Some(params),
-        lang: record.language,
+        lang: record.language.or_else(|| options.default_language.clone()),
         original: None,
         isbn13: record.isbn13,
         openlibrary_edition_id: record.edition_id,
         openlibrary_work_id: record.work_id,
         google_books_volume_id: None,
         asin: None,
+        modified: record
+            .last_modified
+            .as_deref()
+            .and_then(parse_datetime_millis)
+            .unwrap_or_default(),
         ..Default::default()
     };
 
@@ -319,13 +1089,23 @@ pub fn openlibrary_book_to_result(record: OpenLibraryBookRecord) -> RsLookupMeta
     }
 }
 
-pub fn openlibrary_book_to_images(record: &OpenLibraryBookRecord) -> Vec<ExternalImage> {
-    build_images(record)
+pub fn openlibrary_book_to_images(
+    record: &OpenLibraryBookRecord,
+    options: &LookupOptions,
+) -> Vec<ExternalImage> {
+    build_images(record, options)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::openlibrary::{
+        book_record_from_edition_response, book_record_from_work_response,
+        enrich_edition_with_work, merge_work_with_edition, OpenLibraryContributor,
+        OpenLibraryEditionResponse, OpenLibraryRating, OpenLibraryTocEntry,
+        OpenLibraryWorkResponse,
+    };
+    use crate::options::{CoverPreference, CoverScheme};
 
     #[test]
     fn prefers_cover_id_for_images() {
@@ -337,7 +1117,7 @@ mod tests {
             ..Default::default()
         };
 
-        let images = openlibrary_book_to_images(&record);
+        let images = openlibrary_book_to_images(&record, &LookupOptions::default());
         assert_eq!(images.len(), 1);
         assert_eq!(
             images[0].url.url,
@@ -345,6 +1125,57 @@ mod tests {
         );
     }
 
+    #[test]
+    fn data_endpoint_cover_maps_to_sized_images() {
+        let cover: OpenLibraryDataCover = serde_json::from_str(
+            r#"{
+                "small": "https://covers.openlibrary.org/b/id/240726-S.jpg",
+                "medium": "https://covers.openlibrary.org/b/id/240726-M.jpg",
+                "large": "https://covers.openlibrary.org/b/id/240726-L.jpg"
+            }"#,
+        )
+        .unwrap();
+
+        let url = |options: &LookupOptions| -> String {
+            let image = data_cover_to_image(&cover, options).expect("Expected an image");
+            assert_eq!(image.kind, Some(ImageType::Poster));
+            image.url.url
+        };
+        assert_eq!(
+            url(&LookupOptions::default()),
+            "https://covers.openlibrary.org/b/id/240726-L.jpg"
+        );
+        assert_eq!(
+            url(&LookupOptions {
+                cover_size: CoverSize::Medium,
+                ..Default::default()
+            }),
+            "https://covers.openlibrary.org/b/id/240726-M.jpg"
+        );
+
+        let large_only = OpenLibraryDataCover {
+            large: Some("https://covers.openlibrary.org/b/id/240726-L.jpg".to_string()),
+            small: Some(" ".to_string()),
+            ..Default::default()
+        };
+        let image = data_cover_to_image(
+            &large_only,
+            &LookupOptions {
+                cover_size: CoverSize::Small,
+                ..Default::default()
+            },
+        )
+        .expect("Expected the large fallback");
+        assert_eq!(
+            image.url.url,
+            "https://covers.openlibrary.org/b/id/240726-L.jpg"
+        );
+        assert!(
+            data_cover_to_image(&OpenLibraryDataCover::default(), &LookupOptions::default())
+                .is_none()
+        );
+    }
+
     #[test]
     fn uses_all_cover_ids_for_images() {
         let record = OpenLibraryBookRecord {
@@ -353,7 +1184,7 @@ mod tests {
             ..Default::default()
         };
 
-        let images = openlibrary_book_to_images(&record);
+        let images = openlibrary_book_to_images(&record, &LookupOptions::default());
         assert_eq!(images.len(), 2);
         assert_eq!(
             images[0].url.url,
@@ -366,29 +1197,1234 @@ mod tests {
     }
 
     #[test]
-    fn maps_record_to_book_metadata() {
+    fn strips_trailing_parentheticals_into_title_note() {
+        assert_eq!(
+            split_title_note("The Hobbit (Middle-earth Universe)"),
+            (
+                "The Hobbit".to_string(),
+                Some("Middle-earth Universe".to_string())
+            )
+        );
+        assert_eq!(
+            split_title_note("Dune (Dune Chronicles) (Book 1)"),
+            (
+                "Dune".to_string(),
+                Some("Dune Chronicles; Book 1".to_string())
+            )
+        );
+    }
+
+    #[test]
+    fn preserves_parentheses_that_belong_to_the_title() {
+        for title in [
+            "The (Mis)adventures of Tom",
+            "Sign(s)",
+            "(Untitled)",
+            "Unbalanced title)",
+        ] {
+            assert_eq!(split_title_note(title), (title.to_string(), None));
+        }
+    }
+
+    #[test]
+    fn strip_parentheticals_is_opt_in() {
         let record = OpenLibraryBookRecord {
-            title: "The Hobbit".to_string(),
-            edition_id: Some("OL7353617M".to_string()),
-            work_id: Some("OL45804W".to_string()),
-            isbn13: Some("9780140328721".to_string()),
-            publish_year: Some(1937),
+            title: "The Hobbit (Middle-earth Universe)".to_string(),
             ..Default::default()
         };
 
-        let result = openlibrary_book_to_result(record);
+        let result = openlibrary_book_to_result(record.clone(), &LookupOptions::default());
+        let RsLookupMetadataResult::Book(book) = result.metadata else {
+            panic!("Expected book metadata");
+        };
+        assert_eq!(book.name, "The Hobbit (Middle-earth Universe)");
+        assert!(book.params.unwrap().get("titleNote").is_none());
 
-        if let RsLookupMetadataResult::Book(book) = result.metadata {
+        let options = LookupOptions {
+            strip_parentheticals: true,
+            ..Default::default()
+        };
+        let result = openlibrary_book_to_result(record, &options);
+        let RsLookupMetadataResult::Book(book) = result.metadata else {
+            panic!("Expected book metadata");
+        };
+        assert_eq!(book.name, "The Hobbit");
+        assert_eq!(
+            book.params.unwrap().get("titleNote"),
+            Some(&json!("Middle-earth Universe"))
+        );
+    }
+
+    #[test]
+    fn emits_primary_cover_size_variants() {
+        let record = OpenLibraryBookRecord {
+            title: "The Hobbit".to_string(),
+            cover_id: Some(12345),
+            cover_ids: vec![12345, 67890],
+            ..Default::default()
+        };
+
+        let result = openlibrary_book_to_result(record, &LookupOptions::default());
+
+        if let RsLookupMetadataResult::Book(book) = result.metadata {
+            let cover = &book.params.expect("Expected params")["cover"];
+            assert_eq!(cover["id"], 12345);
+            assert_eq!(
+                cover["small"],
+                "https://covers.openlibrary.org/b/id/12345-S.jpg"
+            );
+            assert_eq!(
+                cover["medium"],
+                "https://covers.openlibrary.org/b/id/12345-M.jpg"
+            );
+            assert_eq!(
+                cover["large"],
+                "https://covers.openlibrary.org/b/id/12345-L.jpg"
+            );
+        } else {
+            panic!("Expected Book metadata");
+        }
+    }
+
+    #[test]
+    fn cover_size_param_applies_to_every_cover_url() {
+        let options = |size: &str| {
+            LookupOptions::from_params(Some(&HashMap::from([(
+                "cover_size".to_string(),
+                size.to_string(),
+            )])))
+        };
+        let with_ids = OpenLibraryBookRecord {
+            title: "The Hobbit".to_string(),
+            cover_ids: vec![10, 20],
+            ..Default::default()
+        };
+        let with_olid = OpenLibraryBookRecord {
+            title: "The Hobbit".to_string(),
+            edition_id: Some("OL7353617M".to_string()),
+            ..Default::default()
+        };
+        let urls = |record: &OpenLibraryBookRecord, options: &LookupOptions| -> Vec<String> {
+            openlibrary_book_to_images(record, options)
+                .into_iter()
+                .map(|image| image.url.url)
+                .collect()
+        };
+
+        assert_eq!(
+            urls(&with_ids, &options("S")),
+            vec![
+                "https://covers.openlibrary.org/b/id/10-S.jpg",
+                "https://covers.openlibrary.org/b/id/20-S.jpg",
+            ]
+        );
+        assert_eq!(
+            urls(&with_olid, &options("m")),
+            vec!["https://covers.openlibrary.org/b/olid/OL7353617M-M.jpg"]
+        );
+        assert_eq!(
+            urls(&with_olid, &options("XL")),
+            vec!["https://covers.openlibrary.org/b/olid/OL7353617M-L.jpg"]
+        );
+    }
+
+    #[test]
+    fn cover_index_selects_one_sized_cover() {
+        let record = OpenLibraryBookRecord {
+            title: "The Hobbit".to_string(),
+            cover_id: Some(10),
+            cover_ids: vec![10, 20, 30],
+            ..Default::default()
+        };
+        let options = LookupOptions {
+            cover_index: Some(1),
+            cover_size: CoverSize::Medium,
+            ..Default::default()
+        };
+
+        let images = openlibrary_book_to_images(&record, &options);
+        assert_eq!(images.len(), 1);
+        assert_eq!(
+            images[0].url.url,
+            "https://covers.openlibrary.org/b/id/20-M.jpg"
+        );
+
+        let options = LookupOptions {
+            cover_index: Some(9),
+            ..Default::default()
+        };
+        let images = openlibrary_book_to_images(&record, &options);
+        assert_eq!(images.len(), 1);
+        assert_eq!(
+            images[0].url.url,
+            "https://covers.openlibrary.org/b/id/10-L.jpg"
+        );
+    }
+
+    #[test]
+    fn no_cover_marker_skips_identifier_fallback() {
+        let response: OpenLibraryEditionResponse = serde_json::from_value(json!({
+            "key": "/books/OL7353617M",
+            "title": "The Hobbit",
+            "covers": [-1]
+        }))
+        .expect("Expected valid edition json");
+        let record = book_record_from_edition_response(&response);
+
+        assert!(record.no_cover);
+        assert!(openlibrary_book_to_images(&record, &LookupOptions::default()).is_empty());
+    }
+
+    #[test]
+    fn skips_identifier_cover_fallback_when_disabled() {
+        let record = OpenLibraryBookRecord {
+            title: "The Hobbit".to_string(),
+            edition_id: Some("OL7353617M".to_string()),
+            lccn: Some("93005405".to_string()),
+            ..Default::default()
+        };
+        let options = LookupOptions {
+            olid_cover_fallback: Some(false),
+            ..Default::default()
+        };
+
+        assert!(openlibrary_book_to_images(&record, &options).is_empty());
+        assert_eq!(
+            openlibrary_book_to_images(&record, &LookupOptions::default()).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn default_language_fills_missing_language_only() {
+        let options = LookupOptions {
+            default_language: Some("fre".to_string()),
+            ..Default::default()
+        };
+        let cases = [(None, Some("fre")), (Some("eng"), Some("eng"))];
+        for (language, expected) in cases {
+            let record = OpenLibraryBookRecord {
+                title: "Le Hobbit".to_string(),
+                language: language.map(str::to_string),
+                ..Default::default()
+            };
+
+            let result = openlibrary_book_to_result(record, &options);
+
+            if let RsLookupMetadataResult::Book(book) = result.metadata {
+                assert_eq!(book.lang.as_deref(), expected);
+            } else {
+                panic!("Expected Book metadata");
+            }
+        }
+
+        let record = OpenLibraryBookRecord {
+            title: "The Hobbit".to_string(),
+            ..Default::default()
+        };
+        let result = openlibrary_book_to_result(record, &LookupOptions::default());
+        if let RsLookupMetadataResult::Book(book) = result.metadata {
+            assert_eq!(book.lang, None);
+        } else {
+            panic!("Expected Book metadata");
+        }
+    }
+
+    #[test]
+    fn builds_citation_and_skips_missing_segments() {
+        let mut record = OpenLibraryBookRecord {
+            title: "The Hobbit".to_string(),
+            authors: vec!["J.R.R. Tolkien".to_string()],
+            publishers: vec!["Allen & Unwin".to_string()],
+            publish_year: Some(1937),
+            ..Default::default()
+        };
+        assert_eq!(
+            build_citation(&record),
+            "Tolkien, J.R.R. The Hobbit. Allen & Unwin, 1937."
+        );
+
+        record.publishers.clear();
+        assert_eq!(build_citation(&record), "Tolkien, J.R.R. The Hobbit. 1937.");
+
+        record.authors.push("Christopher Tolkien".to_string());
+        record.publish_year = None;
+        assert_eq!(
+            build_citation(&record),
+            "Tolkien, J.R.R., et al. The Hobbit."
+        );
+
+        let result = openlibrary_book_to_result(record, &LookupOptions::default());
+        if let RsLookupMetadataResult::Book(book) = result.metadata {
+            assert_eq!(
+                book.params.expect("Expected params")["citation"],
+                "Tolkien, J.R.R., et al. The Hobbit."
+            );
+        } else {
+            panic!("Expected Book metadata");
+        }
+    }
+
+    #[test]
+    fn emits_signed_year_for_historical_dates() {
+        let cases = [
+            ("45 BC", None, Some((-45, "BCE"))),
+            ("800", Some(800), Some((800, "CE"))),
+            ("1937", Some(1937), None),
+        ];
+        for (publish_date, year, signed) in cases {
+            let response: OpenLibraryEditionResponse = serde_json::from_value(json!({
+                "key": "/books/OL1M",
+                "title": "Commentarii",
+                "publish_date": publish_date
+            }))
+            .expect("Expected valid edition json");
+
+            let result = openlibrary_book_to_result(
+                book_record_from_edition_response(&response),
+                &LookupOptions::default(),
+            );
+            let RsLookupMetadataResult::Book(book) = result.metadata else {
+                panic!("Expected Book metadata");
+            };
+            assert_eq!(book.year, year);
+            let params = book.params.expect("Expected params");
+            match signed {
+                Some((signed_year, era)) => {
+                    assert_eq!(params["signedYear"], signed_year);
+                    assert_eq!(params["era"], era);
+                }
+                None => assert!(params.get("signedYear").is_none()),
+            }
+        }
+    }
+
+    #[test]
+    fn emits_raw_publish_date_unchanged() {
+        let response: OpenLibraryEditionResponse = serde_json::from_value(json!({
+            "key": "/books/OL7353617M",
+            "title": "The Hobbit",
+            "publish_date": "First published 1937, reprinted 1966"
+        }))
+        .expect("Expected valid edition json");
+
+        let result = openlibrary_book_to_result(
+            book_record_from_edition_response(&response),
+            &LookupOptions::default(),
+        );
+        if let RsLookupMetadataResult::Book(book) = result.metadata {
+            assert_eq!(
+                book.params.expect("Expected params")["publishDateRaw"],
+                "First published 1937, reprinted 1966"
+            );
+        } else {
+            panic!("Expected Book metadata");
+        }
+    }
+
+    #[test]
+    fn emits_first_publish_year_beside_edition_year() {
+        let record = OpenLibraryBookRecord {
+            title: "The Hobbit".to_string(),
+            publish_year: Some(1966),
+            first_publish_year: Some(1937),
+            ..Default::default()
+        };
+
+        let result = openlibrary_book_to_result(record, &LookupOptions::default());
+        let RsLookupMetadataResult::Book(book) = result.metadata else {
+            panic!("Expected Book metadata");
+        };
+        assert_eq!(book.year, Some(1966));
+        assert_eq!(
+            book.params.expect("Expected params")["firstPublishYear"],
+            1937
+        );
+    }
+
+    #[test]
+    fn structured_work_series_maps_to_serie_and_volume() {
+        let work: OpenLibraryWorkResponse = serde_json::from_value(json!({
+            "key": "/works/OL82563W",
+            "title": "Harry Potter and the Chamber of Secrets",
+            "series": [
+                "Harry Potter series ; 7",
+                {"series": {"key": "/series/OL1L", "name": " Harry Potter "}, "position": "2"}
+            ]
+        }))
+        .expect("Expected valid work json");
+
+        let result = openlibrary_book_to_result(
+            book_record_from_work_response(&work),
+            &LookupOptions::default(),
+        );
+        let RsLookupMetadataResult::Book(book) = result.metadata else {
+            panic!("Expected Book metadata");
+        };
+        assert_eq!(book.serie_ref.as_deref(), Some("Harry Potter"));
+        assert_eq!(book.volume, Some(2.0));
+        let params = book.params.expect("Expected params");
+        assert_eq!(params["series"], "Harry Potter");
+        assert_eq!(params["seriesPosition"], 2.0);
+
+        let numbered: OpenLibraryWorkResponse = serde_json::from_value(json!({
+            "key": "/works/OL1W",
+            "title": "Mort",
+            "series": [{"name": "Discworld", "position": 4}, {"series": {"key": "/series/OL2L"}}]
+        }))
+        .expect("Expected valid work json");
+        let record = book_record_from_work_response(&numbered);
+        let series = record.series.expect("Expected a series");
+        assert_eq!(series.name, "Discworld");
+        assert_eq!(series.position, Some(4.0));
+
+        let malformed: OpenLibraryWorkResponse = serde_json::from_value(json!({
+            "key": "/works/OL1W",
+            "title": "Mort",
+            "series": [null, 42, {"name": ["Discworld"]}, {"name": "Discworld", "position": 4}]
+        }))
+        .expect("Expected bad series entries to be skipped");
+        assert_eq!(malformed.series.len(), 1);
+        let not_a_list: OpenLibraryWorkResponse = serde_json::from_value(json!({
+            "key": "/works/OL1W",
+            "title": "Mort",
+            "series": null
+        }))
+        .expect("Expected a null series to be skipped");
+        assert!(not_a_list.series.is_empty());
+
+        let edition = OpenLibraryBookRecord {
+            title: "Mort".to_string(),
+            edition_id: Some("OL1M".to_string()),
+            ..Default::default()
+        };
+        let enriched = enrich_edition_with_work(edition, &numbered);
+        assert_eq!(
+            enriched.series.map(|series| series.name).as_deref(),
+            Some("Discworld")
+        );
+    }
+
+    #[test]
+    fn edition_count_param_is_omitted_when_unknown() {
+        let edition_count = |edition_count: Option<u64>| {
+            let record = OpenLibraryBookRecord {
+                title: "The Hobbit".to_string(),
+                edition_count,
+                ..Default::default()
+            };
+            let result = openlibrary_book_to_result(record, &LookupOptions::default());
+            let RsLookupMetadataResult::Book(book) = result.metadata else {
+                panic!("Expected Book metadata");
+            };
+            book.params
+                .expect("Expected params")
+                .get("editionCount")
+                .cloned()
+        };
+        assert_eq!(edition_count(Some(12)), Some(json!(12)));
+        assert_eq!(edition_count(None), None);
+    }
+
+    #[test]
+    fn emits_trimmed_pagination_beside_pages() {
+        let pagination = |value: serde_json::Value| {
+            let response: OpenLibraryEditionResponse = serde_json::from_value(json!({
+                "key": "/books/OL7353617M",
+                "title": "The Hobbit",
+                "number_of_pages": 310,
+                "pagination": value
+            }))
+            .expect("Expected valid edition json");
+
+            let result = openlibrary_book_to_result(
+                book_record_from_edition_response(&response),
+                &LookupOptions::default(),
+            );
+            let RsLookupMetadataResult::Book(book) = result.metadata else {
+                panic!("Expected Book metadata");
+            };
+            assert_eq!(book.pages, Some(310));
+            book.params
+                .expect("Expected params")
+                .get("pagination")
+                .cloned()
+        };
+
+        assert_eq!(
+            pagination(json!(" xii, 310 p. ")),
+            Some(json!("xii, 310 p."))
+        );
+        assert_eq!(pagination(json!("  ")), None);
+        assert_eq!(pagination(serde_json::Value::Null), None);
+    }
+
+    #[test]
+    fn links_editions_to_their_parent_work() {
+        let options = LookupOptions {
+            include_work_link_for_editions: true,
+            ..Default::default()
+        };
+        let response: OpenLibraryEditionResponse = serde_json::from_value(json!({
+            "key": "/books/OL7353617M",
+            "title": "The Hobbit",
+            "works": [{"key": "/works/OL262758W"}]
+        }))
+        .expect("Expected valid edition json");
+
+        let result =
+            openlibrary_book_to_result(book_record_from_edition_response(&response), &options);
+        if let RsLookupMetadataResult::Book(book) = result.metadata {
+            let params = book.params.expect("Expected params");
+            assert_eq!(params["parentWorkId"], "OL262758W");
+            assert_eq!(
+                params["parentWorkUrl"],
+                "https://openlibrary.org/works/OL262758W"
+            );
+        } else {
+            panic!("Expected Book metadata");
+        }
+
+        let unlinked = OpenLibraryBookRecord {
+            title: "The Hobbit".to_string(),
+            edition_id: Some("OL7353617M".to_string()),
+            ..Default::default()
+        };
+        let result = openlibrary_book_to_result(unlinked, &options);
+        if let RsLookupMetadataResult::Book(book) = result.metadata {
+            assert!(book
+                .params
+                .expect("Expected params")
+                .get("parentWorkId")
+                .is_none());
+        } else {
+            panic!("Expected Book metadata");
+        }
+    }
+
+    #[test]
+    fn params_keys_are_sorted() {
+        let record = OpenLibraryBookRecord {
+            title: "The Hobbit".to_string(),
+            work_id: Some("OL45804W".to_string()),
+            edition_id: Some("OL7353617M".to_string()),
+            authors: vec!["J.R.R. Tolkien".to_string()],
+            confidence: Some(1.0),
+            ..Default::default()
+        };
+
+        let result = openlibrary_book_to_result(record, &LookupOptions::default());
+
+        if let RsLookupMetadataResult::Book(book) = result.metadata {
+            let params = serde_json::to_string(&book.params.expect("Expected params"))
+                .expect("Expected serializable params");
+            assert_eq!(
+                params,
+                r#"{"authors":["J.R.R. Tolkien"],"citation":"Tolkien, J.R.R. The Hobbit.","confidence":1.0,"contentHash":"8c15cb2a6a3d6ed8","coverCount":1,"openlibraryEditionId":"OL7353617M","openlibraryWorkId":"OL45804W","primaryAuthor":"J.R.R. Tolkien","slug":"the-hobbit-j-r-r-tolkien"}"#
+            );
+        } else {
+            panic!("Expected Book metadata");
+        }
+    }
+
+    #[test]
+    fn content_hash_is_stable_and_tracks_title() {
+        let record = OpenLibraryBookRecord {
+            title: "The Hobbit".to_string(),
+            edition_id: Some("OL7353617M".to_string()),
+            publish_year: Some(1937),
+            cover_id: Some(42),
+            ..Default::default()
+        };
+        let same = OpenLibraryBookRecord {
+            title: "  the   HOBBIT ".to_string(),
+            confidence: Some(0.5),
+            ..record.clone()
+        };
+        let retitled = OpenLibraryBookRecord {
+            title: "The Silmarillion".to_string(),
+            ..record.clone()
+        };
+
+        let hash = build_content_hash(&record, CanonicalPreference::Isbn);
+        assert_eq!(hash.len(), 16);
+        assert_eq!(hash, build_content_hash(&record, CanonicalPreference::Isbn));
+        assert_eq!(hash, build_content_hash(&same, CanonicalPreference::Isbn));
+        assert_ne!(
+            hash,
+            build_content_hash(&retitled, CanonicalPreference::Isbn)
+        );
+    }
+
+    #[test]
+    fn pairs_publishers_with_places_when_lengths_match() {
+        let matched = OpenLibraryBookRecord {
+            title: "The Hobbit".to_string(),
+            publishers: vec![
+                "George Allen & Unwin".to_string(),
+                "Houghton Mifflin".to_string(),
+            ],
+            publish_places: vec!["London".to_string(), "Boston".to_string()],
+            ..Default::default()
+        };
+        let params = build_params(&matched, &LookupOptions::default());
+        assert_eq!(
+            params["publishersDetailed"],
+            json!([
+                {"name": "George Allen & Unwin", "place": "London"},
+                {"name": "Houghton Mifflin", "place": "Boston"}
+            ])
+        );
+        assert!(params.get("publishPlaces").is_none());
+
+        let mismatched = OpenLibraryBookRecord {
+            publish_places: vec!["London".to_string()],
+            ..matched
+        };
+        let params = build_params(&mismatched, &LookupOptions::default());
+        assert!(params.get("publishersDetailed").is_none());
+        assert_eq!(params["publishers"].as_array().map(Vec::len), Some(2));
+        assert_eq!(params["publishPlaces"], json!(["London"]));
+    }
+
+    #[test]
+    fn emits_read_or_borrow_url_by_access() {
+        let cases = [
+            (
+                Some(EbookAccess::Public),
+                Some("https://archive.org/details/hobbit00tolk"),
+                None,
+            ),
+            (
+                Some(EbookAccess::Borrowable),
+                None,
+                Some("https://openlibrary.org/borrow/ia/hobbit00tolk"),
+            ),
+            (Some(EbookAccess::PrintDisabled), None, None),
+            (Some(EbookAccess::NoEbook), None, None),
+            (None, None, None),
+        ];
+        for (ebook_access, read_url, borrow_url) in cases {
+            let record = OpenLibraryBookRecord {
+                title: "The Hobbit".to_string(),
+                ocaid: Some("hobbit00tolk".to_string()),
+                ebook_access,
+                ..Default::default()
+            };
+            let params = build_params(&record, &LookupOptions::default());
+            assert_eq!(params.get("readUrl").and_then(|url| url.as_str()), read_url);
+            assert_eq!(
+                params.get("borrowUrl").and_then(|url| url.as_str()),
+                borrow_url
+            );
+        }
+    }
+
+    #[test]
+    fn cover_scheme_applies_to_cover_urls() {
+        let record = OpenLibraryBookRecord {
+            title: "The Hobbit".to_string(),
+            cover_id: Some(42),
+            ..Default::default()
+        };
+        let options = LookupOptions {
+            cover_scheme: CoverScheme::Http,
+            ..Default::default()
+        };
+
+        let images = openlibrary_book_to_images(&record, &options);
+        assert_eq!(
+            images[0].url.url,
+            "http://covers.openlibrary.org/b/id/42-L.jpg"
+        );
+        let params = build_params(&record, &options);
+        assert_eq!(
+            params["cover"]["small"],
+            "http://covers.openlibrary.org/b/id/42-S.jpg"
+        );
+
+        let fallback = OpenLibraryBookRecord {
+            title: "The Hobbit".to_string(),
+            edition_id: Some("OL7353617M".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            openlibrary_book_to_images(&fallback, &options)[0].url.url,
+            "http://covers.openlibrary.org/b/olid/OL7353617M-L.jpg"
+        );
+        assert_eq!(
+            openlibrary_book_to_images(&record, &LookupOptions::default())[0]
+                .url
+                .url,
+            "https://covers.openlibrary.org/b/id/42-L.jpg"
+        );
+    }
+
+    #[test]
+    fn relations_are_capped_by_max_subjects_and_max_people() {
+        let record = OpenLibraryBookRecord {
+            title: "Good Omens".to_string(),
+            authors: vec![
+                "Terry Pratchett".to_string(),
+                "Neil Gaiman".to_string(),
+                "Terry Pratchett".to_string(),
+            ],
+            contributors: vec![OpenLibraryContributor {
+                name: "Stephen Briggs".to_string(),
+                role: "Narrator".to_string(),
+            }],
+            subjects: vec![
+                "Fantasy".to_string(),
+                " fantasy ".to_string(),
+                "Humor".to_string(),
+                "Apocalypse".to_string(),
+            ],
+            ..Default::default()
+        };
+
+        let uncapped = LookupOptions::default();
+        assert_eq!(build_people_details(&record, &uncapped).unwrap().len(), 3);
+        assert_eq!(build_tags_details(&record, &uncapped).unwrap().len(), 3);
+
+        let options = LookupOptions {
+            max_people: Some(2),
+            max_subjects: Some(2),
+            ..Default::default()
+        };
+        let people = build_people_details(&record, &options).unwrap();
+        assert_eq!(
+            people
+                .iter()
+                .map(|person| person.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["Terry Pratchett", "Neil Gaiman"]
+        );
+        let tags = build_tags_details(&record, &options).unwrap();
+        assert_eq!(
+            tags.iter().map(|tag| tag.name.as_str()).collect::<Vec<_>>(),
+            vec!["Fantasy", "Humor"]
+        );
+
+        let none = LookupOptions {
+            max_people: Some(0),
+            max_subjects: Some(0),
+            ..Default::default()
+        };
+        assert!(build_people_details(&record, &none).is_none());
+        assert!(build_tags_details(&record, &none).is_none());
+    }
+
+    #[test]
+    fn classifies_organization_names() {
+        for name in [
+            "United States. Congress.",
+            "United States. Dept. of Agriculture",
+            "Oxford University Press",
+            "Marvel Comics Inc.",
+        ] {
+            assert!(is_organization_name(name), "{name}");
+        }
+        for name in [
+            "J.R.R. Tolkien",
+            "J. R. R. Tolkien.",
+            "Tolkien, J. R. R.",
+            "Martin Luther King Jr.",
+            "Ursula K. Le Guin",
+        ] {
+            assert!(!is_organization_name(name), "{name}");
+        }
+
+        let record = OpenLibraryBookRecord {
+            title: "Congressional Record".to_string(),
+            authors: vec![
+                "United States. Congress.".to_string(),
+                "Jane Doe".to_string(),
+            ],
+            ..Default::default()
+        };
+        let kinds = |options: &LookupOptions| -> Vec<Option<String>> {
+            build_people_details(&record, options)
+                .unwrap()
+                .into_iter()
+                .map(|person| person.kind)
+                .collect()
+        };
+        assert_eq!(
+            kinds(&LookupOptions::default()),
+            vec![Some("author".to_string()), Some("author".to_string())]
+        );
+        let options = LookupOptions {
+            exclude_org_authors: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            kinds(&options),
+            vec![Some("organization".to_string()), Some("author".to_string())]
+        );
+    }
+
+    #[test]
+    fn emits_cover_count() {
+        let cases = [
+            (vec![1, 2, 2, 3], None, 3),
+            (vec![], Some("OL7353617M"), 1),
+            (vec![], None, 0),
+        ];
+        for (cover_ids, edition_id, expected) in cases {
+            let record = OpenLibraryBookRecord {
+                title: "The Hobbit".to_string(),
+                cover_ids,
+                edition_id: edition_id.map(str::to_string),
+                ..Default::default()
+            };
+
+            let result = openlibrary_book_to_result(record, &LookupOptions::default());
+
+            if let RsLookupMetadataResult::Book(book) = result.metadata {
+                assert_eq!(
+                    book.params.expect("Expected params")["coverCount"],
+                    expected
+                );
+            } else {
+                panic!("Expected Book metadata");
+            }
+        }
+
+        let record = OpenLibraryBookRecord {
+            title: "The Hobbit".to_string(),
+            cover_ids: vec![1, 2, 3],
+            ..Default::default()
+        };
+        let options = LookupOptions {
+            cover_index: Some(1),
+            ..Default::default()
+        };
+        let RsLookupMetadataResult::Book(book) =
+            openlibrary_book_to_result(record, &options).metadata
+        else {
+            panic!("Expected Book metadata");
+        };
+        assert_eq!(book.params.expect("Expected params")["coverCount"], 3);
+    }
+
+    #[test]
+    fn falls_back_to_lccn_then_oclc_covers() {
+        let record = OpenLibraryBookRecord {
+            title: "Catalogued".to_string(),
+            lccn: Some("93005405".to_string()),
+            oclc: Some("28419896".to_string()),
+            ..Default::default()
+        };
+        let images = openlibrary_book_to_images(&record, &LookupOptions::default());
+        assert_eq!(images.len(), 1);
+        assert_eq!(
+            images[0].url.url,
+            "https://covers.openlibrary.org/b/lccn/93005405-L.jpg"
+        );
+
+        let record = OpenLibraryBookRecord {
+            title: "Catalogued".to_string(),
+            oclc: Some("28419896".to_string()),
+            ..Default::default()
+        };
+        let images = openlibrary_book_to_images(&record, &LookupOptions::default());
+        assert_eq!(
+            images[0].url.url,
+            "https://covers.openlibrary.org/b/oclc/28419896-L.jpg"
+        );
+    }
+
+    #[test]
+    fn maps_record_to_book_metadata() {
+        let record = OpenLibraryBookRecord {
+            title: "The Hobbit".to_string(),
+            edition_id: Some("OL7353617M".to_string()),
+            work_id: Some("OL45804W".to_string()),
+            isbn13: Some("9780140328721".to_string()),
+            publish_year: Some(1937),
+            ..Default::default()
+        };
+
+        let result = openlibrary_book_to_result(record, &LookupOptions::default());
+
+        if let RsLookupMetadataResult::Book(book) = result.metadata {
             assert_eq!(book.id, "isbn13:9780140328721".to_string());
             assert_eq!(book.name, "The Hobbit");
             assert_eq!(book.kind, Some("book".to_string()));
             assert_eq!(book.year, Some(1937));
             assert_eq!(book.openlibrary_work_id, Some("OL45804W".to_string()));
+            assert_eq!(book.isbn13, Some("9780140328721".to_string()));
+            assert_eq!(
+                book.params.expect("Expected params")["isbn13Display"],
+                "978-0-14-032872-1"
+            );
+        } else {
+            panic!("Expected Book metadata");
+        }
+    }
+
+    #[test]
+    fn maps_last_modified_to_params_and_modified() {
+        let record = OpenLibraryBookRecord {
+            title: "The Hobbit".to_string(),
+            last_modified: Some("2021-10-04T04:05:33.147982".to_string()),
+            revision: Some(14),
+            ..Default::default()
+        };
+
+        let result = openlibrary_book_to_result(record, &LookupOptions::default());
+
+        if let RsLookupMetadataResult::Book(book) = result.metadata {
+            assert_eq!(book.modified, 1_633_320_333_147);
+            let params = book.params.expect("Expected params");
+            assert_eq!(params["lastModified"], "2021-10-04T04:05:33.147982");
+            assert_eq!(params["revision"], 14);
+        } else {
+            panic!("Expected Book metadata");
+        }
+    }
+
+    #[test]
+    fn isbn_only_canonical_keys_on_isbn_over_work() {
+        let record = OpenLibraryBookRecord {
+            title: "The Hobbit".to_string(),
+            work_id: Some("OL45804W".to_string()),
+            isbn13: Some("9780140328721".to_string()),
+            ..Default::default()
+        };
+        let book = |record: OpenLibraryBookRecord, options: &LookupOptions| {
+            match openlibrary_book_to_result(record, options).metadata {
+                RsLookupMetadataResult::Book(book) => book,
+                _ => panic!("Expected Book metadata"),
+            }
+        };
+
+        let flagged = LookupOptions {
+            canonical_preference: CanonicalPreference::Work,
+            isbn_only_canonical: IsbnOnlyCanonical::Flag,
+            ..Default::default()
+        };
+        assert_eq!(book(record.clone(), &flagged).id, "isbn13:9780140328721");
+        assert_eq!(
+            book(
+                record.clone(),
+                &LookupOptions {
+                    canonical_preference: CanonicalPreference::Work,
+                    ..Default::default()
+                }
+            )
+            .id,
+            "olwid:OL45804W"
+        );
+
+        let without_isbn = OpenLibraryBookRecord {
+            isbn13: None,
+            ..record
+        };
+        let params = book(without_isbn, &flagged)
+            .params
+            .expect("Expected params");
+        assert_eq!(params["missingIsbn"], true);
+    }
+
+    #[test]
+    fn canonical_preference_reorders_id_chain() {
+        let record = OpenLibraryBookRecord {
+            title: "The Hobbit".to_string(),
+            edition_id: Some("OL7353617M".to_string()),
+            work_id: Some("OL45804W".to_string()),
+            isbn13: Some("9780140328721".to_string()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            canonical_rs_id(&record, CanonicalPreference::Isbn),
+            Some("isbn13:9780140328721".to_string())
+        );
+        assert_eq!(
+            canonical_rs_id(&record, CanonicalPreference::Edition),
+            Some("oleid:OL7353617M".to_string())
+        );
+        assert_eq!(
+            canonical_rs_id(&record, CanonicalPreference::Work),
+            Some("olwid:OL45804W".to_string())
+        );
+
+        let without_work = OpenLibraryBookRecord {
+            work_id: None,
+            ..record
+        };
+        assert_eq!(
+            canonical_rs_id(&without_work, CanonicalPreference::Work),
+            Some("isbn13:9780140328721".to_string())
+        );
+    }
+
+    #[test]
+    fn result_fields_restricts_emitted_params() {
+        let record = OpenLibraryBookRecord {
+            title: "The Hobbit".to_string(),
+            work_id: Some("OL45804W".to_string()),
+            authors: vec!["J.R.R. Tolkien".to_string()],
+            subjects: vec!["Fantasy".to_string()],
+            ..Default::default()
+        };
+        let options = LookupOptions {
+            result_fields: Some(vec![
+                "authors".to_string(),
+                "openlibraryWorkId".to_string(),
+                "unknownKey".to_string(),
+            ]),
+            ..Default::default()
+        };
+
+        let result = openlibrary_book_to_result(record, &options);
+
+        if let RsLookupMetadataResult::Book(book) = result.metadata {
+            let params = book.params.expect("Expected params");
+            let keys: Vec<&String> = params.as_object().unwrap().keys().collect();
+            assert_eq!(keys, vec!["authors", "openlibraryWorkId"]);
+        } else {
+            panic!("Expected Book metadata");
+        }
+    }
+
+    #[test]
+    fn subtitle_prefers_the_edition_and_is_emitted() {
+        let work: OpenLibraryWorkResponse = serde_json::from_value(json!({
+            "key": "/works/OL893415W",
+            "title": "Dune",
+            "subtitle": "A Novel"
+        }))
+        .expect("Expected valid work json");
+        let edition: OpenLibraryEditionResponse = serde_json::from_value(json!({
+            "key": "/books/OL26242482M",
+            "title": "Dune",
+            "subtitle": " The Graphic Novel "
+        }))
+        .expect("Expected valid edition json");
+        let untitled: OpenLibraryEditionResponse = serde_json::from_value(json!({
+            "key": "/books/OL1M",
+            "title": "Dune",
+            "subtitle": ""
+        }))
+        .expect("Expected valid edition json");
+
+        let subtitle = |edition: &OpenLibraryEditionResponse| {
+            let merged = merge_work_with_edition(
+                book_record_from_work_response(&work),
+                Some(book_record_from_edition_response(edition)),
+                CoverPreference::Work,
+            );
+            let result = openlibrary_book_to_result(merged, &LookupOptions::default());
+            let RsLookupMetadataResult::Book(book) = result.metadata else {
+                panic!("Expected Book metadata");
+            };
+            assert_eq!(book.name, "Dune");
+            book.params.expect("Expected params")["subtitle"].clone()
+        };
+        assert_eq!(subtitle(&edition), "The Graphic Novel");
+        assert_eq!(subtitle(&untitled), "A Novel");
+    }
+
+    #[test]
+    fn primary_author_is_the_first_work_author() {
+        let work = OpenLibraryBookRecord {
+            title: "The Hobbit".to_string(),
+            work_id: Some("OL45804W".to_string()),
+            authors: vec!["Tolkien, J.R.R.".to_string(), "Alan Lee".to_string()],
+            ..Default::default()
+        };
+        let edition = OpenLibraryBookRecord {
+            title: "The Hobbit".to_string(),
+            edition_id: Some("OL7353617M".to_string()),
+            authors: vec!["Douglas A. Anderson".to_string()],
+            ..Default::default()
+        };
+        let merged = merge_work_with_edition(work, Some(edition), CoverPreference::Work);
+
+        let primary = |options: &LookupOptions| {
+            let result = openlibrary_book_to_result(merged.clone(), options);
+            let RsLookupMetadataResult::Book(book) = result.metadata else {
+                panic!("Expected Book metadata");
+            };
+            let params = book.params.expect("Expected params");
+            assert_eq!(params["authors"], json!(["Tolkien, J.R.R.", "Alan Lee"]));
+            params["primaryAuthor"].clone()
+        };
+        assert_eq!(primary(&LookupOptions::default()), "Tolkien, J.R.R.");
+        assert_eq!(
+            primary(&LookupOptions {
+                normalize_authors: true,
+                ..Default::default()
+            }),
+            "J. R. R. Tolkien"
+        );
+    }
+
+    #[test]
+    fn normalizes_last_first_and_initials() {
+        assert_eq!(normalize_author_name("Tolkien, J.R.R."), "J. R. R. Tolkien");
+        assert_eq!(normalize_author_name("J.R.R.  Tolkien"), "J. R. R. Tolkien");
+        assert_eq!(
+            normalize_author_name("Le Guin, Ursula K."),
+            "Ursula K. Le Guin"
+        );
+        assert_eq!(normalize_author_name("Homer"), "Homer");
+        assert_eq!(normalize_author_name("Plato,"), "Plato,");
+        assert_eq!(
+            normalize_author_name("Martin Luther King, Jr."),
+            "Martin Luther King, Jr."
+        );
+        assert_eq!(normalize_author_name("Ph.D. Smith"), "Ph.D. Smith");
+    }
+
+    #[test]
+    fn normalize_authors_keeps_raw_name_in_params() {
+        let record = OpenLibraryBookRecord {
+            title: "The Hobbit".to_string(),
+            authors: vec!["Tolkien, J.R.R.".to_string(), "Alan Lee".to_string()],
+            ..Default::default()
+        };
+        let options = LookupOptions {
+            normalize_authors: true,
+            ..Default::default()
+        };
+
+        let people = build_people_details(&record, &options).expect("Expected people");
+        assert_eq!(people[0].name, "J. R. R. Tolkien");
+        assert_eq!(
+            people[0].params.as_ref().unwrap()["rawName"],
+            "Tolkien, J.R.R."
+        );
+        assert_eq!(people[1].name, "Alan Lee");
+        assert!(people[1].params.is_none());
+
+        let people =
+            build_people_details(&record, &LookupOptions::default()).expect("Expected people");
+        assert_eq!(people[0].name, "Tolkien, J.R.R.");
+    }
+
+    #[test]
+    fn tags_use_canonical_subject_keys_when_present() {
+        let record = OpenLibraryBookRecord {
+            title: "The Hobbit".to_string(),
+            subjects: vec!["Fantasy fiction".to_string(), "Dragons".to_string()],
+            subject_keys: vec!["fantasy_fiction".to_string()],
+            ..Default::default()
+        };
+
+        let tags = build_tags_details(&record, &LookupOptions::default()).expect("Expected tags");
+        assert_eq!(tags[0].id, "openlib-tag:fantasy_fiction");
+        assert_eq!(
+            tags[0].params.as_ref().unwrap()["openlibraryTagKey"],
+            "fantasy_fiction"
+        );
+        assert_eq!(tags[1].id, "openlib-tag:dragons");
+    }
+
+    #[test]
+    fn tags_carry_subject_work_counts() {
+        let record = OpenLibraryBookRecord {
+            title: "The Hobbit".to_string(),
+            subjects: vec!["Fantasy fiction".to_string(), "Dragons".to_string()],
+            subject_work_counts: [("fantasy_fiction".to_string(), 12_345)].into(),
+            ..Default::default()
+        };
+
+        let tags = build_tags_details(&record, &LookupOptions::default()).expect("Expected tags");
+        assert_eq!(tags[0].params.as_ref().unwrap()["subjectWorkCount"], 12_345);
+        assert!(tags[1]
+            .params
+            .as_ref()
+            .unwrap()
+            .get("subjectWorkCount")
+            .is_none());
+    }
+
+    #[test]
+    fn truncates_description_at_word_boundary() {
+        assert_eq!(
+            truncate_at_word("In a hole in the ground there lived a hobbit.", 20),
+            Some("In a hole in the…".to_string())
+        );
+        assert_eq!(
+            truncate_at_word("In a hole, in the ground", 10),
+            Some("In a hole…".to_string())
+        );
+        assert_eq!(
+            truncate_at_word("Supercalifragilistic", 5),
+            Some("Super…".to_string())
+        );
+        assert_eq!(truncate_at_word("Short enough", 12), None);
+    }
+
+    #[test]
+    fn max_description_chars_keeps_full_text_when_requested() {
+        let record = OpenLibraryBookRecord {
+            title: "The Hobbit".to_string(),
+            description: Some("In a hole in the ground there lived a hobbit.".to_string()),
+            ..Default::default()
+        };
+
+        let result = openlibrary_book_to_result(record.clone(), &LookupOptions::default());
+        if let RsLookupMetadataResult::Book(book) = result.metadata {
+            assert_eq!(
+                book.overview.as_deref(),
+                Some("In a hole in the ground there lived a hobbit.")
+            );
+        } else {
+            panic!("Expected Book metadata");
+        }
+
+        let options = LookupOptions {
+            max_description_chars: Some(20),
+            keep_full_description: true,
+            ..Default::default()
+        };
+        let result = openlibrary_book_to_result(record, &options);
+        if let RsLookupMetadataResult::Book(book) = result.metadata {
+            assert_eq!(book.overview.as_deref(), Some("In a hole in the…"));
+            assert_eq!(
+                book.params.expect("Expected params")["descriptionFull"],
+                "In a hole in the ground there lived a hobbit."
+            );
         } else {
             panic!("Expected Book metadata");
         }
     }
 
+    #[test]
+    fn merges_people_sharing_an_author_key() {
+        let record = OpenLibraryBookRecord {
+            title: "The Silmarillion".to_string(),
+            authors: vec!["J.R.R. Tolkien".to_string(), "J. R. R. Tolkien".to_string()],
+            author_keys: vec!["OL26320A".to_string(), "OL26320A".to_string()],
+            contributors: vec![
+                OpenLibraryContributor {
+                    name: "J.R.R. Tolkien".to_string(),
+                    role: "editor".to_string(),
+                },
+                OpenLibraryContributor {
+                    name: "Christopher Tolkien".to_string(),
+                    role: "editor".to_string(),
+                },
+            ],
+            ..Default::default()
+        };
+
+        let people =
+            build_people_details(&record, &LookupOptions::default()).expect("Expected people");
+        assert_eq!(people.len(), 2);
+        assert_eq!(people[0].name, "J.R.R. Tolkien");
+        assert_eq!(people[0].alt, Some(vec!["J. R. R. Tolkien".to_string()]));
+        let params = people[0].params.as_ref().unwrap();
+        assert_eq!(params["openlibraryAuthorId"], "ol26320a");
+        assert_eq!(params["roles"], json!(["author", "editor"]));
+        assert_eq!(people[1].name, "Christopher Tolkien");
+        assert_eq!(people[1].kind.as_deref(), Some("editor"));
+    }
+
     #[test]
     fn uses_canonical_work_id_when_edition_is_missing() {
         let record = OpenLibraryBookRecord {
@@ -397,7 +2433,7 @@ mod tests {
             ..Default::default()
         };
 
-        let result = openlibrary_book_to_result(record);
+        let result = openlibrary_book_to_result(record, &LookupOptions::default());
 
         if let RsLookupMetadataResult::Book(book) = result.metadata {
             assert_eq!(book.id, "olwid:OL45804W".to_string());
@@ -414,7 +2450,7 @@ mod tests {
             ..Default::default()
         };
 
-        let result = openlibrary_book_to_result(record);
+        let result = openlibrary_book_to_result(record, &LookupOptions::default());
 
         if let RsLookupMetadataResult::Book(book) = result.metadata {
             assert_eq!(book.id, "isbn13:9780140328721".to_string());
@@ -430,7 +2466,7 @@ mod tests {
             ..Default::default()
         };
 
-        let result = openlibrary_book_to_result(record);
+        let result = openlibrary_book_to_result(record, &LookupOptions::default());
 
         if let RsLookupMetadataResult::Book(book) = result.metadata {
             assert_eq!(book.id, "openlibrary-title-the-hobbit".to_string());
@@ -439,6 +2475,260 @@ mod tests {
         }
     }
 
+    #[test]
+    fn emits_all_work_ids_and_keeps_first_as_work_id() {
+        let record = OpenLibraryBookRecord {
+            title: "Collected Stories".to_string(),
+            edition_id: Some("OL1234567M".to_string()),
+            work_id: Some("OL100W".to_string()),
+            work_ids: vec!["OL100W".to_string(), "OL200W".to_string()],
+            ..Default::default()
+        };
+
+        let result = openlibrary_book_to_result(record, &LookupOptions::default());
+
+        if let RsLookupMetadataResult::Book(book) = result.metadata {
+            assert_eq!(book.openlibrary_work_id, Some("OL100W".to_string()));
+            let params = book.params.expect("Expected params");
+            assert_eq!(params["workIds"], json!(["OL100W", "OL200W"]));
+        } else {
+            panic!("Expected Book metadata");
+        }
+    }
+
+    #[test]
+    fn emits_table_of_contents_only_when_requested() {
+        let record = OpenLibraryBookRecord {
+            title: "Reference Work".to_string(),
+            table_of_contents: vec![OpenLibraryTocEntry {
+                title: "Introduction".to_string(),
+                pagenum: Some("1".to_string()),
+                label: None,
+            }],
+            ..Default::default()
+        };
+
+        let result = openlibrary_book_to_result(record.clone(), &LookupOptions::default());
+        if let RsLookupMetadataResult::Book(book) = result.metadata {
+            assert!(book
+                .params
+                .expect("Expected params")
+                .get("tableOfContents")
+                .is_none());
+        } else {
+            panic!("Expected Book metadata");
+        }
+
+        let options = LookupOptions {
+            include_toc: true,
+            ..Default::default()
+        };
+        let result = openlibrary_book_to_result(record, &options);
+        if let RsLookupMetadataResult::Book(book) = result.metadata {
+            let params = book.params.expect("Expected params");
+            assert_eq!(
+                params["tableOfContents"],
+                json!([{ "title": "Introduction", "pagenum": "1" }])
+            );
+        } else {
+            panic!("Expected Book metadata");
+        }
+    }
+
+    #[test]
+    fn emits_contributors_as_people_with_their_role() {
+        let record = OpenLibraryBookRecord {
+            title: "The Hobbit".to_string(),
+            authors: vec!["J.R.R. Tolkien".to_string()],
+            author_keys: vec!["OL26320A".to_string()],
+            contributors: vec![
+                OpenLibraryContributor {
+                    name: "Alan Lee".to_string(),
+                    role: "illustrator".to_string(),
+                },
+                OpenLibraryContributor {
+                    name: "Alan Lee".to_string(),
+                    role: "editor".to_string(),
+                },
+            ],
+            ..Default::default()
+        };
+
+        let result = openlibrary_book_to_result(record, &LookupOptions::default());
+        let people = result
+            .relations
+            .and_then(|relations| relations.people_details)
+            .expect("Expected people_details");
+
+        assert_eq!(people.len(), 3);
+        assert_eq!(people[0].kind, Some("author".to_string()));
+        assert_eq!(people[1].id, "openlib-person:alan-lee-illustrator");
+        assert_eq!(people[1].kind, Some("illustrator".to_string()));
+        assert_eq!(people[2].id, "openlib-person:alan-lee-editor");
+        assert_eq!(people[2].kind, Some("editor".to_string()));
+    }
+
+    #[test]
+    fn emits_confidence_when_known() {
+        let record = OpenLibraryBookRecord {
+            title: "The Hobbit".to_string(),
+            confidence: Some(0.8),
+            ..Default::default()
+        };
+
+        let result = openlibrary_book_to_result(record, &LookupOptions::default());
+
+        if let RsLookupMetadataResult::Book(book) = result.metadata {
+            assert_eq!(book.params.expect("Expected params")["confidence"], 0.8);
+        } else {
+            panic!("Expected Book metadata");
+        }
+    }
+
+    #[test]
+    fn emits_edition_and_work_ratings_separately() {
+        let record = OpenLibraryBookRecord {
+            title: "The Hobbit".to_string(),
+            edition_rating: Some(OpenLibraryRating {
+                average: 4.5,
+                count: 2,
+            }),
+            ..Default::default()
+        };
+
+        let result = openlibrary_book_to_result(record, &LookupOptions::default());
+        if let RsLookupMetadataResult::Book(book) = result.metadata {
+            let params = book.params.expect("Expected params");
+            assert_eq!(
+                params["editionRating"],
+                json!({ "average": 4.5, "count": 2 })
+            );
+            assert!(params.get("workRating").is_none());
+        } else {
+            panic!("Expected Book metadata");
+        }
+
+        let record = OpenLibraryBookRecord {
+            title: "The Hobbit".to_string(),
+            work_rating: Some(OpenLibraryRating {
+                average: 4.1,
+                count: 300,
+            }),
+            ..Default::default()
+        };
+
+        let result = openlibrary_book_to_result(record, &LookupOptions::default());
+        if let RsLookupMetadataResult::Book(book) = result.metadata {
+            let params = book.params.expect("Expected params");
+            assert!(params.get("editionRating").is_none());
+            assert_eq!(
+                params["workRating"],
+                json!({ "average": 4.1, "count": 300 })
+            );
+        } else {
+            panic!("Expected Book metadata");
+        }
+    }
+
+    #[test]
+    fn drops_implausible_page_counts() {
+        let plausible = OpenLibraryBookRecord {
+            title: "The Hobbit".to_string(),
+            pages: Some(310),
+            ..Default::default()
+        };
+        let implausible = OpenLibraryBookRecord {
+            title: "The Hobbit".to_string(),
+            pages: Some(50_000),
+            ..Default::default()
+        };
+
+        let options = LookupOptions::default();
+        let pages = |record: OpenLibraryBookRecord, options: &LookupOptions| {
+            match openlibrary_book_to_result(record, options).metadata {
+                RsLookupMetadataResult::Book(book) => book.pages,
+                _ => panic!("Expected Book metadata"),
+            }
+        };
+
+        assert_eq!(pages(plausible.clone(), &options), Some(310));
+        assert_eq!(pages(implausible.clone(), &options), None);
+
+        let raised = LookupOptions {
+            max_pages: Some(60_000),
+            ..Default::default()
+        };
+        assert_eq!(pages(implausible, &raised), Some(50_000));
+        let lowered = LookupOptions {
+            max_pages: Some(300),
+            ..Default::default()
+        };
+        assert_eq!(pages(plausible, &lowered), None);
+    }
+
+    #[test]
+    fn localizes_subject_names_but_keeps_english_ids() {
+        let record = OpenLibraryBookRecord {
+            title: "The Hobbit".to_string(),
+            subjects: vec!["Fantasy".to_string(), "Dragons".to_string()],
+            ..Default::default()
+        };
+        let options = LookupOptions {
+            locale: Some("fr".to_string()),
+            ..Default::default()
+        };
+
+        let result = openlibrary_book_to_result(record, &options);
+        let tags = result
+            .relations
+            .and_then(|relations| relations.tags_details)
+            .expect("Expected tags_details");
+
+        assert_eq!(tags[0].id, "openlib-tag:fantasy");
+        assert_eq!(tags[0].name, "Fantaisie");
+        assert_eq!(tags[1].name, "Dragons");
+    }
+
+    #[test]
+    fn emits_other_titles() {
+        let record = OpenLibraryBookRecord {
+            title: "Noruwei no Mori".to_string(),
+            other_titles: vec!["ノルウェイの森".to_string()],
+            ..Default::default()
+        };
+
+        let result = openlibrary_book_to_result(record, &LookupOptions::default());
+
+        if let RsLookupMetadataResult::Book(book) = result.metadata {
+            let params = book.params.expect("Expected params");
+            assert_eq!(params["otherTitles"], json!(["ノルウェイの森"]));
+        } else {
+            panic!("Expected Book metadata");
+        }
+    }
+
+    #[test]
+    fn emits_slug_from_title_author_and_year() {
+        let record = OpenLibraryBookRecord {
+            title: "The Hobbit: or, There & Back Again!".to_string(),
+            authors: vec!["J.R.R. Tolkien".to_string()],
+            publish_year: Some(1937),
+            ..Default::default()
+        };
+
+        let result = openlibrary_book_to_result(record, &LookupOptions::default());
+
+        if let RsLookupMetadataResult::Book(book) = result.metadata {
+            let params = book.params.expect("Expected params");
+            assert_eq!(
+                params["slug"],
+                "the-hobbit-or-there-back-again-j-r-r-tolkien-1937"
+            );
+        } else {
+            panic!("Expected Book metadata");
+        }
+    }
+
     #[test]
     fn includes_images_people_and_tags_in_relations_details_only() {
         let record = OpenLibraryBookRecord {
@@ -450,7 +2740,7 @@ mod tests {
             ..Default::default()
         };
 
-        let result = openlibrary_book_to_result(record);
+        let result = openlibrary_book_to_result(record, &LookupOptions::default());
         let relations = result.relations.expect("Expected relations");
 
         let images = relations.ext_images.expect("Expected ext_images");
@@ -0,0 +1,236 @@
+use crate::isbn::{isbn10_to_isbn13, isbn13_checksum_valid};
+
+/// What a bare identifier string looks like, with its normalized value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IdentifierKind {
+    /// ISBN-13 with a valid check digit, without hyphens or spaces; valid ISBN-10s are
+    /// converted, a mistyped one is kept as its 10 characters (`X` check digit uppercased).
+    Isbn(String),
+    /// OpenLibrary work id (`OL45804W`).
+    Work(String),
+    /// OpenLibrary edition id (`OL7353617M`).
+    Edition(String),
+    /// OCLC number, digits only.
+    Oclc(String),
+    /// Library of Congress control number in its normalized form (`n78890351`).
+    Lccn(String),
+    Unknown,
+}
+
+/// Classifies `value` by shape only; nothing is looked up. Bare 8 digit numbers could be
+/// either an OCLC number or an LCCN, so they need an `oclc:`/`lccn:` prefix.
+pub fn classify_identifier(value: &str) -> IdentifierKind {
+    let trimmed = value.trim();
+    if trimmed.is_empty() {
+        return IdentifierKind::Unknown;
+    }
+
+    if let Some(isbn) = normalize_exact_isbn_search(trimmed) {
+        return IdentifierKind::Isbn(isbn);
+    }
+    if let Some(kind) = classify_openlibrary_id(trimmed) {
+        return kind;
+    }
+    if let Some(oclc) = normalize_oclc(trimmed) {
+        return IdentifierKind::Oclc(oclc);
+    }
+    if let Some(lccn) = normalize_lccn(trimmed) {
+        return IdentifierKind::Lccn(lccn);
+    }
+
+    IdentifierKind::Unknown
+}
+
+pub fn normalize_exact_isbn_search(value: &str) -> Option<String> {
+    let trimmed = value.trim();
+    if trimmed.is_empty() {
+        return None;
+    }
+
+    let compact: String = trimmed
+        .chars()
+        .filter(|c| *c != '-' && !c.is_ascii_whitespace())
+        .collect();
+
+    if compact.len() == 13 && compact.chars().all(|c| c.is_ascii_digit()) {
+        return isbn13_checksum_valid(&compact).then_some(compact);
+    }
+
+    if compact.len() != 10 {
+        return None;
+    }
+
+    isbn10_to_isbn13(&compact)
+}
+
+fn classify_openlibrary_id(value: &str) -> Option<IdentifierKind> {
+    let id = value
+        .trim_matches('/')
+        .rsplit('/')
+        .next()
+        .unwrap_or(value)
+        .to_ascii_uppercase();
+    let body = id.strip_prefix("OL")?;
+    // Split on chars, not bytes: name queries (`Olé`) reach this too.
+    let mut chars = body.chars();
+    let suffix = chars.next_back()?;
+    let digits = chars.as_str();
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    match suffix {
+        'W' => Some(IdentifierKind::Work(id)),
+        'M' => Some(IdentifierKind::Edition(id)),
+        _ => None,
+    }
+}
+
+/// Accepts `oclc:`/`(OCoLC)` prefixed numbers and the MARC `ocm` (8 digits), `ocn`
+/// (9 digits) and `on` (10+ digits) control number forms.
+fn normalize_oclc(value: &str) -> Option<String> {
+    let lower = value.to_ascii_lowercase();
+    let digits = if let Some(digits) = lower
+        .strip_prefix("(ocolc)")
+        .or_else(|| lower.strip_prefix("oclc:"))
+    {
+        digits.trim()
+    } else {
+        [("ocm", 8..=8), ("ocn", 9..=9), ("on", 10..=usize::MAX)]
+            .into_iter()
+            .find_map(|(prefix, lengths)| {
+                lower
+                    .strip_prefix(prefix)
+                    .filter(|digits| lengths.contains(&digits.len()))
+            })?
+    };
+
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let digits = digits.trim_start_matches('0');
+    if digits.is_empty() {
+        None
+    } else {
+        Some(digits.to_string())
+    }
+}
+
+/// Normalizes per the Library of Congress rules: drop spaces, and zero-pad the serial
+/// after a hyphen to six digits (`93-5405` becomes `93005405`).
+fn normalize_lccn(value: &str) -> Option<String> {
+    let lower = value.to_ascii_lowercase();
+    let explicit = lower.strip_prefix("lccn:");
+    let compact: String = explicit
+        .unwrap_or(&lower)
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect();
+
+    let has_hyphen = compact.contains('-');
+    let normalized = match compact.split_once('-') {
+        Some((head, serial)) => {
+            if serial.is_empty() || serial.len() > 6 || serial.contains('-') {
+                return None;
+            }
+            format!("{head}{serial:0>6}")
+        }
+        None => compact,
+    };
+
+    let prefix_len = normalized
+        .chars()
+        .take_while(|c| c.is_ascii_lowercase())
+        .count();
+    let digits = &normalized[prefix_len..];
+    if prefix_len > 3
+        || !digits.chars().all(|c| c.is_ascii_digit())
+        || !matches!(digits.len(), 8 | 10)
+    {
+        return None;
+    }
+
+    // A bare number is only an LCCN when something else says so.
+    if explicit.is_none() && !has_hyphen && prefix_len == 0 {
+        return None;
+    }
+
+    Some(normalized)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_isbns() {
+        assert_eq!(
+            classify_identifier("978-0-14-032872-1"),
+            IdentifierKind::Isbn("9780140328721".to_string())
+        );
+        assert_eq!(
+            classify_identifier("0-8044-2957-x"),
+            IdentifierKind::Isbn("9780804429573".to_string())
+        );
+    }
+
+    #[test]
+    fn classifies_openlibrary_ids() {
+        assert_eq!(
+            classify_identifier("OL45804W"),
+            IdentifierKind::Work("OL45804W".to_string())
+        );
+        assert_eq!(
+            classify_identifier("/books/ol7353617m"),
+            IdentifierKind::Edition("OL7353617M".to_string())
+        );
+    }
+
+    #[test]
+    fn classifies_oclc_numbers() {
+        assert_eq!(
+            classify_identifier("ocm00012345"),
+            IdentifierKind::Oclc("12345".to_string())
+        );
+        assert_eq!(
+            classify_identifier("(OCoLC)28419896"),
+            IdentifierKind::Oclc("28419896".to_string())
+        );
+        assert_eq!(
+            classify_identifier("oclc:28419896"),
+            IdentifierKind::Oclc("28419896".to_string())
+        );
+    }
+
+    #[test]
+    fn classifies_lccns() {
+        assert_eq!(
+            classify_identifier("n78-890351"),
+            IdentifierKind::Lccn("n78890351".to_string())
+        );
+        assert_eq!(
+            classify_identifier("93-5405"),
+            IdentifierKind::Lccn("93005405".to_string())
+        );
+        assert_eq!(
+            classify_identifier("lccn:2001012345"),
+            IdentifierKind::Lccn("2001012345".to_string())
+        );
+    }
+
+    #[test]
+    fn ambiguous_or_free_text_is_unknown() {
+        assert_eq!(classify_identifier("93005405"), IdentifierKind::Unknown);
+        assert_eq!(classify_identifier("The Hobbit"), IdentifierKind::Unknown);
+        assert_eq!(classify_identifier("OL26320A"), IdentifierKind::Unknown);
+        assert_eq!(classify_identifier("On 1984"), IdentifierKind::Unknown);
+        assert_eq!(classify_identifier(""), IdentifierKind::Unknown);
+    }
+
+    #[test]
+    fn multibyte_names_are_unknown() {
+        assert_eq!(classify_identifier("Olé"), IdentifierKind::Unknown);
+        assert_eq!(classify_identifier("OL12Ж"), IdentifierKind::Unknown);
+        assert_eq!(classify_identifier("Ol"), IdentifierKind::Unknown);
+    }
+}
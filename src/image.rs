@@ -0,0 +1,80 @@
+/// Reads `(width, height)` from the header of a JPEG, PNG or GIF image without decoding it.
+pub fn image_dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    if bytes.starts_with(&[0xFF, 0xD8]) {
+        return jpeg_dimensions(bytes);
+    }
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") && bytes.get(12..16) == Some(b"IHDR") {
+        let width = u32::from_be_bytes(bytes.get(16..20)?.try_into().ok()?);
+        let height = u32::from_be_bytes(bytes.get(20..24)?.try_into().ok()?);
+        return Some((width, height));
+    }
+    if bytes.starts_with(b"GIF8") {
+        let width = u16::from_le_bytes(bytes.get(6..8)?.try_into().ok()?);
+        let height = u16::from_le_bytes(bytes.get(8..10)?.try_into().ok()?);
+        return Some((width.into(), height.into()));
+    }
+    None
+}
+
+/// Walks the JPEG segments up to the first start-of-frame marker, which carries the size.
+fn jpeg_dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    let mut offset = 2;
+    loop {
+        if *bytes.get(offset)? != 0xFF {
+            return None;
+        }
+        let marker = *bytes.get(offset + 1)?;
+        if marker == 0xFF {
+            offset += 1;
+            continue;
+        }
+        // Standalone markers carry no length.
+        if marker == 0x01 || (0xD0..=0xD7).contains(&marker) {
+            offset += 2;
+            continue;
+        }
+
+        let length = u16::from_be_bytes(bytes.get(offset + 2..offset + 4)?.try_into().ok()?);
+        let is_start_of_frame =
+            (0xC0..=0xCF).contains(&marker) && !matches!(marker, 0xC4 | 0xC8 | 0xCC);
+        if is_start_of_frame {
+            let segment = bytes.get(offset + 5..offset + 9)?;
+            let height = u16::from_be_bytes([segment[0], segment[1]]);
+            let width = u16::from_be_bytes([segment[2], segment[3]]);
+            return Some((width.into(), height.into()));
+        }
+        offset += 2 + usize::from(length);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_jpeg_dimensions_after_app_segments() {
+        let jpeg = [
+            0xFF, 0xD8, // SOI
+            0xFF, 0xE0, 0x00, 0x04, 0x4A, 0x46, // APP0, 2 bytes payload
+            0xFF, 0xC0, 0x00, 0x11, 0x08, 0x01, 0xF4, 0x01, 0x4D, // SOF0 500x333
+        ];
+        assert_eq!(image_dimensions(&jpeg), Some((333, 500)));
+    }
+
+    #[test]
+    fn reads_png_and_gif_dimensions() {
+        let mut png = b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0dIHDR".to_vec();
+        png.extend_from_slice(&180u32.to_be_bytes());
+        png.extend_from_slice(&270u32.to_be_bytes());
+        assert_eq!(image_dimensions(&png), Some((180, 270)));
+
+        let gif = b"GIF89a\x2c\x01\xc8\x00";
+        assert_eq!(image_dimensions(gif), Some((300, 200)));
+    }
+
+    #[test]
+    fn unknown_or_truncated_images_have_no_dimensions() {
+        assert_eq!(image_dimensions(b"not an image"), None);
+        assert_eq!(image_dimensions(&[0xFF, 0xD8, 0xFF, 0xC0, 0x00]), None);
+    }
+}
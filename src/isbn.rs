@@ -0,0 +1,347 @@
+/// Registrant ranges for the registration groups we know how to hyphenate.
+/// Each range is `(start, end, registrant_length)` over the first seven digits
+/// that follow the group. Only the common groups are listed, so hyphenation is
+/// best-effort: anything outside these tables is left unhyphenated.
+struct RegistrationGroup {
+    prefix: &'static str,
+    group: &'static str,
+    ranges: &'static [(u32, u32, usize)],
+}
+
+const REGISTRATION_GROUPS: &[RegistrationGroup] = &[
+    // English language
+    RegistrationGroup {
+        prefix: "978",
+        group: "0",
+        ranges: &[
+            (0, 1_999_999, 2),
+            (2_000_000, 6_999_999, 3),
+            (7_000_000, 8_499_999, 4),
+            (8_500_000, 8_999_999, 5),
+            (9_000_000, 9_499_999, 6),
+            (9_500_000, 9_999_999, 7),
+        ],
+    },
+    // English language
+    RegistrationGroup {
+        prefix: "978",
+        group: "1",
+        ranges: &[
+            (0, 999_999, 2),
+            (1_000_000, 3_999_999, 3),
+            (4_000_000, 5_499_999, 4),
+            (5_500_000, 8_697_999, 5),
+            (8_698_000, 9_989_999, 6),
+            (9_990_000, 9_999_999, 7),
+        ],
+    },
+    // French language
+    RegistrationGroup {
+        prefix: "978",
+        group: "2",
+        ranges: &[
+            (0, 1_999_999, 2),
+            (2_000_000, 3_499_999, 3),
+            (3_500_000, 3_999_999, 5),
+            (4_000_000, 6_999_999, 3),
+            (7_000_000, 8_399_999, 4),
+            (8_400_000, 8_999_999, 5),
+            (9_000_000, 9_499_999, 6),
+            (9_500_000, 9_999_999, 7),
+        ],
+    },
+    // German language
+    RegistrationGroup {
+        prefix: "978",
+        group: "3",
+        ranges: &[
+            (0, 299_999, 2),
+            (300_000, 339_999, 3),
+            (340_000, 369_999, 4),
+            (370_000, 399_999, 5),
+            (400_000, 1_999_999, 2),
+            (2_000_000, 6_999_999, 3),
+            (7_000_000, 8_499_999, 4),
+            (8_500_000, 8_999_999, 5),
+            (9_000_000, 9_499_999, 6),
+            (9_500_000, 9_539_999, 7),
+            (9_540_000, 9_699_999, 5),
+            (9_700_000, 9_849_999, 7),
+            (9_850_000, 9_999_999, 5),
+        ],
+    },
+    // Japan
+    RegistrationGroup {
+        prefix: "978",
+        group: "4",
+        ranges: &[
+            (0, 1_999_999, 2),
+            (2_000_000, 6_999_999, 3),
+            (7_000_000, 8_499_999, 4),
+            (8_500_000, 8_999_999, 5),
+            (9_000_000, 9_499_999, 6),
+            (9_500_000, 9_999_999, 7),
+        ],
+    },
+    // France
+    RegistrationGroup {
+        prefix: "979",
+        group: "10",
+        ranges: &[
+            (0, 1_999_999, 2),
+            (2_000_000, 6_999_999, 3),
+            (7_000_000, 8_999_999, 4),
+            (9_000_000, 9_759_999, 5),
+            (9_760_000, 9_999_999, 6),
+        ],
+    },
+];
+
+/// Formats a compact ISBN-13 with hyphens (`978-0-14-032872-1`).
+/// Returns `None` when the input is not 13 digits or its registration group is unknown.
+pub fn hyphenate_isbn13(isbn13: &str) -> Option<String> {
+    if isbn13.len() != 13 || !isbn13.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let (prefix, rest) = isbn13.split_at(3);
+    let group = REGISTRATION_GROUPS
+        .iter()
+        .find(|group| group.prefix == prefix && rest.starts_with(group.group))?;
+
+    let body = &rest[group.group.len()..rest.len() - 1];
+    let check = &rest[rest.len() - 1..];
+    let mut window: String = body.chars().take(7).collect();
+    while window.len() < 7 {
+        window.push('0');
+    }
+    let value: u32 = window.parse().ok()?;
+
+    let registrant_length = group
+        .ranges
+        .iter()
+        .find(|(start, end, _)| (*start..=*end).contains(&value))
+        .map(|(_, _, length)| *length)?;
+    if registrant_length >= body.len() {
+        return None;
+    }
+
+    let (registrant, publication) = body.split_at(registrant_length);
+    Some(format!(
+        "{prefix}-{}-{registrant}-{publication}-{check}",
+        group.group
+    ))
+}
+
+/// True for 13 digits whose ISBN-13 check digit matches.
+pub fn isbn13_checksum_valid(isbn13: &str) -> bool {
+    let digits: Vec<u32> = isbn13.chars().filter_map(|c| c.to_digit(10)).collect();
+    if digits.len() != 13 || isbn13.len() != 13 {
+        return false;
+    }
+    let sum: u32 = digits
+        .iter()
+        .enumerate()
+        .map(|(index, digit)| if index % 2 == 0 { *digit } else { digit * 3 })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+/// `body` (the first 12 digits of an ISBN-13) with its check digit appended.
+fn with_isbn13_check_digit(body: &str) -> String {
+    let sum: u32 = body
+        .chars()
+        .filter_map(|c| c.to_digit(10))
+        .enumerate()
+        .map(|(index, digit)| if index % 2 == 0 { digit } else { digit * 3 })
+        .sum();
+    format!("{body}{}", (10 - sum % 10) % 10)
+}
+
+/// ISBN-13 for a valid ISBN-10 (hyphens and spaces ignored, `X` check digit allowed):
+/// `978` plus the first nine digits, with the ISBN-13 mod-10 check digit recomputed.
+/// `None` when `value` is not an ISBN-10 or its mod-11 check digit is wrong.
+pub fn isbn10_to_isbn13(value: &str) -> Option<String> {
+    let compact: Vec<char> = value
+        .chars()
+        .filter(|c| *c != '-' && !c.is_whitespace())
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    if compact.len() != 10 {
+        return None;
+    }
+
+    let mut sum = 0;
+    for (index, c) in compact.iter().enumerate() {
+        let digit = match c {
+            'X' if index == 9 => 10,
+            _ => c.to_digit(10)?,
+        };
+        sum += digit * (10 - index as u32);
+    }
+    if sum % 11 != 0 {
+        return None;
+    }
+
+    let body: String = "978".chars().chain(compact[..9].iter().copied()).collect();
+    Some(with_isbn13_check_digit(&body))
+}
+
+/// ISBN-13 form of an ISBN-10 or ISBN-13 (hyphens and spaces ignored). ISBN-10s must
+/// pass their mod-11 check, see `isbn10_to_isbn13`.
+pub fn canonical_isbn13(value: &str) -> Option<String> {
+    let compact: String = value
+        .chars()
+        .filter(|c| *c != '-' && !c.is_whitespace())
+        .collect();
+
+    if compact.len() == 13 && compact.chars().all(|c| c.is_ascii_digit()) {
+        Some(compact)
+    } else {
+        isbn10_to_isbn13(&compact)
+    }
+}
+
+/// Valid `978`/`979` ISBN-13s one edit away from an OCR'd or mistyped value: a digit
+/// inserted into 12 digits, removed from 14, or substituted in 13 with a bad checksum.
+/// Edits run from the last position to the first, so a wrong, missing or doubled check
+/// digit comes first. Candidates are deduped.
+pub fn fuzzy_isbn13_candidates(value: &str) -> Vec<String> {
+    let digits: String = value
+        .chars()
+        .filter(|c| *c != '-' && !c.is_whitespace())
+        .collect();
+    if !digits.chars().all(|c| c.is_ascii_digit()) {
+        return vec![];
+    }
+
+    let mut edits = Vec::new();
+    match digits.len() {
+        12 => {
+            for position in (0..=digits.len()).rev() {
+                for digit in '0'..='9' {
+                    let mut candidate = digits.clone();
+                    candidate.insert(position, digit);
+                    edits.push(candidate);
+                }
+            }
+        }
+        14 => {
+            for position in (0..digits.len()).rev() {
+                let mut candidate = digits.clone();
+                candidate.remove(position);
+                edits.push(candidate);
+            }
+        }
+        13 if !isbn13_checksum_valid(&digits) => {
+            for position in (0..digits.len()).rev() {
+                for digit in '0'..='9' {
+                    let mut candidate = digits.clone();
+                    candidate.replace_range(position..=position, &digit.to_string());
+                    edits.push(candidate);
+                }
+            }
+        }
+        _ => {}
+    }
+
+    let mut candidates: Vec<String> = Vec::new();
+    for candidate in edits {
+        if (candidate.starts_with("978") || candidate.starts_with("979"))
+            && isbn13_checksum_valid(&candidate)
+            && !candidates.contains(&candidate)
+        {
+            candidates.push(candidate);
+        }
+    }
+    candidates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn canonical_isbn13_converts_isbn10() {
+        assert_eq!(
+            canonical_isbn13("0-14-032872-6"),
+            Some("9780140328721".to_string())
+        );
+        assert_eq!(canonical_isbn13("0140328728"), None);
+        assert_eq!(
+            canonical_isbn13("978-0-14-032872-1"),
+            Some("9780140328721".to_string())
+        );
+        assert_eq!(
+            canonical_isbn13("080442957x"),
+            Some("9780804429573".to_string())
+        );
+        assert_eq!(canonical_isbn13("12345"), None);
+    }
+
+    #[test]
+    fn isbn10_converts_to_isbn13() {
+        assert_eq!(
+            isbn10_to_isbn13("0140328726").as_deref(),
+            Some("9780140328721")
+        );
+        assert_eq!(
+            isbn10_to_isbn13("0-8044-2957-x").as_deref(),
+            Some("9780804429573")
+        );
+        assert_eq!(isbn10_to_isbn13("0140328727"), None);
+        assert_eq!(isbn10_to_isbn13("X140328726"), None);
+        assert_eq!(isbn10_to_isbn13("9780140328721"), None);
+    }
+
+    #[test]
+    fn fuzzy_candidates_are_one_edit_away_and_valid() {
+        let missing_digit = fuzzy_isbn13_candidates("978014032872");
+        assert_eq!(missing_digit[0], "9780140328721");
+        assert!(missing_digit
+            .iter()
+            .all(|candidate| isbn13_checksum_valid(candidate)));
+
+        let extra_digit = fuzzy_isbn13_candidates("97801403287210");
+        assert_eq!(extra_digit[0], "9780140328721");
+
+        let substituted = fuzzy_isbn13_candidates("978-0-14-032872-2");
+        assert_eq!(substituted[0], "9780140328721");
+        assert!(!substituted.contains(&"9780140328722".to_string()));
+
+        assert!(fuzzy_isbn13_candidates("9780140328721").is_empty());
+        assert!(fuzzy_isbn13_candidates("12345").is_empty());
+        assert!(fuzzy_isbn13_candidates("97801403287X").is_empty());
+    }
+
+    #[test]
+    fn hyphenates_common_groups() {
+        assert_eq!(
+            hyphenate_isbn13("9780140328721"),
+            Some("978-0-14-032872-1".to_string())
+        );
+        assert_eq!(
+            hyphenate_isbn13("9781449373320"),
+            Some("978-1-4493-7332-0".to_string())
+        );
+        assert_eq!(
+            hyphenate_isbn13("9782070368228"),
+            Some("978-2-07-036822-8".to_string())
+        );
+        assert_eq!(
+            hyphenate_isbn13("9783161484100"),
+            Some("978-3-16-148410-0".to_string())
+        );
+        assert_eq!(
+            hyphenate_isbn13("9791032305690"),
+            Some("979-10-323-0569-0".to_string())
+        );
+    }
+
+    #[test]
+    fn leaves_unknown_groups_and_bad_input_alone() {
+        assert_eq!(hyphenate_isbn13("9788845292613"), None);
+        assert_eq!(hyphenate_isbn13("978014032872"), None);
+        assert_eq!(hyphenate_isbn13("978014032872X"), None);
+    }
+}
@@ -1,24 +1,64 @@
-use extism_pdk::{http, log, plugin_fn, FnResult, HttpRequest, Json, LogLevel, WithReturnCode};
-use std::collections::HashSet;
+use extism_pdk::{
+    http, log, plugin_fn, FnResult, HttpRequest, HttpResponse, Json, LogLevel, WithReturnCode,
+};
+use std::{
+    cell::{Cell, RefCell},
+    collections::{HashMap, HashSet},
+};
 
 use rs_plugin_common_interfaces::{
     domain::external_images::ExternalImage,
-    lookup::{RsLookupMetadataResultWrapper, RsLookupQuery, RsLookupWrapper},
-    PluginInformation, PluginType,
+    domain::rs_ids::RsIds,
+    lookup::{
+        RsLookupBook, RsLookupMetadataResult, RsLookupMetadataResultWrapper, RsLookupQuery,
+        RsLookupWrapper,
+    },
+    CustomParam, CustomParamTypes, PluginInformation, PluginType,
 };
 
+mod cache;
 mod convert;
+mod identifier;
+mod image;
+mod isbn;
 mod openlibrary;
+mod options;
 
-use convert::{openlibrary_book_to_images, openlibrary_book_to_result};
+use cache::{load_cached_response, resolve_response_body, store_cached_response};
+
+use convert::{
+    data_cover_to_image, openlibrary_book_to_images, openlibrary_book_to_result,
+    record_source_summary,
+};
+use identifier::{classify_identifier, IdentifierKind};
+use image::image_dimensions;
+use isbn::{canonical_isbn13, fuzzy_isbn13_candidates, isbn10_to_isbn13};
 use openlibrary::{
-    book_record_from_edition_response, book_record_from_search_doc, book_record_from_work_response,
-    build_edition_url, build_isbn_url, build_search_url, build_work_editions_url, build_work_url,
-    first_record_from_work_editions, merge_work_with_edition, normalize_isbn13,
-    normalize_openlibrary_id, OpenLibraryBookRecord, OpenLibraryEditionResponse,
-    OpenLibrarySearchResponse, OpenLibraryWorkEditionsResponse, OpenLibraryWorkResponse,
+    author_details_from_response, author_redirect_target, book_record_from_edition_response,
+    book_record_from_search_doc, book_record_from_search_inside_hit, book_record_from_subject_work,
+    book_record_from_work_response, build_author_url, build_books_data_url,
+    build_cover_metadata_url, build_edition_ratings_url, build_edition_url,
+    build_fielded_search_url, build_fielded_search_url_at_offset, build_goodreads_search_url,
+    build_isbn_url, build_recent_changes_url, build_search_inside_url, build_search_url,
+    build_search_url_at_offset, build_subject_url, build_subject_works_url,
+    build_wikidata_search_url, build_work_editions_url, build_work_ratings_url, build_work_url,
+    changed_records, detect_volume_number, edition_has_isbn, enrich_edition_with_work,
+    merge_edition_covers, merge_work_with_edition, normalize_isbn13, normalize_openlibrary_id,
+    normalize_wikidata_id, openlibrary_subject_key, parse_datetime_millis, rating_from_response,
+    recent_change_id, records_from_work_editions, search_confidence,
+    select_edition_from_work_editions, title_contains_query_tokens, work_author_ids,
+    work_edition_total, ChangedRecordType, OpenLibraryAuthorResponse, OpenLibraryBookRecord,
+    OpenLibraryBooksDataEntry, OpenLibraryCoverMetadata, OpenLibraryDescription,
+    OpenLibraryEditionResponse, OpenLibraryRating, OpenLibraryRatingsResponse,
+    OpenLibraryRecentChange, OpenLibrarySearchInsideResponse, OpenLibrarySearchResponse,
+    OpenLibrarySubjectResponse, OpenLibraryWorkEditionsResponse, OpenLibraryWorkResponse,
+    ResolvedBy, EDITION_CANDIDATE_LIMIT, MAX_SUBJECT_WORKS_LIMIT, OPENLIBRARY_BASE_URL,
+    RECENT_CHANGES_LIMIT, SUBJECT_WORKS_LIMIT,
 };
-use serde::de::DeserializeOwned;
+use options::{
+    AmbiguityMode, EditionPreference, EnrichmentLevel, IsbnOnlyCanonical, LookupOptions, SortOrder,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
 #[plugin_fn]
 pub fn infos() -> FnResult<Json<PluginInformation>> {
@@ -31,16 +71,117 @@ pub fn infos() -> FnResult<Json<PluginInformation>> {
         publisher: "neckaros".into(),
         description: "Look up book metadata from OpenLibrary".into(),
         credential_kind: None,
-        settings: vec![],
+        settings: vec![
+            CustomParam {
+                name: "caching".into(),
+                param: CustomParamTypes::Text(Some("false".into())),
+                description: Some(
+                    "Reuse cached OpenLibrary responses with ETag conditional requests (true/false)"
+                        .into(),
+                ),
+                required: false,
+            },
+            CustomParam {
+                name: "cover_size".into(),
+                param: CustomParamTypes::Text(Some("L".into())),
+                description: Some(
+                    "Cover image size: S (small), M (medium) or L (large); anything else uses L"
+                        .into(),
+                ),
+                required: false,
+            },
+            CustomParam {
+                name: "enrichment".into(),
+                param: CustomParamTypes::Text(Some("full".into())),
+                description: Some(
+                    "Enrichment requests allowed: off (none), conservative (work and ratings only) or full"
+                        .into(),
+                ),
+                required: false,
+            },
+        ],
         ..Default::default()
     }))
 }
 
+/// Per-invocation state shared by every request made for one lookup.
+struct LookupContext {
+    options: LookupOptions,
+    requests: Cell<u32>,
+    /// First URL requested, i.e. the endpoint that resolved the lookup.
+    endpoint: RefCell<Option<String>>,
+    /// Rate limit reported by the latest response that carried the headers.
+    rate_limit: Cell<Option<RateLimit>>,
+}
+
+impl LookupContext {
+    fn new(options: LookupOptions) -> Self {
+        Self {
+            options,
+            requests: Cell::new(0),
+            endpoint: RefCell::new(None),
+            rate_limit: Cell::new(None),
+        }
+    }
+
+    /// Whether an optional enrichment request still fits in `max_requests` and leaves
+    /// OpenLibrary's rate limit at least `RATE_LIMIT_RESERVE` requests of headroom.
+    fn has_budget(&self) -> bool {
+        let within_max = self
+            .options
+            .max_requests
+            .is_none_or(|max_requests| self.requests.get() < max_requests);
+        let within_rate_limit = self
+            .rate_limit
+            .get()
+            .is_none_or(|rate_limit| rate_limit.remaining > RATE_LIMIT_RESERVE);
+        within_max && within_rate_limit
+    }
+}
+
+/// Requests kept back from the rate limit for the lookups that must still run. The
+/// plugin cannot sleep, so once the window is this low it sheds optional enrichment
+/// instead of delaying it.
+const RATE_LIMIT_RESERVE: u32 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct RateLimit {
+    remaining: u32,
+    /// Seconds (or epoch seconds, depending on the server) until the window resets.
+    reset: Option<u64>,
+}
+
+fn find_header<'a>(headers: &'a HashMap<String, String>, name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.trim())
+}
+
+/// Reads `X-RateLimit-Remaining`/`X-RateLimit-Reset`. A `Retry-After` alone means the
+/// window is spent, resetting after its delay (`None` for an HTTP-date). `None` without
+/// either header or a usable remaining count.
+fn parse_rate_limit(headers: &HashMap<String, String>) -> Option<RateLimit> {
+    let Some(remaining) = find_header(headers, "x-ratelimit-remaining") else {
+        return find_header(headers, "retry-after").map(|retry_after| RateLimit {
+            remaining: 0,
+            reset: retry_after.parse().ok(),
+        });
+    };
+    let reset = find_header(headers, "x-ratelimit-reset").and_then(|value| value.parse().ok());
+    Some(RateLimit {
+        remaining: remaining.parse().ok()?,
+        reset,
+    })
+}
+
 #[derive(Debug, Default)]
 struct BookIds {
     isbn13: Option<String>,
     edition_id: Option<String>,
     work_id: Option<String>,
+    wikidata: Option<String>,
+    goodreads: Option<String>,
 }
 
 fn extract_book_ids(query: &RsLookupQuery) -> Option<BookIds> {
@@ -50,20 +191,27 @@ fn extract_book_ids(query: &RsLookupQuery) -> Option<BookIds> {
             Some(BookIds {
                 isbn13: ids
                     .and_then(|ids| ids.isbn13.as_ref())
-                    .and_then(|value| normalize_isbn13(value)),
+                    .and_then(|value| normalize_isbn13(value).or_else(|| isbn10_to_isbn13(value))),
                 edition_id: ids
                     .and_then(|ids| ids.openlibrary_edition_id.as_ref())
                     .and_then(|value| normalize_openlibrary_id(value, "books")),
                 work_id: ids
                     .and_then(|ids| ids.openlibrary_work_id.as_ref())
                     .and_then(|value| normalize_openlibrary_id(value, "works")),
+                wikidata: ids
+                    .and_then(|ids| ids.get_other("wikidata"))
+                    .and_then(|value| normalize_wikidata_id(&value)),
+                goodreads: ids
+                    .and_then(|ids| ids.get_other("goodreads"))
+                    .map(|value| value.trim().to_string())
+                    .filter(|value| !value.is_empty() && value.chars().all(|c| c.is_ascii_digit())),
             })
         }
         _ => None,
     }
 }
 
-fn build_http_request(url: String) -> HttpRequest {
+fn build_http_request(url: String, etag: Option<&str>) -> HttpRequest {
     let mut request = HttpRequest {
         url,
         headers: Default::default(),
@@ -73,31 +221,177 @@ fn build_http_request(url: String) -> HttpRequest {
     request
         .headers
         .insert("Accept".to_string(), "application/json".to_string());
+    if let Some(etag) = etag {
+        request
+            .headers
+            .insert("If-None-Match".to_string(), etag.to_string());
+    }
 
     request
 }
 
-fn execute_get<T: DeserializeOwned>(url: String) -> FnResult<T> {
-    let request = build_http_request(url);
-    let res = http::request::<Vec<u8>>(&request, None);
+const ERROR_SNIPPET_BYTES: usize = 500;
 
-    match res {
-        Ok(res) if res.status_code() >= 200 && res.status_code() < 300 => match res.json::<T>() {
-            Ok(parsed) => Ok(parsed),
-            Err(e) => {
-                log!(LogLevel::Error, "OpenLibrary JSON parse error: {}", e);
-                Err(WithReturnCode::new(e, 500))
-            }
+/// Flattens whitespace and control characters and caps the body at `ERROR_SNIPPET_BYTES`.
+fn error_body_snippet(body: &[u8]) -> String {
+    let text = String::from_utf8_lossy(&body[..body.len().min(ERROR_SNIPPET_BYTES)]);
+    text.split(|c: char| c.is_whitespace() || c.is_control())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn http_error_message(status: u16, body: &[u8], verbose: bool) -> String {
+    let snippet = if verbose {
+        error_body_snippet(body)
+    } else {
+        String::new()
+    };
+
+    if snippet.is_empty() {
+        format!("HTTP error: {status}")
+    } else {
+        format!("HTTP error: {status}: {snippet}")
+    }
+}
+
+/// Only safe methods may be sent again; extism treats a missing method as GET.
+fn is_idempotent(request: &HttpRequest) -> bool {
+    match request.method.as_deref() {
+        None => true,
+        Some(method) => matches!(method.to_ascii_uppercase().as_str(), "GET" | "HEAD"),
+    }
+}
+
+/// Sends `request` until `should_retry` rejects the outcome or `max_retries` extra
+/// attempts are spent. Non-idempotent requests are always sent exactly once.
+fn send_with_retries<R>(
+    request: &HttpRequest,
+    max_retries: u32,
+    mut send: impl FnMut(&HttpRequest) -> R,
+    should_retry: impl Fn(&R) -> bool,
+) -> R {
+    let retries = if is_idempotent(request) {
+        max_retries
+    } else {
+        0
+    };
+    let mut outcome = send(request);
+    for _ in 0..retries {
+        if !should_retry(&outcome) {
+            break;
+        }
+        outcome = send(request);
+    }
+    outcome
+}
+
+/// Transport failures, rate limiting and server errors may succeed on a second try.
+fn is_transient_failure(outcome: &Result<HttpResponse, extism_pdk::Error>) -> bool {
+    match outcome {
+        Ok(res) => is_retryable_status(res.status_code(), res.headers()),
+        Err(_) => true,
+    }
+}
+
+/// 429 and 5xx responses, unless they carry a `Retry-After`: the plugin cannot sleep, so
+/// an immediate retry would land inside the window the server asked us to wait out.
+fn is_retryable_status(status: u16, headers: &HashMap<String, String>) -> bool {
+    (status == 429 || status >= 500) && find_header(headers, "retry-after").is_none()
+}
+
+/// Sends a GET through the shared request path: records the endpoint, counts every
+/// attempt against the budget, retries per `max_retries` and tracks the rate limit.
+fn send_get(
+    request: &HttpRequest,
+    context: &LookupContext,
+) -> Result<HttpResponse, extism_pdk::Error> {
+    context
+        .endpoint
+        .borrow_mut()
+        .get_or_insert_with(|| request.url.clone());
+    let res = send_with_retries(
+        request,
+        context.options.max_retries.unwrap_or(0),
+        |request| {
+            context.requests.set(context.requests.get() + 1);
+            http::request::<Vec<u8>>(request, None)
         },
+        is_transient_failure,
+    );
+    if let Some(rate_limit) = res
+        .as_ref()
+        .ok()
+        .and_then(|res| parse_rate_limit(res.headers()))
+    {
+        context.rate_limit.set(Some(rate_limit));
+    }
+    res
+}
+
+fn execute_get<T: DeserializeOwned>(url: String, context: &LookupContext) -> FnResult<T> {
+    let cached = if context.options.caching {
+        load_cached_response(&url)
+    } else {
+        None
+    };
+    let request = build_http_request(
+        url.clone(),
+        cached.as_ref().map(|cached| cached.etag.as_str()),
+    );
+
+    match send_get(&request, context) {
+        Ok(res)
+            if (res.status_code() >= 200 && res.status_code() < 300)
+                || res.status_code() == 304 =>
+        {
+            let status = res.status_code();
+            let etag = res
+                .headers()
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case("etag"))
+                .map(|(_, value)| value.clone());
+            let Some(body) = resolve_response_body(status, res.body(), cached) else {
+                log!(
+                    LogLevel::Error,
+                    "OpenLibrary returned 304 without a cached body for {}",
+                    url
+                );
+                return Err(WithReturnCode::new(
+                    extism_pdk::Error::msg("HTTP error: 304"),
+                    500,
+                ));
+            };
+
+            match serde_json::from_slice::<T>(&body) {
+                Ok(parsed) => {
+                    if context.options.caching && status != 304 {
+                        if let Some(etag) = etag {
+                            store_cached_response(&url, etag, body);
+                        }
+                    }
+                    Ok(parsed)
+                }
+                Err(e) => {
+                    log!(LogLevel::Error, "OpenLibrary JSON parse error: {}", e);
+                    Err(WithReturnCode::new(e.into(), 500))
+                }
+            }
+        }
         Ok(res) => {
+            let body = res.body();
             log!(
                 LogLevel::Error,
                 "OpenLibrary HTTP error {}: {}",
                 res.status_code(),
-                String::from_utf8_lossy(&res.body())
+                String::from_utf8_lossy(&body)
             );
             Err(WithReturnCode::new(
-                extism_pdk::Error::msg(format!("HTTP error: {}", res.status_code())),
+                extism_pdk::Error::msg(http_error_message(
+                    res.status_code(),
+                    &body,
+                    context.options.verbose_errors,
+                )),
                 res.status_code() as i32,
             ))
         }
@@ -108,244 +402,3150 @@ fn execute_get<T: DeserializeOwned>(url: String) -> FnResult<T> {
     }
 }
 
-fn fetch_by_isbn(isbn13: &str) -> FnResult<Vec<OpenLibraryBookRecord>> {
-    let edition: OpenLibraryEditionResponse = execute_get(build_isbn_url(isbn13))?;
-    Ok(vec![book_record_from_edition_response(&edition)])
+/// Records fetched directly by identifier are exact matches.
+fn resolved_exactly(
+    mut record: OpenLibraryBookRecord,
+    resolved_by: ResolvedBy,
+) -> OpenLibraryBookRecord {
+    record.confidence = Some(1.0);
+    record.resolved_by = Some(resolved_by);
+    record
 }
 
-fn fetch_by_edition(edition_id: &str) -> FnResult<Vec<OpenLibraryBookRecord>> {
-    let edition: OpenLibraryEditionResponse = execute_get(build_edition_url(edition_id))?;
-    Ok(vec![book_record_from_edition_response(&edition)])
+/// Fills an edition's empty author list from its work, resolving each author's name.
+/// Authors whose fetch fails or who have no name are left out.
+fn attach_work_authors(
+    record: &mut OpenLibraryBookRecord,
+    work: &OpenLibraryWorkResponse,
+    mut fetch_author: impl FnMut(&str) -> Option<OpenLibraryAuthorResponse>,
+) {
+    if !record.authors.is_empty() {
+        return;
+    }
+
+    for author_id in work_author_ids(work) {
+        let name = fetch_author(&author_id)
+            .and_then(|author| author.name)
+            .map(|name| name.trim().to_string())
+            .filter(|name| !name.is_empty());
+        if let Some(name) = name {
+            record.authors.push(name);
+            record.author_keys.push(author_id);
+        }
+    }
 }
 
-fn fetch_by_work(work_id: &str) -> FnResult<Vec<OpenLibraryBookRecord>> {
-    let work: OpenLibraryWorkResponse = execute_get(build_work_url(work_id))?;
-    let editions: OpenLibraryWorkEditionsResponse = execute_get(build_work_editions_url(work_id))?;
-    let merged = merge_work_with_edition(
-        book_record_from_work_response(&work),
-        first_record_from_work_editions(&editions),
+/// Redirect hops followed for merged authors before giving up.
+const MAX_AUTHOR_REDIRECTS: usize = 2;
+
+fn fetch_author(author_id: &str, context: &LookupContext) -> Option<OpenLibraryAuthorResponse> {
+    let mut author_id = author_id.to_string();
+    for _ in 0..=MAX_AUTHOR_REDIRECTS {
+        let response = fetch_author_record(&author_id, context)?;
+        match author_redirect_target(&response) {
+            Some(target) => author_id = target,
+            None => return Some(response),
+        }
+    }
+    log!(
+        LogLevel::Warn,
+        "OpenLibrary author {} redirects too many times",
+        author_id
     );
-    Ok(vec![merged])
+    None
 }
 
-fn fetch_by_search(search: &str) -> FnResult<Vec<OpenLibraryBookRecord>> {
-    let response: OpenLibrarySearchResponse = execute_get(build_search_url(search))?;
-    Ok(response
-        .docs
-        .iter()
-        .filter_map(book_record_from_search_doc)
-        .collect())
+fn fetch_author_record(
+    author_id: &str,
+    context: &LookupContext,
+) -> Option<OpenLibraryAuthorResponse> {
+    if !context.has_budget() {
+        return None;
+    }
+    match execute_get::<OpenLibraryAuthorResponse>(build_author_url(author_id), context) {
+        Ok(response) => Some(response),
+        Err(e) => {
+            log!(
+                LogLevel::Warn,
+                "OpenLibrary author {} unavailable: {}",
+                author_id,
+                e.0
+            );
+            None
+        }
+    }
 }
 
-fn normalize_exact_isbn_search(value: &str) -> Option<String> {
-    let trimmed = value.trim();
-    if trimmed.is_empty() {
-        return None;
+/// Follows the edition's first work when `enrich_from_work` or `authors_from_work` is
+/// set. Failing to fetch the work keeps the edition as is.
+fn enrich_from_work(
+    record: OpenLibraryBookRecord,
+    context: &LookupContext,
+) -> OpenLibraryBookRecord {
+    let options = &context.options;
+    let wants_authors = options.authors_from_work() && record.authors.is_empty();
+    if !(options.enrich_from_work || wants_authors) || !context.has_budget() {
+        return record;
     }
+    let Some(work_id) = record.work_id.clone() else {
+        return record;
+    };
 
-    let compact: String = trimmed
-        .chars()
-        .filter(|c| *c != '-' && !c.is_ascii_whitespace())
-        .collect();
+    match execute_get::<OpenLibraryWorkResponse>(build_work_url(&work_id), context) {
+        Ok(work) => {
+            let mut record = if options.enrich_from_work {
+                enrich_edition_with_work(record, &work)
+            } else {
+                record
+            };
+            if wants_authors {
+                attach_work_authors(&mut record, &work, |author_id| {
+                    fetch_author(author_id, context)
+                });
+            }
+            record
+        }
+        Err(e) => {
+            log!(
+                LogLevel::Warn,
+                "OpenLibrary work {} unavailable for enrichment: {}",
+                work_id,
+                e.0
+            );
+            record
+        }
+    }
+}
 
-    if compact.len() == 13 && compact.chars().all(|c| c.is_ascii_digit()) {
-        return Some(compact);
+fn fetch_by_isbn(isbn13: &str, context: &LookupContext) -> FnResult<Vec<OpenLibraryBookRecord>> {
+    let edition: OpenLibraryEditionResponse = execute_get(build_isbn_url(isbn13), context)?;
+    if context.options.strict_isbn_match && !edition_has_isbn(&edition, isbn13) {
+        log!(
+            LogLevel::Warn,
+            "OpenLibrary edition {} does not list ISBN {}",
+            edition.key,
+            isbn13
+        );
+        return Ok(vec![]);
     }
+    let record = enrich_from_work(book_record_from_edition_response(&edition), context);
+    split_ambiguous_works(
+        resolved_exactly(record, ResolvedBy::Isbn),
+        context.options.on_ambiguous,
+        |work_id| fetch_alternate_work(work_id, context),
+    )
+}
 
-    if compact.len() != 10 {
+fn fetch_by_edition(
+    edition_id: &str,
+    context: &LookupContext,
+) -> FnResult<Vec<OpenLibraryBookRecord>> {
+    let edition: OpenLibraryEditionResponse = execute_get(build_edition_url(edition_id), context)?;
+    let record = enrich_from_work(book_record_from_edition_response(&edition), context);
+    split_ambiguous_works(
+        resolved_exactly(record, ResolvedBy::Edition),
+        context.options.on_ambiguous,
+        |work_id| fetch_alternate_work(work_id, context),
+    )
+}
+
+/// A work linked by an ambiguous edition, as its own record. `None` when the budget is
+/// spent or the work is unavailable.
+fn fetch_alternate_work(work_id: &str, context: &LookupContext) -> Option<OpenLibraryBookRecord> {
+    if !context.has_budget() {
         return None;
     }
+    let work: OpenLibraryWorkResponse = execute_get(build_work_url(work_id), context).ok()?;
+    let mut record = book_record_from_work_response(&work);
+    if context.options.authors_from_work() {
+        attach_work_authors(&mut record, &work, |author_id| {
+            fetch_author(author_id, context)
+        });
+    }
+    Some(record)
+}
+
+/// Applies `on_ambiguous` to an edition linking several works. In `All` mode the edition
+/// stays with its first work and every other work is built from its own work response;
+/// works that cannot be fetched are left out.
+fn split_ambiguous_works(
+    record: OpenLibraryBookRecord,
+    mode: AmbiguityMode,
+    mut fetch_work: impl FnMut(&str) -> Option<OpenLibraryBookRecord>,
+) -> FnResult<Vec<OpenLibraryBookRecord>> {
+    if record.work_ids.len() < 2 {
+        return Ok(vec![record]);
+    }
+    match mode {
+        AmbiguityMode::First => Ok(vec![record]),
+        AmbiguityMode::Error => Err(WithReturnCode::new(
+            extism_pdk::Error::msg(format!(
+                "OpenLibrary edition {} links several works: {}",
+                record.edition_id.as_deref().unwrap_or("?"),
+                record.work_ids.join(", ")
+            )),
+            409,
+        )),
+        AmbiguityMode::All => {
+            let first_work = record
+                .work_id
+                .clone()
+                .unwrap_or_else(|| record.work_ids[0].clone());
+            let alternates: Vec<OpenLibraryBookRecord> = record
+                .work_ids
+                .iter()
+                .filter(|work_id| **work_id != first_work)
+                .filter_map(|work_id| {
+                    let mut alternate = fetch_work(work_id)?;
+                    alternate.work_id = Some(work_id.clone());
+                    alternate.work_ids = vec![work_id.clone()];
+                    alternate.resolved_by = record.resolved_by;
+                    alternate.confidence = record.confidence;
+                    Some(alternate)
+                })
+                .collect();
+            let primary = OpenLibraryBookRecord {
+                work_id: Some(first_work.clone()),
+                work_ids: vec![first_work],
+                ..record
+            };
+            Ok(std::iter::once(primary).chain(alternates).collect())
+        }
+    }
+}
 
-    let mut chars = compact.chars();
-    let last = chars.next_back()?;
-    let body = chars.as_str();
-    if body.chars().all(|c| c.is_ascii_digit())
-        && (last.is_ascii_digit() || last == 'X' || last == 'x')
+fn fetch_by_work(work_id: &str, context: &LookupContext) -> FnResult<Vec<OpenLibraryBookRecord>> {
+    if context.options.all_editions {
+        return fetch_editions_by_work(work_id, EDITION_CANDIDATE_LIMIT, context);
+    }
+    let work: OpenLibraryWorkResponse = execute_get(build_work_url(work_id), context)?;
+    let work_record = book_record_from_work_response(&work);
+    let preference = context.options.edition_preference;
+    let preferred_country = context.options.preferred_country.as_deref();
+    let language_chain = &context.options.language_fallback_chain;
+    let preferred_binding = context.options.preferred_binding;
+    let limit = if preference == EditionPreference::First
+        && preferred_country.is_none()
+        && preferred_binding.is_none()
+        && language_chain.is_empty()
     {
-        return Some(format!("{body}{}", last.to_ascii_uppercase()));
+        1
+    } else {
+        EDITION_CANDIDATE_LIMIT
+    };
+    let editions: OpenLibraryWorkEditionsResponse =
+        execute_get(build_work_editions_url(work_id, limit), context)?;
+    let edition = select_edition_from_work_editions(
+        &editions,
+        preference,
+        work_record.publish_year,
+        language_chain,
+        preferred_country,
+        preferred_binding,
+    );
+    let sources = context
+        .options
+        .debug_sources
+        .then(|| work_sources(&work_record, edition.as_ref()));
+    let mut merged =
+        merge_work_with_edition(work_record, edition, context.options.cover_preference);
+    merged.edition_count = work_edition_total(&editions).or(merged.edition_count);
+    if context.options.authors_from_work() {
+        attach_work_authors(&mut merged, &work, |author_id| {
+            fetch_author(author_id, context)
+        });
     }
+    merged.debug_sources = sources;
+    Ok(vec![resolved_exactly(merged, ResolvedBy::Work)])
+}
 
-    None
+/// Up to `limit` editions of a work, each merged with the work. The work's authors are
+/// resolved once and shared by every edition.
+fn fetch_editions_by_work(
+    work_id: &str,
+    limit: u32,
+    context: &LookupContext,
+) -> FnResult<Vec<OpenLibraryBookRecord>> {
+    let work: OpenLibraryWorkResponse = execute_get(build_work_url(work_id), context)?;
+    let mut work_record = book_record_from_work_response(&work);
+    if context.options.authors_from_work() {
+        attach_work_authors(&mut work_record, &work, |author_id| {
+            fetch_author(author_id, context)
+        });
+    }
+    let editions: OpenLibraryWorkEditionsResponse =
+        execute_get(build_work_editions_url(work_id, limit), context)?;
+    Ok(
+        records_from_work_editions(&work_record, &editions, context.options.cover_preference)
+            .into_iter()
+            .map(|mut record| {
+                record.edition_count = work_edition_total(&editions).or(record.edition_count);
+                resolved_exactly(record, ResolvedBy::Work)
+            })
+            .collect(),
+    )
 }
 
-fn deduplicate_records(records: Vec<OpenLibraryBookRecord>) -> Vec<OpenLibraryBookRecord> {
-    let mut seen = HashSet::new();
-    let mut deduped = Vec::new();
+/// `_sources` for a work lookup: the work and the edition merged into it, before merging.
+fn work_sources(
+    work: &OpenLibraryBookRecord,
+    edition: Option<&OpenLibraryBookRecord>,
+) -> serde_json::Value {
+    serde_json::json!({
+        "work": record_source_summary(work),
+        "edition": edition.map(record_source_summary),
+    })
+}
 
-    for record in records {
-        if seen.insert(record.dedup_key()) {
-            deduped.push(record);
+/// A stale or merged work id 404s even when searching the book name would find the
+/// renamed work, so a 404 is retried as a search when enabled and a usable name exists.
+fn with_name_fallback(
+    result: FnResult<Vec<OpenLibraryBookRecord>>,
+    name: Option<&str>,
+    enabled: bool,
+    search: impl FnOnce(&str) -> FnResult<Vec<OpenLibraryBookRecord>>,
+) -> FnResult<Vec<OpenLibraryBookRecord>> {
+    match (result, name) {
+        (Err(error), Some(name)) if enabled && error.1 == 404 && is_searchable_name(name) => {
+            search(name)
         }
+        (result, _) => result,
     }
+}
 
-    deduped
+/// Runs an identifier search (`id_wikidata:`, `id_goodreads:`) and keeps the first match.
+fn fetch_first_search_match(
+    url: String,
+    not_found: String,
+    resolved_by: ResolvedBy,
+    context: &LookupContext,
+) -> FnResult<Vec<OpenLibraryBookRecord>> {
+    let response: OpenLibrarySearchResponse = execute_get(url, context)?;
+    match response.docs.iter().find_map(book_record_from_search_doc) {
+        Some(record) => Ok(vec![resolved_exactly(record, resolved_by)]),
+        None => Err(WithReturnCode::new(extism_pdk::Error::msg(not_found), 404)),
+    }
 }
 
-fn deduplicate_images(images: Vec<ExternalImage>) -> Vec<ExternalImage> {
-    let mut seen_urls = HashSet::new();
-    let mut deduped = Vec::new();
+fn fetch_by_wikidata(qid: &str, context: &LookupContext) -> FnResult<Vec<OpenLibraryBookRecord>> {
+    fetch_first_search_match(
+        build_wikidata_search_url(qid),
+        format!("No OpenLibrary book for Wikidata {qid}"),
+        ResolvedBy::Wikidata,
+        context,
+    )
+}
 
-    for image in images {
-        if seen_urls.insert(image.url.url.clone()) {
-            deduped.push(image);
-        }
+fn fetch_by_goodreads(
+    goodreads_id: &str,
+    context: &LookupContext,
+) -> FnResult<Vec<OpenLibraryBookRecord>> {
+    let mut records = fetch_first_search_match(
+        build_goodreads_search_url(goodreads_id),
+        format!("No OpenLibrary book for Goodreads {goodreads_id}"),
+        ResolvedBy::Goodreads,
+        context,
+    )?;
+    for record in records.iter_mut() {
+        record.goodreads_id = Some(goodreads_id.to_string());
     }
-
-    deduped
+    Ok(records)
 }
 
-fn lookup_book_records(lookup: &RsLookupWrapper) -> FnResult<Vec<OpenLibraryBookRecord>> {
-    let Some(mut ids) = extract_book_ids(&lookup.query) else {
-        return Ok(vec![]);
+/// Lookups tried while recovering a malformed ISBN; further candidates are ignored.
+const MAX_FUZZY_ISBN_LOOKUPS: usize = 5;
+
+/// A recovered ISBN is a guess: any existing neighbour is a real, possibly different book.
+const FUZZY_ISBN_CONFIDENCE: f64 = 0.5;
+
+/// The raw ISBN id, else the book name, when it has the shape of a near-ISBN.
+fn near_isbn_input(query: &RsLookupQuery) -> Option<String> {
+    let RsLookupQuery::Book(book) = query else {
+        return None;
     };
+    book.ids
+        .as_ref()
+        .and_then(|ids| ids.isbn13.clone())
+        .into_iter()
+        .chain(book.name.clone())
+        .find(|value| !fuzzy_isbn13_candidates(value).is_empty())
+}
 
-    if ids.isbn13.is_none() {
-        if let RsLookupQuery::Book(book) = &lookup.query {
-            if let Some(name) = book.name.as_deref() {
-                ids.isbn13 = normalize_exact_isbn_search(name);
+/// Looks up candidates in order until one exists. Not-found candidates are skipped; any
+/// other error stops the recovery. Hits are tagged `FuzzyIsbn` with a reduced confidence.
+fn recover_fuzzy_isbn(
+    value: &str,
+    mut fetch: impl FnMut(&str) -> FnResult<Vec<OpenLibraryBookRecord>>,
+) -> FnResult<Vec<OpenLibraryBookRecord>> {
+    for candidate in fuzzy_isbn13_candidates(value)
+        .iter()
+        .take(MAX_FUZZY_ISBN_LOOKUPS)
+    {
+        match fetch(candidate) {
+            Ok(records) if !records.is_empty() => {
+                return Ok(records
+                    .into_iter()
+                    .map(|mut record| {
+                        record.resolved_by = Some(ResolvedBy::FuzzyIsbn);
+                        record.confidence = Some(FUZZY_ISBN_CONFIDENCE);
+                        record
+                    })
+                    .collect())
             }
+            Ok(_) => {}
+            Err(error) if error.1 == 404 => {}
+            Err(error) => return Err(error),
         }
     }
+    Err(WithReturnCode::new(
+        extism_pdk::Error::msg(format!("No ISBN recovered from {value}")),
+        404,
+    ))
+}
 
-    let records = if let Some(isbn13) = ids.isbn13 {
-        fetch_by_isbn(&isbn13)?
-    } else if let Some(edition_id) = ids.edition_id {
-        fetch_by_edition(&edition_id)?
-    } else if let Some(work_id) = ids.work_id {
-        fetch_by_work(&work_id)?
+fn fetch_by_fuzzy_isbn(
+    value: &str,
+    context: &LookupContext,
+) -> FnResult<Vec<OpenLibraryBookRecord>> {
+    recover_fuzzy_isbn(value, |candidate| {
+        if !context.has_budget() {
+            return Ok(vec![]);
+        }
+        fetch_by_isbn(candidate, context)
+    })
+}
+
+const MAX_SEARCH_PAGES: u32 = 5;
+
+fn fetch_by_search(search: &str, context: &LookupContext) -> FnResult<Vec<OpenLibraryBookRecord>> {
+    let records = if context.options.search_inside {
+        fetch_by_search_inside(search, context)?
     } else {
-        let search = match &lookup.query {
-            RsLookupQuery::Book(book) => book.name.as_deref(),
-            _ => None,
+        let min_results = context.options.min_results.unwrap_or(0);
+        let limit = context.options.search_limit();
+        let plain = || {
+            collect_search_pages(min_results, |page| {
+                execute_get(build_search_url(search, limit, page), context)
+            })
         };
-
-        match search {
-            Some(name) if !name.trim().is_empty() => fetch_by_search(name)?,
-            _ => {
-                return Err(WithReturnCode::new(
-                    extism_pdk::Error::msg("Not supported"),
-                    404,
-                ));
-            }
+        match context.options.author.as_deref() {
+            Some(author) => broaden_if_few(
+                collect_search_pages(min_results, |page| {
+                    execute_get(
+                        build_fielded_search_url(search, author, limit, page),
+                        context,
+                    )
+                }),
+                min_results,
+                context.options.broaden_if_few,
+                plain,
+            )?,
+            None => plain()?,
         }
     };
 
-    Ok(deduplicate_records(records))
+    let records = retain_isbn_records(
+        score_search_records(records, search, !context.options.search_inside),
+        context.options.require_isbn,
+    );
+    let records = retain_title_matches(records, search, context.options.require_all_tokens);
+    Ok(sort_records(records, context.options.sort))
 }
 
-fn lookup_book_records_for_images(
-    lookup: &RsLookupWrapper,
-) -> FnResult<Vec<OpenLibraryBookRecord>> {
-    let Some(mut ids) = extract_book_ids(&lookup.query) else {
-        return Ok(vec![]);
-    };
+fn sort_records(
+    mut records: Vec<OpenLibraryBookRecord>,
+    order: SortOrder,
+) -> Vec<OpenLibraryBookRecord> {
+    if order == SortOrder::EditionsDesc {
+        // Stable, so equal counts keep the search ranking.
+        records.sort_by_key(|record| std::cmp::Reverse(record.edition_count));
+    }
+    records
+}
+
+/// Drops records whose title misses a significant query token when `require_all_tokens`
+/// is set.
+fn retain_title_matches(
+    records: Vec<OpenLibraryBookRecord>,
+    search: &str,
+    require_all_tokens: bool,
+) -> Vec<OpenLibraryBookRecord> {
+    if !require_all_tokens {
+        return records;
+    }
+    records
+        .into_iter()
+        .filter(|record| title_contains_query_tokens(&record.title, search))
+        .collect()
+}
 
-    if ids.isbn13.is_none() {
-        if let RsLookupQuery::Book(book) = &lookup.query {
-            if let Some(name) = book.name.as_deref() {
-                ids.isbn13 = normalize_exact_isbn_search(name);
+/// Tags search results with their lookup path and, for metadata searches, a confidence
+/// they are then ranked by (see `compare_search_records`).
+fn score_search_records(
+    records: Vec<OpenLibraryBookRecord>,
+    search: &str,
+    with_confidence: bool,
+) -> Vec<OpenLibraryBookRecord> {
+    let mut records: Vec<OpenLibraryBookRecord> = records
+        .into_iter()
+        .map(|mut record| {
+            if with_confidence {
+                record.confidence = Some(search_confidence(&record, search));
             }
-        }
+            record.resolved_by = Some(ResolvedBy::Search);
+            record
+        })
+        .collect();
+    if with_confidence {
+        records.sort_by(compare_search_records);
     }
+    records
+}
 
-    if ids.isbn13.is_some() || ids.edition_id.is_some() || ids.work_id.is_some() {
-        let mut records = Vec::new();
+/// Highest confidence first. Ties go to the record with a cover, then an ISBN, then a
+/// work id, then the lowest work id, so the top result doesn't depend on search order.
+fn compare_search_records(
+    left: &OpenLibraryBookRecord,
+    right: &OpenLibraryBookRecord,
+) -> std::cmp::Ordering {
+    let confidence = |record: &OpenLibraryBookRecord| record.confidence.unwrap_or(0.0);
+    confidence(right)
+        .total_cmp(&confidence(left))
+        .then_with(|| right.cover_id.is_some().cmp(&left.cover_id.is_some()))
+        .then_with(|| right.isbn13.is_some().cmp(&left.isbn13.is_some()))
+        .then_with(|| right.work_id.is_some().cmp(&left.work_id.is_some()))
+        .then_with(|| left.work_id.cmp(&right.work_id))
+}
 
-        if let Some(isbn13) = ids.isbn13.as_deref() {
-            records.extend(fetch_by_isbn(isbn13)?);
-        }
-        if let Some(edition_id) = ids.edition_id.as_deref() {
-            records.extend(fetch_by_edition(edition_id)?);
-        }
-        if let Some(work_id) = ids.work_id.as_deref() {
-            records.extend(fetch_by_work(work_id)?);
-        }
+/// Drops records without an ISBN-13 when `require_isbn` is set.
+fn retain_isbn_records(
+    records: Vec<OpenLibraryBookRecord>,
+    require_isbn: bool,
+) -> Vec<OpenLibraryBookRecord> {
+    if !require_isbn {
+        return records;
+    }
+    records
+        .into_iter()
+        .filter(|record| record.isbn13.is_some())
+        .collect()
+}
+
+/// Full-text search: hits are editions whose scanned contents match the phrase.
+fn fetch_by_search_inside(
+    search: &str,
+    context: &LookupContext,
+) -> FnResult<Vec<OpenLibraryBookRecord>> {
+    let response: OpenLibrarySearchInsideResponse =
+        execute_get(build_search_inside_url(search), context)?;
+    Ok(response
+        .hits
+        .hits
+        .iter()
+        .filter_map(book_record_from_search_inside_hit)
+        .collect())
+}
 
+/// With `broaden_if_few`, a fielded search keeping fewer than `min_results` records (or
+/// none, without `min_results`) is merged with the plain search and deduped, which
+/// recovers books whose author hint was slightly off.
+fn broaden_if_few(
+    fielded: FnResult<Vec<OpenLibraryBookRecord>>,
+    min_results: usize,
+    enabled: bool,
+    plain: impl FnOnce() -> FnResult<Vec<OpenLibraryBookRecord>>,
+) -> FnResult<Vec<OpenLibraryBookRecord>> {
+    let records = fielded?;
+    if !enabled || deduplicate_records(records.clone()).len() >= min_results.max(1) {
         return Ok(records);
     }
-
-    lookup_book_records(lookup)
+    let mut combined = records;
+    combined.extend(plain()?);
+    Ok(deduplicate_records(combined))
 }
 
-#[plugin_fn]
-pub fn lookup_metadata(
-    Json(lookup): Json<RsLookupWrapper>,
-) -> FnResult<Json<Vec<RsLookupMetadataResultWrapper>>> {
-    let all_books = lookup_book_records(&lookup)?;
+/// Fetches search pages until `min_results` records survive dedup, the result set is
+/// exhausted or `MAX_SEARCH_PAGES` is reached. A `min_results` of 0 fetches one page.
+fn collect_search_pages(
+    min_results: usize,
+    mut fetch_page: impl FnMut(u32) -> FnResult<OpenLibrarySearchResponse>,
+) -> FnResult<Vec<OpenLibraryBookRecord>> {
+    let mut records = Vec::new();
+    let mut fetched_docs: u64 = 0;
+    let mut page = 1;
 
-    let results: Vec<RsLookupMetadataResultWrapper> = all_books
-        .into_iter()
-        .map(openlibrary_book_to_result)
-        .collect();
+    loop {
+        let response = fetch_page(page)?;
+        fetched_docs += response.docs.len() as u64;
+        let page_was_empty = response.docs.is_empty();
+        records.extend(response.docs.iter().filter_map(book_record_from_search_doc));
+
+        let has_more = response
+            .num_found
+            .is_some_and(|num_found| fetched_docs < num_found);
+        if page_was_empty
+            || !has_more
+            || page >= MAX_SEARCH_PAGES
+            || deduplicate_records(records.clone()).len() >= min_results
+        {
+            break;
+        }
+        page += 1;
+    }
 
-    Ok(Json(results))
+    Ok(records)
 }
 
-#[plugin_fn]
-pub fn lookup_metadata_images(
-    Json(lookup): Json<RsLookupWrapper>,
-) -> FnResult<Json<Vec<ExternalImage>>> {
-    let all_books = lookup_book_records_for_images(&lookup)?;
+fn fetch_rating(url: String, context: &LookupContext) -> Option<OpenLibraryRating> {
+    if !context.has_budget() {
+        return None;
+    }
+    match execute_get::<OpenLibraryRatingsResponse>(url, context) {
+        Ok(response) => rating_from_response(&response),
+        Err(_) => None,
+    }
+}
 
-    let images: Vec<ExternalImage> = all_books
-        .into_iter()
-        .flat_map(|book| openlibrary_book_to_images(&book))
-        .collect();
+/// Fetches edition ratings and falls back to the work ratings when the edition has none.
+fn enrich_ratings(records: &mut [OpenLibraryBookRecord], context: &LookupContext) {
+    if !context.options.include_ratings {
+        return;
+    }
 
-    Ok(Json(deduplicate_images(images)))
+    for record in records.iter_mut() {
+        if let Some(edition_id) = record.edition_id.as_deref() {
+            record.edition_rating = fetch_rating(build_edition_ratings_url(edition_id), context);
+        }
+        if record.edition_rating.is_none() {
+            if let Some(work_id) = record.work_id.as_deref() {
+                record.work_rating = fetch_rating(build_work_ratings_url(work_id), context);
+            }
+        }
+    }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use rs_plugin_common_interfaces::{domain::rs_ids::RsIds, lookup::RsLookupBook};
-
-    #[test]
-    fn book_query_extracts_ids() {
-        let query = RsLookupQuery::Book(RsLookupBook {
-            name: None,
-            ids: Some(RsIds {
-                isbn13: Some("9780140328721".to_string()),
-                openlibrary_edition_id: Some("/books/OL7353617M".to_string()),
-                openlibrary_work_id: Some("works/OL45804W".to_string()),
-                ..Default::default()
-            }),
-        });
+/// Cap on covers gathered per work by `all_edition_covers`.
+const MAX_EDITION_COVERS: usize = 30;
 
-        let ids = extract_book_ids(&query).expect("Expected ids");
-        assert_eq!(ids.isbn13, Some("9780140328721".to_string()));
-        assert_eq!(ids.edition_id, Some("OL7353617M".to_string()));
-        assert_eq!(ids.work_id, Some("OL45804W".to_string()));
+/// Adds the covers of every edition of each work to the image lookup. Each work's
+/// editions are fetched once; a failed fetch keeps the record's own covers.
+fn gather_edition_covers(records: &mut [OpenLibraryBookRecord], context: &LookupContext) {
+    if !context.options.all_edition_covers {
+        return;
     }
 
-    #[test]
-    fn normalize_exact_isbn_search_accepts_isbn13() {
+    let mut editions_by_work: HashMap<String, Option<OpenLibraryWorkEditionsResponse>> =
+        HashMap::new();
+    for record in records.iter_mut() {
+        let Some(work_id) = record.work_id.clone() else {
+            continue;
+        };
+        if !editions_by_work.contains_key(&work_id) {
+            if !context.has_budget() {
+                break;
+            }
+            let editions = execute_get(
+                build_work_editions_url(&work_id, EDITION_CANDIDATE_LIMIT),
+                context,
+            );
+            if let Err(e) = &editions {
+                log!(
+                    LogLevel::Warn,
+                    "OpenLibrary editions of {} unavailable for covers: {}",
+                    work_id,
+                    e.0
+                );
+            }
+            editions_by_work.insert(work_id.clone(), editions.ok());
+        }
+        if let Some(Some(editions)) = editions_by_work.get(&work_id) {
+            merge_edition_covers(record, editions, MAX_EDITION_COVERS);
+        }
+    }
+}
+
+/// Covers ranked per record; the rest keep their position after the ranked ones.
+const COVER_RANKING_CANDIDATES: usize = 5;
+
+/// Moves the largest covers (by stored pixel area) to the front so the primary cover is
+/// the best scan. Covers without metadata keep their relative order behind the ranked ones.
+/// The primary's recorded source, if any, becomes `cover_source`.
+fn rank_record_covers(
+    record: &mut OpenLibraryBookRecord,
+    mut fetch_metadata: impl FnMut(u64) -> Option<OpenLibraryCoverMetadata>,
+) {
+    if record.cover_ids.is_empty() {
+        return;
+    }
+
+    let mut ranked: Vec<(u64, u64)> = Vec::new();
+    let mut unranked: Vec<u64> = Vec::new();
+    let mut sources: HashMap<u64, String> = HashMap::new();
+    for (index, cover_id) in record.cover_ids.iter().copied().enumerate() {
+        let metadata = (index < COVER_RANKING_CANDIDATES)
+            .then(|| fetch_metadata(cover_id))
+            .flatten();
+        if let Some(source) = metadata
+            .as_ref()
+            .and_then(|metadata| metadata.source.as_deref())
+            .map(str::trim)
+            .filter(|source| !source.is_empty())
+        {
+            sources.insert(cover_id, source.to_string());
+        }
+        let area = metadata.and_then(|metadata| Some(metadata.width? * metadata.height?));
+        match area {
+            Some(area) => ranked.push((cover_id, area)),
+            None => unranked.push(cover_id),
+        }
+    }
+    ranked.sort_by_key(|(_, area)| std::cmp::Reverse(*area));
+
+    record.cover_ids = ranked
+        .into_iter()
+        .map(|(cover_id, _)| cover_id)
+        .chain(unranked)
+        .collect();
+    record.cover_id = record.cover_ids.first().copied();
+    record.cover_source = record
+        .cover_id
+        .and_then(|cover_id| sources.remove(&cover_id));
+}
+
+fn rank_covers(records: &mut [OpenLibraryBookRecord], context: &LookupContext) {
+    if !context.options.rank_covers {
+        return;
+    }
+
+    for record in records.iter_mut() {
+        rank_record_covers(record, |cover_id| {
+            if !context.has_budget() {
+                return None;
+            }
+            execute_get(build_cover_metadata_url(cover_id), context).ok()
+        });
+    }
+}
+
+/// Fetches each distinct author key once. An author whose fetch fails (`None`) simply gets
+/// no details; the name already on the record is kept either way.
+fn resolve_author_details(
+    records: &mut [OpenLibraryBookRecord],
+    mut fetch_author: impl FnMut(&str) -> Option<OpenLibraryAuthorResponse>,
+) {
+    let mut resolved = HashMap::new();
+
+    for record in records.iter_mut() {
+        for author_key in record.author_keys.clone() {
+            let details = resolved
+                .entry(author_key.clone())
+                .or_insert_with(|| {
+                    fetch_author(&author_key)
+                        .map(|response| author_details_from_response(&author_key, &response))
+                })
+                .clone();
+
+            if let Some(details) = details {
+                if !record.author_details.contains(&details) {
+                    record.author_details.push(details);
+                }
+            }
+        }
+    }
+}
+
+/// Distinct subjects fetched by one lookup's subject enrichment, across all records.
+const MAX_SUBJECT_ENRICHMENT: usize = 10;
+
+/// Fetches work counts for the first `limit` subjects of each record, each distinct
+/// subject once and at most `MAX_SUBJECT_ENRICHMENT` in total. Subjects whose fetch
+/// fails (`None`) or falls past the cap get no count.
+fn resolve_subject_work_counts(
+    records: &mut [OpenLibraryBookRecord],
+    limit: usize,
+    mut fetch_subject: impl FnMut(&str) -> Option<OpenLibrarySubjectResponse>,
+) {
+    let mut resolved: HashMap<String, Option<u64>> = HashMap::new();
+
+    for record in records.iter_mut() {
+        let keys: Vec<String> = record
+            .subjects
+            .iter()
+            .map(|subject| openlibrary_subject_key(subject))
+            .filter(|key| !key.is_empty())
+            .take(limit.min(MAX_SUBJECT_ENRICHMENT))
+            .collect();
+
+        for key in keys {
+            if !resolved.contains_key(&key) && resolved.len() >= MAX_SUBJECT_ENRICHMENT {
+                continue;
+            }
+            let work_count = *resolved
+                .entry(key.clone())
+                .or_insert_with(|| fetch_subject(&key).and_then(|subject| subject.work_count));
+            if let Some(work_count) = work_count {
+                record.subject_work_counts.insert(key, work_count);
+            }
+        }
+    }
+}
+
+fn enrich_subjects(records: &mut [OpenLibraryBookRecord], context: &LookupContext) {
+    let Some(limit) = context.options.subject_enrichment else {
+        return;
+    };
+
+    resolve_subject_work_counts(records, limit, |subject_key| {
+        if !context.has_budget() {
+            return None;
+        }
+        execute_get::<OpenLibrarySubjectResponse>(build_subject_url(subject_key), context).ok()
+    });
+}
+
+fn enrich_authors(records: &mut [OpenLibraryBookRecord], context: &LookupContext) {
+    if !context.options.resolve_authors {
+        return;
+    }
+
+    resolve_author_details(records, |author_key| {
+        let author_id = normalize_openlibrary_id(author_key, "authors")?;
+        fetch_author(&author_id, context)
+    });
+}
+
+/// A name worth sending to the search endpoint: at least one letter or digit, so
+/// blank or punctuation-only names ("!!!") are rejected without a request.
+fn is_searchable_name(name: &str) -> bool {
+    name.chars().any(char::is_alphanumeric)
+}
+
+/// Routes a `book.name` that is clearly an identifier to its endpoint. Identifiers without
+/// one (OCLC, LCCN) come back as the fielded search to run instead.
+fn apply_name_identifier(query: &RsLookupQuery, ids: &mut BookIds) -> Option<String> {
+    let RsLookupQuery::Book(book) = query else {
+        return None;
+    };
+
+    match classify_identifier(book.name.as_deref()?) {
+        IdentifierKind::Isbn(isbn) => {
+            ids.isbn13.get_or_insert(isbn);
+            None
+        }
+        IdentifierKind::Edition(edition_id) => {
+            ids.edition_id.get_or_insert(edition_id);
+            None
+        }
+        IdentifierKind::Work(work_id) => {
+            ids.work_id.get_or_insert(work_id);
+            None
+        }
+        IdentifierKind::Oclc(oclc) => Some(format!("oclc:{oclc}")),
+        IdentifierKind::Lccn(lccn) => Some(format!("lccn:{lccn}")),
+        IdentifierKind::Unknown => None,
+    }
+}
+
+/// Builds the book query for loose input: identifiers go to their `RsIds` field (ISBNs
+/// as ISBN-13), OCLC numbers and LCCNs to a prefixed name, anything else is the title.
+fn build_book_query(input: &str) -> RsLookupQuery {
+    let ids = |ids: RsIds| RsLookupBook {
+        name: None,
+        ids: Some(ids),
+    };
+    let name = |name: String| RsLookupBook {
+        name: Some(name),
+        ids: None,
+    };
+
+    let book = match classify_identifier(input) {
+        IdentifierKind::Isbn(isbn) => match canonical_isbn13(&isbn) {
+            Some(isbn13) => ids(RsIds {
+                isbn13: Some(isbn13),
+                ..Default::default()
+            }),
+            None => name(isbn),
+        },
+        IdentifierKind::Edition(edition_id) => ids(RsIds {
+            openlibrary_edition_id: Some(edition_id),
+            ..Default::default()
+        }),
+        IdentifierKind::Work(work_id) => ids(RsIds {
+            openlibrary_work_id: Some(work_id),
+            ..Default::default()
+        }),
+        IdentifierKind::Oclc(oclc) => name(format!("oclc:{oclc}")),
+        IdentifierKind::Lccn(lccn) => name(format!("lccn:{lccn}")),
+        IdentifierKind::Unknown => {
+            let title = input.trim();
+            RsLookupBook {
+                name: (!title.is_empty()).then(|| title.to_string()),
+                ids: None,
+            }
+        }
+    };
+    RsLookupQuery::Book(book)
+}
+
+/// Turns a loose string (title, ISBN, OLID, OCLC, LCCN) into the query `lookup_metadata`
+/// expects.
+#[plugin_fn]
+pub fn lookup_query(input: String) -> FnResult<Json<RsLookupQuery>> {
+    Ok(Json(build_book_query(&input)))
+}
+
+fn deduplicate_records(records: Vec<OpenLibraryBookRecord>) -> Vec<OpenLibraryBookRecord> {
+    deduplicate_records_by(records, OpenLibraryBookRecord::dedup_key)
+}
+
+fn deduplicate_records_by(
+    records: Vec<OpenLibraryBookRecord>,
+    key: impl Fn(&OpenLibraryBookRecord) -> String,
+) -> Vec<OpenLibraryBookRecord> {
+    let mut seen = HashSet::new();
+    let mut deduped = Vec::new();
+
+    for record in records {
+        if seen.insert(key(&record)) {
+            deduped.push(record);
+        }
+    }
+
+    deduped
+}
+
+/// Drops blank records (and ISBN-less ones under `isbn_only_canonical=exclude`), then
+/// `deduplicate_records` (per edition with `all_editions`) unless the caller asked for the
+/// raw list with `no_dedup`.
+fn finalize_records(
+    records: Vec<OpenLibraryBookRecord>,
+    options: &LookupOptions,
+) -> Vec<OpenLibraryBookRecord> {
+    let isbn_required = options.isbn_only_canonical == IsbnOnlyCanonical::Exclude;
+    let records: Vec<OpenLibraryBookRecord> = records
+        .into_iter()
+        .filter(|record| !record.is_blank() && (!isbn_required || record.isbn13.is_some()))
+        .collect();
+    if options.no_dedup {
+        records
+    } else if options.all_editions {
+        deduplicate_records_by(records, OpenLibraryBookRecord::edition_dedup_key)
+    } else {
+        deduplicate_records(records)
+    }
+}
+
+fn has_description(record: &OpenLibraryBookRecord) -> bool {
+    record
+        .description
+        .as_deref()
+        .is_some_and(|description| !description.trim().is_empty())
+}
+
+/// Fills missing descriptions from each record's work; search documents carry none.
+/// Each work is fetched once.
+fn fill_work_descriptions(
+    records: &mut [OpenLibraryBookRecord],
+    mut fetch_description: impl FnMut(&str) -> Option<String>,
+) {
+    let mut resolved: HashMap<String, Option<String>> = HashMap::new();
+    for record in records.iter_mut() {
+        if has_description(record) {
+            continue;
+        }
+        let Some(work_id) = record.work_id.clone() else {
+            continue;
+        };
+        let description = resolved
+            .entry(work_id)
+            .or_insert_with_key(|work_id| fetch_description(work_id))
+            .clone();
+        if description.is_some() {
+            record.description = description;
+        }
+    }
+}
+
+/// Applies `require_description` once records are enriched: work descriptions are
+/// fetched within the request budget, then records still without one are dropped.
+fn with_required_descriptions(
+    records: Vec<OpenLibraryBookRecord>,
+    context: &LookupContext,
+) -> Vec<OpenLibraryBookRecord> {
+    retain_described(records, &context.options, |work_id| {
+        if !context.has_budget() {
+            return None;
+        }
+        execute_get::<OpenLibraryWorkResponse>(build_work_url(work_id), context)
+            .ok()?
+            .description
+            .as_ref()
+            .and_then(OpenLibraryDescription::as_text)
+    })
+}
+
+/// `require_description` without the HTTP layer. `enrichment=off` makes no work fetch,
+/// so only records that already carry a description are kept.
+fn retain_described(
+    mut records: Vec<OpenLibraryBookRecord>,
+    options: &LookupOptions,
+    fetch_description: impl FnMut(&str) -> Option<String>,
+) -> Vec<OpenLibraryBookRecord> {
+    if !options.require_description {
+        return records;
+    }
+    if options.enrichment != EnrichmentLevel::Off {
+        fill_work_descriptions(&mut records, fetch_description);
+    }
+    records.retain(has_description);
+    records
+}
+
+fn deduplicate_images(images: Vec<ExternalImage>) -> Vec<ExternalImage> {
+    let mut seen_urls = HashSet::new();
+    let mut deduped = Vec::new();
+
+    for image in images {
+        if seen_urls.insert(image.url.url.clone()) {
+            deduped.push(image);
+        }
+    }
+
+    deduped
+}
+
+fn lookup_book_records(
+    lookup: &RsLookupWrapper,
+    context: &LookupContext,
+) -> FnResult<Vec<OpenLibraryBookRecord>> {
+    let Some(mut ids) = extract_book_ids(&lookup.query) else {
+        return Ok(vec![]);
+    };
+    let identifier_search = apply_name_identifier(&lookup.query, &mut ids);
+    // A 13 digit id with a bad checksum never reaches `ids`, so fuzzy mode can recover it.
+    let near_isbn = if context.options.fuzzy_isbn {
+        near_isbn_input(&lookup.query)
+    } else {
+        None
+    };
+
+    let records = if let Some(isbn13) = ids.isbn13 {
+        fetch_by_isbn(&isbn13, context)?
+    } else if let Some(edition_id) = ids.edition_id {
+        fetch_by_edition(&edition_id, context)?
+    } else if let Some(work_id) = ids.work_id {
+        let name = match &lookup.query {
+            RsLookupQuery::Book(book) => book.name.as_deref(),
+            _ => None,
+        };
+        with_name_fallback(
+            fetch_by_work(&work_id, context),
+            name,
+            context.options.work_fallback_to_name(),
+            |name| fetch_by_search(name, context),
+        )?
+    } else if let Some(qid) = ids.wikidata {
+        fetch_by_wikidata(&qid, context)?
+    } else if let Some(goodreads_id) = ids.goodreads {
+        fetch_by_goodreads(&goodreads_id, context)?
+    } else if let Some(near_isbn) = near_isbn {
+        fetch_by_fuzzy_isbn(&near_isbn, context)?
+    } else {
+        let search = match &lookup.query {
+            RsLookupQuery::Book(book) => identifier_search.as_deref().or(book.name.as_deref()),
+            _ => None,
+        };
+
+        match search {
+            Some(name) if is_searchable_name(name) => fetch_by_search(name, context)?,
+            _ => return unsupported_query(search, &context.options),
+        }
+    };
+
+    Ok(finalize_records(records, &context.options))
+}
+
+/// A query with no id and no searchable name is a 404, except that `empty_query_ok`
+/// turns a missing or blank name into an empty result for capability probes.
+fn unsupported_query(
+    search: Option<&str>,
+    options: &LookupOptions,
+) -> FnResult<Vec<OpenLibraryBookRecord>> {
+    let is_empty = search.is_none_or(|name| name.trim().is_empty());
+    if is_empty && options.empty_query_ok {
+        return Ok(vec![]);
+    }
+    Err(WithReturnCode::new(
+        extism_pdk::Error::msg("Not supported"),
+        404,
+    ))
+}
+
+fn lookup_book_records_for_images(
+    lookup: &RsLookupWrapper,
+    context: &LookupContext,
+) -> FnResult<Vec<OpenLibraryBookRecord>> {
+    let Some(mut ids) = extract_book_ids(&lookup.query) else {
+        return Ok(vec![]);
+    };
+    apply_name_identifier(&lookup.query, &mut ids);
+
+    if ids.isbn13.is_some()
+        || ids.edition_id.is_some()
+        || ids.work_id.is_some()
+        || ids.wikidata.is_some()
+        || ids.goodreads.is_some()
+    {
+        let mut records = Vec::new();
+
+        if let Some(isbn13) = ids.isbn13.as_deref() {
+            records.extend(fetch_by_isbn(isbn13, context)?);
+        }
+        if let Some(edition_id) = ids.edition_id.as_deref() {
+            records.extend(fetch_by_edition(edition_id, context)?);
+        }
+        if let Some(work_id) = ids.work_id.as_deref() {
+            records.extend(fetch_by_work(work_id, context)?);
+        }
+        if let Some(qid) = ids.wikidata.as_deref() {
+            records.extend(fetch_by_wikidata(qid, context)?);
+        }
+        if let Some(goodreads_id) = ids.goodreads.as_deref() {
+            records.extend(fetch_by_goodreads(goodreads_id, context)?);
+        }
+
+        records.retain(|record| !record.is_blank());
+        return Ok(records);
+    }
+
+    lookup_book_records(lookup, context)
+}
+
+/// `_meta` object added by `with_envelope`. The plugin runs without a clock, so it
+/// carries no duration; hosts time the call themselves.
+fn envelope_meta(context: &LookupContext) -> serde_json::Value {
+    serde_json::json!({
+        "endpoint": context.endpoint.borrow().clone(),
+        "baseUrl": OPENLIBRARY_BASE_URL,
+        "requests": context.requests.get(),
+        "rateLimitRemaining": context.rate_limit.get().map(|rate_limit| rate_limit.remaining),
+        "rateLimitReset": context.rate_limit.get().and_then(|rate_limit| rate_limit.reset),
+    })
+}
+
+fn records_to_results(
+    records: Vec<OpenLibraryBookRecord>,
+    context: &LookupContext,
+) -> Vec<RsLookupMetadataResultWrapper> {
+    let meta = context
+        .options
+        .with_envelope
+        .then(|| envelope_meta(context));
+    records
+        .into_iter()
+        .map(|record| {
+            let mut result = openlibrary_book_to_result(record, &context.options);
+            if let (Some(meta), RsLookupMetadataResult::Book(book)) = (&meta, &mut result.metadata)
+            {
+                if let Some(serde_json::Value::Object(params)) = book.params.as_mut() {
+                    params.insert("_meta".to_string(), meta.clone());
+                }
+            }
+            result
+        })
+        .collect()
+}
+
+#[plugin_fn]
+pub fn lookup_metadata(
+    Json(lookup): Json<RsLookupWrapper>,
+) -> FnResult<Json<Vec<RsLookupMetadataResultWrapper>>> {
+    let context = LookupContext::new(LookupOptions::from_params(lookup.params.as_ref()));
+    let mut all_books = lookup_book_records(&lookup, &context)?;
+    enrich_ratings(&mut all_books, &context);
+    enrich_authors(&mut all_books, &context);
+    enrich_subjects(&mut all_books, &context);
+    rank_covers(&mut all_books, &context);
+    let all_books = with_required_descriptions(all_books, &context);
+
+    Ok(Json(records_to_results(all_books, &context)))
+}
+
+fn series_position(record: &OpenLibraryBookRecord) -> Option<f64> {
+    record
+        .series
+        .as_ref()
+        .and_then(|series| series.position)
+        .or_else(|| detect_volume_number(&record.title))
+}
+
+/// Orders series candidates by detected volume; records without one keep search order at the end.
+fn order_series_records(records: Vec<OpenLibraryBookRecord>) -> Vec<OpenLibraryBookRecord> {
+    let mut positioned: Vec<(f64, OpenLibraryBookRecord)> = Vec::new();
+    let mut unpositioned = Vec::new();
+
+    for record in records {
+        match series_position(&record) {
+            Some(position) => positioned.push((position, record)),
+            None => unpositioned.push(record),
+        }
+    }
+
+    positioned.sort_by(|(left, _), (right, _)| left.total_cmp(right));
+    positioned
+        .into_iter()
+        .map(|(_, record)| record)
+        .chain(unpositioned)
+        .collect()
+}
+
+fn series_name_for_lookup(
+    lookup: &RsLookupWrapper,
+    context: &LookupContext,
+) -> FnResult<Option<String>> {
+    if let Some(series) = lookup
+        .params
+        .as_ref()
+        .and_then(|params| params.get("series"))
+        .map(|series| series.trim())
+        .filter(|series| !series.is_empty())
+    {
+        return Ok(Some(series.to_string()));
+    }
+
+    let records = lookup_book_records(lookup, context)?;
+    Ok(records
+        .into_iter()
+        .find_map(|record| record.series.map(|series| series.name)))
+}
+
+#[plugin_fn]
+pub fn lookup_series(
+    Json(lookup): Json<RsLookupWrapper>,
+) -> FnResult<Json<Vec<RsLookupMetadataResultWrapper>>> {
+    let context = LookupContext::new(LookupOptions::from_params(lookup.params.as_ref()));
+    let Some(series) = series_name_for_lookup(&lookup, &context)? else {
+        return Ok(Json(vec![]));
+    };
+
+    let records = with_required_descriptions(
+        finalize_records(fetch_by_search(&series, &context)?, &context.options),
+        &context,
+    );
+    Ok(Json(records_to_results(
+        order_series_records(records),
+        &context,
+    )))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubjectLookupRequest {
+    /// A subject name (`Science fiction`) or key (`science_fiction`).
+    pub subject: String,
+    /// Defaults to `SUBJECT_WORKS_LIMIT`, capped at `MAX_SUBJECT_WORKS_LIMIT`.
+    pub limit: Option<u32>,
+    pub params: Option<HashMap<String, String>>,
+}
+
+/// Top works of a subject for browse-by-genre flows. An unknown subject has no works and
+/// yields an empty list.
+#[plugin_fn]
+pub fn lookup_subject(
+    Json(request): Json<SubjectLookupRequest>,
+) -> FnResult<Json<Vec<RsLookupMetadataResultWrapper>>> {
+    let context = LookupContext::new(LookupOptions::from_params(request.params.as_ref()));
+    let subject_key = openlibrary_subject_key(&request.subject);
+    if subject_key.is_empty() {
+        return Err(WithReturnCode::new(
+            extism_pdk::Error::msg("Not supported"),
+            404,
+        ));
+    }
+    let limit = request
+        .limit
+        .unwrap_or(SUBJECT_WORKS_LIMIT)
+        .clamp(1, MAX_SUBJECT_WORKS_LIMIT);
+    let response: OpenLibrarySubjectResponse = execute_get(
+        build_subject_works_url(
+            &subject_key,
+            limit,
+            context.options.ebooks,
+            context.options.published_in.as_deref(),
+        ),
+        &context,
+    )?;
+
+    let records = with_required_descriptions(
+        finalize_records(subject_records(&response), &context.options),
+        &context,
+    );
+    Ok(Json(records_to_results(records, &context)))
+}
+
+fn subject_records(response: &OpenLibrarySubjectResponse) -> Vec<OpenLibraryBookRecord> {
+    response
+        .works
+        .iter()
+        .filter_map(book_record_from_subject_work)
+        .map(|mut record| {
+            record.resolved_by = Some(ResolvedBy::Subject);
+            record
+        })
+        .collect()
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchCursorRequest {
+    pub query: String,
+    /// `None` starts at the first page; otherwise a `nextCursor` from a previous page.
+    pub cursor: Option<String>,
+    pub params: Option<HashMap<String, String>>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchCursorPage {
+    pub results: Vec<RsLookupMetadataResultWrapper>,
+    /// `None` on the final page.
+    pub next_cursor: Option<String>,
+}
+
+const SEARCH_CURSOR_PREFIX: &str = "offset:";
+
+/// Cursors are opaque to callers; they encode the offset of the next page (`offset:50`).
+fn encode_search_cursor(offset: u64) -> String {
+    format!("{SEARCH_CURSOR_PREFIX}{offset}")
+}
+
+fn decode_search_cursor(cursor: &str) -> Option<u64> {
+    cursor
+        .trim()
+        .strip_prefix(SEARCH_CURSOR_PREFIX)?
+        .parse()
+        .ok()
+}
+
+/// Fetches the page starting at `offset` and computes the cursor of the one after it.
+fn search_cursor_page(
+    offset: u64,
+    fetch_page: impl FnOnce(u64) -> FnResult<OpenLibrarySearchResponse>,
+) -> FnResult<(Vec<OpenLibraryBookRecord>, Option<String>)> {
+    let response = fetch_page(offset)?;
+    let next_offset = offset + response.docs.len() as u64;
+    let has_more = !response.docs.is_empty()
+        && response
+            .num_found
+            .is_some_and(|num_found| next_offset < num_found);
+
+    let records = response
+        .docs
+        .iter()
+        .filter_map(book_record_from_search_doc)
+        .collect();
+    Ok((records, has_more.then(|| encode_search_cursor(next_offset))))
+}
+
+/// Pages through a full search result set for bulk ingestion. Pages are not deduplicated
+/// against each other, so every search document is returned exactly once.
+#[plugin_fn]
+pub fn lookup_search_cursor(
+    Json(request): Json<SearchCursorRequest>,
+) -> FnResult<Json<SearchCursorPage>> {
+    let context = LookupContext::new(LookupOptions::from_params(request.params.as_ref()));
+    if !is_searchable_name(&request.query) {
+        return Err(WithReturnCode::new(
+            extism_pdk::Error::msg("Not supported"),
+            404,
+        ));
+    }
+    let offset = match request.cursor.as_deref() {
+        None => 0,
+        Some(cursor) => decode_search_cursor(cursor).ok_or_else(|| {
+            WithReturnCode::new(extism_pdk::Error::msg("Invalid search cursor"), 400)
+        })?,
+    };
+
+    let limit = context.options.search_limit();
+    let (records, next_cursor) = search_cursor_page(offset, |offset| {
+        let url = match context.options.author.as_deref() {
+            Some(author) => {
+                build_fielded_search_url_at_offset(&request.query, author, limit, offset)
+            }
+            None => build_search_url_at_offset(&request.query, limit, offset),
+        };
+        execute_get(url, &context)
+    })?;
+
+    Ok(Json(SearchCursorPage {
+        results: records_to_results(records, &context),
+        next_cursor,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecentChangesRequest {
+    /// OpenLibrary datetime (`2024-05-02T10:15:30`, UTC) or date of the previous sync.
+    pub since: String,
+    /// `None` starts at the newest change; otherwise a `nextCursor` from a previous page.
+    pub cursor: Option<String>,
+    pub params: Option<HashMap<String, String>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecentChange {
+    #[serde(rename = "type")]
+    pub record_type: ChangedRecordType,
+    pub id: String,
+    pub revision: Option<u64>,
+    /// Change kind of the feed (`edit-book`, `add-book`, ...).
+    pub kind: Option<String>,
+    pub timestamp: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecentChangesPage {
+    pub changes: Vec<RecentChange>,
+    /// `None` once the feed reaches `since`.
+    pub next_cursor: Option<String>,
+}
+
+/// Feed pages read per call before handing a cursor back.
+const MAX_RECENT_CHANGES_PAGES: u32 = 5;
+
+/// The last feed entry a page read, so the next call can find it again after newer
+/// changes have pushed it down the feed.
+#[derive(Debug, Clone, PartialEq)]
+struct ChangesAnchor {
+    id: String,
+    changed_at: u64,
+}
+
+/// `offset:N` alone, or `offset:N:{millis}:{id}` when the last entry read has an id
+/// and a timestamp.
+fn encode_changes_cursor(offset: u64, anchor: Option<&ChangesAnchor>) -> String {
+    match anchor {
+        Some(anchor) => format!(
+            "{}:{}:{}",
+            encode_search_cursor(offset),
+            anchor.changed_at,
+            anchor.id
+        ),
+        None => encode_search_cursor(offset),
+    }
+}
+
+fn decode_changes_cursor(cursor: &str) -> Option<(u64, Option<ChangesAnchor>)> {
+    let cursor = cursor.trim().strip_prefix(SEARCH_CURSOR_PREFIX)?;
+    let mut parts = cursor.splitn(3, ':');
+    let offset = parts.next()?.parse().ok()?;
+    match (parts.next(), parts.next()) {
+        (None, None) => Some((offset, None)),
+        (Some(changed_at), Some(id)) if !id.is_empty() => Some((
+            offset,
+            Some(ChangesAnchor {
+                id: id.to_string(),
+                changed_at: changed_at.parse().ok()?,
+            }),
+        )),
+        _ => None,
+    }
+}
+
+fn changes_anchor(change: &OpenLibraryRecentChange) -> Option<ChangesAnchor> {
+    Some(ChangesAnchor {
+        id: recent_change_id(change)?,
+        changed_at: change
+            .timestamp
+            .as_deref()
+            .and_then(parse_datetime_millis)?,
+    })
+}
+
+/// Reads the newest-first feed from `offset` until a change older than `since_millis`.
+/// A record changed several times is reported once, with its newest change.
+///
+/// The feed keeps growing at its head, so a resumed `offset` may point at entries an
+/// earlier page already returned. With an `anchor`, reading restarts one entry early
+/// and skips everything up to the anchored entry, or up to the first entry older than
+/// it when the anchor has vanished from the feed.
+fn recent_changes_page(
+    since_millis: u64,
+    offset: u64,
+    anchor: Option<&ChangesAnchor>,
+    limit: u32,
+    mut fetch_page: impl FnMut(u64) -> FnResult<Vec<OpenLibraryRecentChange>>,
+) -> FnResult<(Vec<RecentChange>, Option<String>)> {
+    let mut changes: Vec<RecentChange> = Vec::new();
+    let mut skip_to = anchor;
+    let mut offset = match anchor {
+        Some(_) => offset.saturating_sub(1),
+        None => offset,
+    };
+    let mut last_read = None;
+
+    for _ in 0..MAX_RECENT_CHANGES_PAGES {
+        let page = fetch_page(offset)?;
+        offset += page.len() as u64;
+        last_read = page.last().and_then(changes_anchor);
+
+        for change in &page {
+            let changed_at = change.timestamp.as_deref().and_then(parse_datetime_millis);
+            if let Some(anchor) = skip_to {
+                if recent_change_id(change).as_deref() == Some(anchor.id.as_str()) {
+                    skip_to = None;
+                    continue;
+                }
+                if changed_at.is_none_or(|changed_at| changed_at >= anchor.changed_at) {
+                    continue;
+                }
+                skip_to = None;
+            }
+            if changed_at.is_some_and(|changed_at| changed_at < since_millis) {
+                return Ok((changes, None));
+            }
+            for (record_type, id, revision) in changed_records(change) {
+                if changes
+                    .iter()
+                    .any(|seen| seen.record_type == record_type && seen.id == id)
+                {
+                    continue;
+                }
+                changes.push(RecentChange {
+                    record_type,
+                    id,
+                    revision,
+                    kind: change.kind.clone(),
+                    timestamp: change.timestamp.clone(),
+                });
+            }
+        }
+
+        if page.len() < limit as usize {
+            return Ok((changes, None));
+        }
+    }
+
+    // Still looking for the anchor: keep it, so the next page goes on skipping.
+    let anchor = skip_to.cloned().or(last_read);
+    Ok((
+        changes,
+        Some(encode_changes_cursor(offset, anchor.as_ref())),
+    ))
+}
+
+/// Works and editions changed since the previous sync, for incremental refreshes.
+#[plugin_fn]
+pub fn lookup_recent_changes(
+    Json(request): Json<RecentChangesRequest>,
+) -> FnResult<Json<RecentChangesPage>> {
+    let context = LookupContext::new(LookupOptions::from_params(request.params.as_ref()));
+    let since_millis = parse_datetime_millis(&request.since).ok_or_else(|| {
+        WithReturnCode::new(extism_pdk::Error::msg("Invalid since timestamp"), 400)
+    })?;
+    let (offset, anchor) = match request.cursor.as_deref() {
+        None => (0, None),
+        Some(cursor) => decode_changes_cursor(cursor).ok_or_else(|| {
+            WithReturnCode::new(extism_pdk::Error::msg("Invalid changes cursor"), 400)
+        })?,
+    };
+
+    let (changes, next_cursor) = recent_changes_page(
+        since_millis,
+        offset,
+        anchor.as_ref(),
+        RECENT_CHANGES_LIMIT,
+        |offset| {
+            execute_get(
+                build_recent_changes_url(RECENT_CHANGES_LIMIT, offset),
+                &context,
+            )
+        },
+    )?;
+
+    Ok(Json(RecentChangesPage {
+        changes,
+        next_cursor,
+    }))
+}
+
+#[plugin_fn]
+pub fn lookup_metadata_images(
+    Json(lookup): Json<RsLookupWrapper>,
+) -> FnResult<Json<Vec<ExternalImage>>> {
+    let context = LookupContext::new(LookupOptions::from_params(lookup.params.as_ref()));
+    let isbn13 = extract_book_ids(&lookup.query).and_then(|mut ids| {
+        apply_name_identifier(&lookup.query, &mut ids);
+        ids.isbn13
+    });
+    // Cover selection options need every cover of the records, not the data endpoint's one.
+    let data_images = isbn13
+        .filter(|_| !context.options.selects_covers())
+        .map(|isbn13| fetch_isbn_data_images(&isbn13, &context))
+        .unwrap_or_default();
+
+    let mut images = if data_images.is_empty() {
+        let mut all_books = lookup_book_records_for_images(&lookup, &context)?;
+        gather_edition_covers(&mut all_books, &context);
+        rank_covers(&mut all_books, &context);
+        ordered_images(&all_books, &context.options)
+    } else {
+        data_images
+    };
+    if context.options.cover_index.is_some() {
+        // One targeted image: the selected cover of the first record that has any.
+        images.truncate(1);
+    }
+
+    if !context.options.verify_covers {
+        return Ok(Json(images));
+    }
+    Ok(Json(verify_cover_images(images, |url| {
+        fetch_cover(url, &context)
+    })))
+}
+
+/// The cover from the books data endpoint, the authoritative source for an ISBN. Empty
+/// when the ISBN is unknown or has no cover, so the caller falls back to the edition lookup.
+fn fetch_isbn_data_images(isbn13: &str, context: &LookupContext) -> Vec<ExternalImage> {
+    let url = build_books_data_url(isbn13);
+    match execute_get::<HashMap<String, OpenLibraryBooksDataEntry>>(url, context) {
+        Ok(response) => response
+            .get(&format!("ISBN:{isbn13}"))
+            .and_then(|entry| entry.cover.as_ref())
+            .and_then(|cover| data_cover_to_image(cover, &context.options))
+            .into_iter()
+            .collect(),
+        Err(_) => vec![],
+    }
+}
+
+enum CoverCheck {
+    Found(Vec<u8>),
+    Missing,
+    /// Not checked, e.g. because the request budget ran out.
+    Skipped,
+}
+
+/// Fetches a cover with `default=false`, which makes the cover store answer 404 instead
+/// of a blank placeholder when it has no image.
+fn fetch_cover(url: &str, context: &LookupContext) -> CoverCheck {
+    if !context.has_budget() {
+        return CoverCheck::Skipped;
+    }
+    let request = HttpRequest {
+        url: format!("{url}?default=false"),
+        headers: Default::default(),
+        method: Some("GET".into()),
+    };
+
+    match send_get(&request, context) {
+        Ok(res) if (200..300).contains(&res.status_code()) => CoverCheck::Found(res.body()),
+        Ok(res) if res.status_code() == 404 => CoverCheck::Missing,
+        Ok(res) => {
+            log!(
+                LogLevel::Warn,
+                "OpenLibrary cover check failed with {} for {}",
+                res.status_code(),
+                url
+            );
+            CoverCheck::Skipped
+        }
+        Err(e) => {
+            log!(LogLevel::Warn, "OpenLibrary cover check failed: {}", e);
+            CoverCheck::Skipped
+        }
+    }
+}
+
+/// Drops covers the store does not have and fills dimensions for those it returns.
+/// Unchecked covers are kept as they are.
+fn verify_cover_images(
+    images: Vec<ExternalImage>,
+    mut check: impl FnMut(&str) -> CoverCheck,
+) -> Vec<ExternalImage> {
+    images
+        .into_iter()
+        .filter_map(|mut image| match check(&image.url.url) {
+            CoverCheck::Found(bytes) => {
+                if let Some((width, height)) = image_dimensions(&bytes) {
+                    image.width = Some(width.into());
+                    image.height = Some(height.into());
+                    if height > 0 {
+                        image.aspect_ratio = Some(f64::from(width) / f64::from(height));
+                    }
+                }
+                Some(image)
+            }
+            CoverCheck::Missing => None,
+            CoverCheck::Skipped => Some(image),
+        })
+        .collect()
+}
+
+/// Best first: records keep their lookup order (the primary record first) and each
+/// contributes its primary cover before its other covers. Dedup runs last so a cover
+/// shared by several records stays at its earliest position.
+fn ordered_images(
+    records: &[OpenLibraryBookRecord],
+    options: &LookupOptions,
+) -> Vec<ExternalImage> {
+    let images: Vec<ExternalImage> = records
+        .iter()
+        .flat_map(|record| openlibrary_book_to_images(record, options))
+        .collect();
+    if options.no_dedup {
+        images
+    } else {
+        deduplicate_images(images)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use identifier::normalize_exact_isbn_search;
+    use openlibrary::{OpenLibraryChangedKey, OpenLibrarySearchDoc};
+    use rs_plugin_common_interfaces::domain::other_ids::OtherIds;
+
+    fn search_doc(work_key: &str, title: &str) -> OpenLibrarySearchDoc {
+        OpenLibrarySearchDoc {
+            key: work_key.to_string(),
+            title: title.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn book_query_extracts_ids() {
+        let query = RsLookupQuery::Book(RsLookupBook {
+            name: None,
+            ids: Some(RsIds {
+                isbn13: Some("9780140328721".to_string()),
+                openlibrary_edition_id: Some("/books/OL7353617M".to_string()),
+                openlibrary_work_id: Some("works/OL45804W".to_string()),
+                ..Default::default()
+            }),
+        });
+
+        let ids = extract_book_ids(&query).expect("Expected ids");
+        assert_eq!(ids.isbn13, Some("9780140328721".to_string()));
+        assert_eq!(ids.edition_id, Some("OL7353617M".to_string()));
+        assert_eq!(ids.work_id, Some("OL45804W".to_string()));
+    }
+
+    #[test]
+    fn book_query_extracts_wikidata_qid() {
+        let query = RsLookupQuery::Book(RsLookupBook {
+            name: None,
+            ids: Some(RsIds {
+                other_ids: Some(OtherIds(vec!["wikidata:q74287".to_string()])),
+                ..Default::default()
+            }),
+        });
+        let ids = extract_book_ids(&query).expect("Expected ids");
+        assert_eq!(ids.wikidata, Some("Q74287".to_string()));
+
+        let query = RsLookupQuery::Book(RsLookupBook {
+            name: None,
+            ids: Some(RsIds {
+                other_ids: Some(OtherIds(vec!["wikidata:74287".to_string()])),
+                ..Default::default()
+            }),
+        });
+        let ids = extract_book_ids(&query).expect("Expected ids");
+        assert_eq!(ids.wikidata, None);
+    }
+
+    #[test]
+    fn book_query_extracts_numeric_goodreads_id() {
+        let query = RsLookupQuery::Book(RsLookupBook {
+            name: None,
+            ids: Some(RsIds {
+                other_ids: Some(OtherIds(vec!["goodreads:5907".to_string()])),
+                ..Default::default()
+            }),
+        });
+        let ids = extract_book_ids(&query).expect("Expected ids");
+        assert_eq!(ids.goodreads, Some("5907".to_string()));
+
+        let query = RsLookupQuery::Book(RsLookupBook {
+            name: None,
+            ids: Some(RsIds {
+                other_ids: Some(OtherIds(vec!["goodreads:5907-the-hobbit".to_string()])),
+                ..Default::default()
+            }),
+        });
+        let ids = extract_book_ids(&query).expect("Expected ids");
+        assert_eq!(ids.goodreads, None);
+    }
+
+    #[test]
+    fn request_budget_counts_requests() {
+        let context = LookupContext::new(LookupOptions {
+            max_requests: Some(2),
+            ..Default::default()
+        });
+        assert!(context.has_budget());
+        context.requests.set(2);
+        assert!(!context.has_budget());
+
+        let unbounded = LookupContext::new(LookupOptions::default());
+        unbounded.requests.set(1000);
+        assert!(unbounded.has_budget());
+    }
+
+    #[test]
+    fn parses_rate_limit_headers() {
+        let headers = HashMap::from([
+            ("x-ratelimit-remaining".to_string(), " 42 ".to_string()),
+            ("X-RateLimit-Reset".to_string(), "30".to_string()),
+        ]);
+        assert_eq!(
+            parse_rate_limit(&headers),
+            Some(RateLimit {
+                remaining: 42,
+                reset: Some(30)
+            })
+        );
+
+        let no_reset = HashMap::from([("X-RateLimit-Remaining".to_string(), "0".to_string())]);
+        assert_eq!(
+            parse_rate_limit(&no_reset),
+            Some(RateLimit {
+                remaining: 0,
+                reset: None
+            })
+        );
+
+        assert_eq!(parse_rate_limit(&HashMap::new()), None);
+        let garbled = HashMap::from([("X-RateLimit-Remaining".to_string(), "lots".to_string())]);
+        assert_eq!(parse_rate_limit(&garbled), None);
+
+        let retry_after = HashMap::from([("Retry-After".to_string(), "120".to_string())]);
+        assert_eq!(
+            parse_rate_limit(&retry_after),
+            Some(RateLimit {
+                remaining: 0,
+                reset: Some(120)
+            })
+        );
+    }
+
+    #[test]
+    fn retry_after_responses_are_not_resent() {
+        let none = HashMap::new();
+        assert!(is_retryable_status(429, &none));
+        assert!(is_retryable_status(503, &none));
+        assert!(!is_retryable_status(404, &none));
+
+        let retry_after = HashMap::from([("retry-after".to_string(), "30".to_string())]);
+        assert!(!is_retryable_status(429, &retry_after));
+        assert!(!is_retryable_status(503, &retry_after));
+    }
+
+    #[test]
+    fn low_rate_limit_stops_optional_requests() {
+        let context = LookupContext::new(LookupOptions::default());
+        context.rate_limit.set(Some(RateLimit {
+            remaining: RATE_LIMIT_RESERVE + 1,
+            reset: None,
+        }));
+        assert!(context.has_budget());
+        context.rate_limit.set(Some(RateLimit {
+            remaining: RATE_LIMIT_RESERVE,
+            reset: None,
+        }));
+        assert!(!context.has_budget());
+    }
+
+    #[test]
+    fn http_error_message_includes_snippet_only_when_verbose() {
+        let body = b"<html>\n  <title>503 Service\tUnavailable</title>\n</html>";
+        assert_eq!(http_error_message(503, body, false), "HTTP error: 503");
+        assert_eq!(
+            http_error_message(503, body, true),
+            "HTTP error: 503: <html> <title>503 Service Unavailable</title> </html>"
+        );
+    }
+
+    #[test]
+    fn error_body_snippet_is_truncated() {
+        let body = vec![b'a'; 2000];
+        assert_eq!(error_body_snippet(&body).len(), ERROR_SNIPPET_BYTES);
+    }
+
+    #[test]
+    fn normalize_exact_isbn_search_accepts_isbn13() {
+        assert_eq!(
+            normalize_exact_isbn_search("978-0-14-032872-1"),
+            Some("9780140328721".to_string())
+        );
+    }
+
+    #[test]
+    fn normalize_exact_isbn_search_accepts_isbn10_with_x() {
+        assert_eq!(
+            normalize_exact_isbn_search("0-684-84328-5"),
+            Some("9780684843285".to_string())
+        );
+        assert_eq!(
+            normalize_exact_isbn_search("0-8044-2957-x"),
+            Some("9780804429573".to_string())
+        );
+        assert_eq!(normalize_exact_isbn_search("0-8044-2957-1"), None);
+    }
+
+    #[test]
+    fn normalize_exact_isbn_search_rejects_non_exact_values() {
+        assert_eq!(
+            normalize_exact_isbn_search("The Hobbit 9780140328721"),
+            None
+        );
+        assert_eq!(normalize_exact_isbn_search("isbn 9780140328721"), None);
+        assert_eq!(normalize_exact_isbn_search("9780140328720"), None);
+        assert_eq!(normalize_exact_isbn_search(""), None);
+    }
+
+    #[test]
+    fn searchable_name_needs_alphanumeric_content() {
+        assert!(!is_searchable_name(""));
+        assert!(!is_searchable_name("   "));
+        assert!(!is_searchable_name("!!!"));
+        assert!(!is_searchable_name(" -- ?! "));
+        assert!(is_searchable_name("The Hobbit"));
+        assert!(is_searchable_name("1984"));
+        assert!(is_searchable_name("Élan!"));
+    }
+
+    #[test]
+    fn work_404_falls_back_to_name_search() {
+        let not_found = || {
+            Err(WithReturnCode::new(
+                extism_pdk::Error::msg("HTTP error: 404"),
+                404,
+            ))
+        };
+        let search = |name: &str| {
+            Ok(vec![OpenLibraryBookRecord {
+                title: name.to_string(),
+                ..Default::default()
+            }])
+        };
+
+        let records = with_name_fallback(not_found(), Some("The Hobbit"), true, search)
+            .expect("Expected search");
+        assert_eq!(records[0].title, "The Hobbit");
+
+        assert!(with_name_fallback(not_found(), Some("The Hobbit"), false, search).is_err());
+        assert!(with_name_fallback(not_found(), None, true, search).is_err());
+
+        let server_error: FnResult<Vec<OpenLibraryBookRecord>> = Err(WithReturnCode::new(
+            extism_pdk::Error::msg("HTTP error: 500"),
+            500,
+        ));
+        assert!(with_name_fallback(server_error, Some("The Hobbit"), true, search).is_err());
+    }
+
+    #[test]
+    fn search_fetches_next_page_when_dedup_collapses_results() {
+        let mut requested_pages = Vec::new();
+        let records = collect_search_pages(2, |page| {
+            requested_pages.push(page);
+            let docs = if page == 1 {
+                vec![
+                    search_doc("/works/OL1W", "Dune"),
+                    search_doc("/works/OL1W", "Dune"),
+                ]
+            } else {
+                vec![search_doc("/works/OL2W", "Dune Messiah")]
+            };
+            Ok(OpenLibrarySearchResponse {
+                docs,
+                num_found: Some(3),
+            })
+        })
+        .expect("Expected records");
+
+        assert_eq!(requested_pages, vec![1, 2]);
+        assert_eq!(deduplicate_records(records).len(), 2);
+    }
+
+    #[test]
+    fn search_records_rank_by_confidence() {
+        let records = vec![
+            OpenLibraryBookRecord {
+                title: "The Hobbit Companion".to_string(),
+                work_id: Some("OL1W".to_string()),
+                ..Default::default()
+            },
+            OpenLibraryBookRecord {
+                title: "The Hobbit".to_string(),
+                work_id: Some("OL2W".to_string()),
+                ..Default::default()
+            },
+        ];
+        let ranked = score_search_records(records, "The Hobbit", true);
+        assert_eq!(ranked[0].work_id.as_deref(), Some("OL2W"));
+    }
+
+    #[test]
+    fn unknown_subject_yields_no_records() {
+        let response: OpenLibrarySubjectResponse =
+            serde_json::from_str(r#"{"key": "/subjects/zzzz", "work_count": 0, "works": []}"#)
+                .expect("Expected valid subject json");
+        assert!(subject_records(&response).is_empty());
+
+        let response: OpenLibrarySubjectResponse = serde_json::from_str(
+            r#"{"works": [{"key": "/works/OL46125W", "title": "Dune"}, {"key": "/works/OL1W"}]}"#,
+        )
+        .expect("Expected valid subject json");
+        let records = subject_records(&response);
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].resolved_by, Some(ResolvedBy::Subject));
+    }
+
+    #[test]
+    fn tied_search_scores_break_deterministically() {
+        let record =
+            |work_id: &str, cover_id: Option<u64>, isbn13: Option<&str>| OpenLibraryBookRecord {
+                title: "The Hobbit".to_string(),
+                work_id: Some(work_id.to_string()),
+                cover_id,
+                isbn13: isbn13.map(str::to_string),
+                ..Default::default()
+            };
+        let records: Vec<OpenLibraryBookRecord> = vec![
+            record("OL9W", None, None),
+            record("OL7W", None, Some("9780261103344")),
+            record("OL5W", Some(1), None),
+            record("OL3W", Some(2), None),
+            OpenLibraryBookRecord {
+                title: "The Hobbit".to_string(),
+                ..Default::default()
+            },
+        ]
+        .into_iter()
+        .map(|record| OpenLibraryBookRecord {
+            confidence: Some(0.5),
+            ..record
+        })
+        .collect();
+        let rank = |mut records: Vec<OpenLibraryBookRecord>| -> Vec<Option<String>> {
+            records.sort_by(compare_search_records);
+            records.into_iter().map(|record| record.work_id).collect()
+        };
+
+        let ranked = rank(records.clone());
+        let mut reversed = records;
+        reversed.reverse();
+        assert_eq!(rank(reversed), ranked);
+        assert_eq!(
+            ranked,
+            vec![
+                Some("OL3W".to_string()),
+                Some("OL5W".to_string()),
+                Some("OL7W".to_string()),
+                Some("OL9W".to_string()),
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn editions_desc_sorts_most_published_first() {
+        let record = |work_id: &str, edition_count: Option<u64>| OpenLibraryBookRecord {
+            title: "The Hobbit".to_string(),
+            work_id: Some(work_id.to_string()),
+            edition_count,
+            ..Default::default()
+        };
+        let records = vec![
+            record("OL1W", Some(3)),
+            record("OL2W", None),
+            record("OL3W", Some(120)),
+            record("OL4W", Some(3)),
+        ];
+        let work_ids = |records: &[OpenLibraryBookRecord]| -> Vec<String> {
+            records
+                .iter()
+                .filter_map(|record| record.work_id.clone())
+                .collect()
+        };
+
+        let sorted = sort_records(records.clone(), SortOrder::EditionsDesc);
+        assert_eq!(work_ids(&sorted), vec!["OL3W", "OL1W", "OL4W", "OL2W"]);
+        let unsorted = sort_records(records, SortOrder::Relevance);
+        assert_eq!(work_ids(&unsorted), vec!["OL1W", "OL2W", "OL3W", "OL4W"]);
+    }
+
+    #[test]
+    fn broadened_search_recovers_a_wrong_author_hint() {
+        let hobbit = || OpenLibraryBookRecord {
+            title: "The Hobbit".to_string(),
+            work_id: Some("OL45804W".to_string()),
+            ..Default::default()
+        };
+
+        let records = broaden_if_few(Ok(vec![]), 0, true, || Ok(vec![hobbit(), hobbit()]))
+            .expect("Expected records");
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].work_id.as_deref(), Some("OL45804W"));
+
+        let records = broaden_if_few(Ok(vec![]), 0, false, || panic!("No broadening expected"))
+            .expect("Expected records");
+        assert!(records.is_empty());
+
+        let records = broaden_if_few(Ok(vec![hobbit()]), 0, true, || {
+            panic!("Enough fielded results")
+        })
+        .expect("Expected records");
+        assert_eq!(records.len(), 1);
+    }
+
+    #[test]
+    fn search_fetches_single_page_without_min_results() {
+        let mut requested_pages = Vec::new();
+        let records = collect_search_pages(0, |page| {
+            requested_pages.push(page);
+            Ok(OpenLibrarySearchResponse {
+                docs: vec![search_doc("/works/OL1W", "Dune")],
+                num_found: Some(100),
+            })
+        })
+        .expect("Expected records");
+
+        assert_eq!(requested_pages, vec![1]);
+        assert_eq!(records.len(), 1);
+    }
+
+    #[test]
+    fn series_records_are_ordered_by_volume() {
+        let record = |title: &str, position: Option<f64>| OpenLibraryBookRecord {
+            title: title.to_string(),
+            series: position.map(|position| openlibrary::OpenLibrarySeries {
+                name: "Dune".to_string(),
+                position: Some(position),
+            }),
+            ..Default::default()
+        };
+
+        let ordered = order_series_records(vec![
+            record("Dune Companion", None),
+            record("Children of Dune", Some(3.0)),
+            record("Dune Messiah, Book 2", None),
+            record("Dune", Some(1.0)),
+        ]);
+
+        let titles: Vec<&str> = ordered.iter().map(|record| record.title.as_str()).collect();
+        assert_eq!(
+            titles,
+            vec![
+                "Dune",
+                "Dune Messiah, Book 2",
+                "Children of Dune",
+                "Dune Companion"
+            ]
+        );
+    }
+
+    #[test]
+    fn resolved_author_photo_becomes_portrait() {
+        let mut records = vec![OpenLibraryBookRecord {
+            title: "Good Omens".to_string(),
+            authors: vec!["Terry Pratchett".to_string(), "Neil Gaiman".to_string()],
+            author_keys: vec!["OL25712A".to_string(), "OL53305A".to_string()],
+            ..Default::default()
+        }];
+
+        resolve_author_details(&mut records, |author_key| {
+            Some(OpenLibraryAuthorResponse {
+                photos: if author_key == "OL25712A" {
+                    vec![-1, 6257553]
+                } else {
+                    vec![-1]
+                },
+                bio: Some(OpenLibraryDescription::Text("Fantasy author.".to_string())),
+                birth_date: Some("28 April 1948".to_string()),
+                ..Default::default()
+            })
+        });
+
+        let result = openlibrary_book_to_result(records.remove(0), &LookupOptions::default());
+        let people = result
+            .relations
+            .and_then(|relations| relations.people_details)
+            .expect("Expected people");
+        assert_eq!(
+            people[0].portrait.as_deref(),
+            Some("https://covers.openlibrary.org/a/id/6257553-L.jpg")
+        );
+        assert!(people[1].portrait.is_none());
+        assert_eq!(people[0].bio.as_deref(), Some("Fantasy author."));
+        assert_eq!(people[0].params.as_ref().unwrap()["birthYear"], 1948);
+    }
+
+    #[test]
+    fn author_resolution_keeps_names_when_one_fetch_fails() {
+        let mut records = vec![OpenLibraryBookRecord {
+            title: "Good Omens".to_string(),
+            authors: vec!["Terry Pratchett".to_string(), "Neil Gaiman".to_string()],
+            author_keys: vec!["OL25712A".to_string(), "OL53305A".to_string()],
+            ..Default::default()
+        }];
+        let mut fetched = Vec::new();
+
+        resolve_author_details(&mut records, |author_key| {
+            fetched.push(author_key.to_string());
+            (author_key == "OL25712A").then(|| OpenLibraryAuthorResponse {
+                birth_date: Some("28 April 1948".to_string()),
+                ..Default::default()
+            })
+        });
+
+        assert_eq!(fetched, vec!["OL25712A", "OL53305A"]);
+        assert_eq!(records[0].author_details.len(), 1);
+        assert_eq!(records[0].author_details[0].key, "OL25712A");
+
+        let result = openlibrary_book_to_result(records.remove(0), &LookupOptions::default());
+        let people = result
+            .relations
+            .and_then(|relations| relations.people_details)
+            .expect("Expected people");
+        assert_eq!(people.len(), 2);
+        assert_eq!(people[0].name, "Terry Pratchett");
+        assert_eq!(
+            people[0].params.as_ref().unwrap()["birthDate"],
+            "28 April 1948"
+        );
+        assert_eq!(people[1].name, "Neil Gaiman");
+        assert!(people[1]
+            .params
+            .as_ref()
+            .unwrap()
+            .get("birthDate")
+            .is_none());
+    }
+
+    #[test]
+    fn images_are_ordered_best_first_across_records() {
+        let records = vec![
+            OpenLibraryBookRecord {
+                title: "The Hobbit".to_string(),
+                cover_id: Some(30),
+                cover_ids: vec![10, 30, 20],
+                ..Default::default()
+            },
+            OpenLibraryBookRecord {
+                title: "The Hobbit".to_string(),
+                cover_id: Some(20),
+                cover_ids: vec![20, 40],
+                ..Default::default()
+            },
+        ];
+
+        let urls: Vec<String> = ordered_images(&records, &LookupOptions::default())
+            .into_iter()
+            .map(|image| image.url.url)
+            .collect();
+        assert_eq!(
+            urls,
+            vec![
+                "https://covers.openlibrary.org/b/id/30-L.jpg",
+                "https://covers.openlibrary.org/b/id/10-L.jpg",
+                "https://covers.openlibrary.org/b/id/20-L.jpg",
+                "https://covers.openlibrary.org/b/id/40-L.jpg",
+            ]
+        );
+    }
+
+    #[test]
+    fn subject_enrichment_is_bounded_and_fetched_once() {
+        let mut records = vec![
+            OpenLibraryBookRecord {
+                title: "The Hobbit".to_string(),
+                subjects: vec![
+                    "Fantasy fiction".to_string(),
+                    "Dragons".to_string(),
+                    "Wizards".to_string(),
+                ],
+                ..Default::default()
+            },
+            OpenLibraryBookRecord {
+                title: "The Hobbit".to_string(),
+                subjects: vec!["Dragons".to_string()],
+                ..Default::default()
+            },
+        ];
+        let mut fetched = Vec::new();
+
+        resolve_subject_work_counts(&mut records, 2, |subject_key| {
+            fetched.push(subject_key.to_string());
+            Some(OpenLibrarySubjectResponse {
+                work_count: Some(fetched.len() as u64 * 100),
+                ..Default::default()
+            })
+        });
+
+        assert_eq!(fetched, vec!["fantasy_fiction", "dragons"]);
+        assert_eq!(records[0].subject_work_counts.get("dragons"), Some(&200));
+        assert_eq!(records[0].subject_work_counts.get("wizards"), None);
+        assert_eq!(records[1].subject_work_counts.get("dragons"), Some(&200));
+    }
+
+    #[test]
+    fn subject_enrichment_fan_out_is_capped() {
+        let mut records: Vec<OpenLibraryBookRecord> = (0..4)
+            .map(|record| OpenLibraryBookRecord {
+                subjects: (0..5)
+                    .map(|subject| format!("Subject {record} {subject}"))
+                    .collect(),
+                ..Default::default()
+            })
+            .collect();
+        let mut fetched = 0;
+
+        resolve_subject_work_counts(&mut records, usize::MAX, |_| {
+            fetched += 1;
+            Some(OpenLibrarySubjectResponse {
+                work_count: Some(1),
+                ..Default::default()
+            })
+        });
+
+        assert_eq!(fetched, MAX_SUBJECT_ENRICHMENT);
+        assert_eq!(records[1].subject_work_counts.len(), 5);
+        assert!(records[2].subject_work_counts.is_empty());
+    }
+
+    #[test]
+    fn verified_covers_carry_dimensions() {
+        let records = vec![OpenLibraryBookRecord {
+            title: "The Hobbit".to_string(),
+            cover_ids: vec![1, 2, 3],
+            ..Default::default()
+        }];
+        let gif = b"GIF89a\x2c\x01\xc8\x00".to_vec();
+
+        let images =
+            verify_cover_images(ordered_images(&records, &LookupOptions::default()), |url| {
+                if url.contains("/1-") {
+                    CoverCheck::Found(gif.clone())
+                } else if url.contains("/2-") {
+                    CoverCheck::Missing
+                } else {
+                    CoverCheck::Skipped
+                }
+            });
+
+        assert_eq!(images.len(), 2);
+        assert_eq!(images[0].width, Some(300));
+        assert_eq!(images[0].height, Some(200));
+        assert_eq!(images[0].aspect_ratio, Some(1.5));
+        assert!(images[1].url.url.contains("/3-"));
+        assert_eq!(images[1].width, None);
+    }
+
+    #[test]
+    fn search_cursor_pages_through_results() {
+        let page = |offset: u64| OpenLibrarySearchResponse {
+            docs: vec![
+                search_doc(&format!("/works/OL{}W", offset + 1), "The Hobbit"),
+                search_doc(&format!("/works/OL{}W", offset + 2), "The Hobbit"),
+            ],
+            num_found: Some(4),
+        };
+        let mut requested = Vec::new();
+
+        let (first, cursor) = search_cursor_page(0, |offset| {
+            requested.push(offset);
+            Ok(page(offset))
+        })
+        .expect("Expected first page");
+        assert_eq!(first.len(), 2);
+        assert_eq!(cursor.as_deref(), Some("offset:2"));
+
+        let offset = decode_search_cursor(&cursor.unwrap()).expect("Expected offset");
+        let (second, cursor) = search_cursor_page(offset, |offset| {
+            requested.push(offset);
+            Ok(page(offset))
+        })
+        .expect("Expected second page");
+        assert_eq!(second[1].work_id.as_deref(), Some("OL4W"));
+        assert_eq!(cursor, None);
+        assert_eq!(requested, vec![0, 2]);
+
+        assert_eq!(decode_search_cursor("page:2"), None);
+    }
+
+    #[test]
+    fn recent_changes_stop_at_since() {
+        let change = |timestamp: &str, keys: &[&str]| OpenLibraryRecentChange {
+            id: None,
+            kind: Some("edit-book".to_string()),
+            timestamp: Some(timestamp.to_string()),
+            changes: keys
+                .iter()
+                .map(|key| OpenLibraryChangedKey {
+                    key: key.to_string(),
+                    revision: None,
+                })
+                .collect(),
+        };
+        let feed = [
+            change("2024-05-02T10:00:00", &["/books/OL1M", "/works/OL1W"]),
+            change("2024-05-02T09:00:00", &["/works/OL1W", "/authors/OL1A"]),
+            change("2024-05-01T12:00:00", &["/works/OL2W"]),
+            change("2024-04-30T12:00:00", &["/works/OL3W"]),
+        ];
+        let since = parse_datetime_millis("2024-05-01").unwrap();
+        let mut requested = Vec::new();
+
+        let (changes, cursor) = recent_changes_page(since, 0, None, 2, |offset| {
+            requested.push(offset);
+            Ok(feed.iter().skip(offset as usize).take(2).cloned().collect())
+        })
+        .expect("Expected changes");
+
+        let ids: Vec<&str> = changes.iter().map(|change| change.id.as_str()).collect();
+        assert_eq!(ids, vec!["OL1M", "OL1W", "OL2W"]);
+        assert_eq!(changes[0].record_type, ChangedRecordType::Edition);
+        assert_eq!(changes[1].timestamp.as_deref(), Some("2024-05-02T10:00:00"));
+        assert_eq!(cursor, None);
+        assert_eq!(requested, vec![0, 2]);
+
+        let (_, cursor) = recent_changes_page(since, 0, None, 1, |_| Ok(vec![feed[0].clone()]))
+            .expect("Expected changes");
+        assert_eq!(cursor.as_deref(), Some("offset:5"));
+    }
+
+    #[test]
+    fn resumed_recent_changes_skip_entries_pushed_down_the_feed() {
+        let change = |id: u64, timestamp: &str, key: &str| OpenLibraryRecentChange {
+            id: Some(serde_json::Value::String(id.to_string())),
+            kind: Some("edit-book".to_string()),
+            timestamp: Some(timestamp.to_string()),
+            changes: vec![OpenLibraryChangedKey {
+                key: key.to_string(),
+                revision: None,
+            }],
+        };
+        let mut feed = vec![
+            change(4, "2024-05-02T10:00:00", "/works/OL4W"),
+            change(3, "2024-05-02T09:00:00", "/works/OL3W"),
+            change(2, "2024-05-02T08:00:00", "/works/OL2W"),
+            change(1, "2024-05-02T07:00:00", "/works/OL1W"),
+        ];
+        let since = parse_datetime_millis("2024-05-01").unwrap();
+        let read =
+            |feed: &[OpenLibraryRecentChange], offset: u64| -> Vec<OpenLibraryRecentChange> {
+                feed.iter().skip(offset as usize).take(2).cloned().collect()
+            };
+
+        // A previous call returned OL4W and OL3W.
+        let cursor = encode_changes_cursor(2, changes_anchor(&feed[1]).as_ref());
+        assert_eq!(cursor, "offset:2:1714640400000:3");
+
+        // Two newer changes arrive before the next call, shifting OL3W to index 3.
+        feed.insert(0, change(5, "2024-05-02T11:00:00", "/works/OL5W"));
+        feed.insert(0, change(6, "2024-05-02T12:00:00", "/works/OL6W"));
+        let (offset, anchor) = decode_changes_cursor(&cursor).expect("Expected cursor");
+        let (resumed, _) = recent_changes_page(since, offset, anchor.as_ref(), 2, |offset| {
+            Ok(read(&feed, offset))
+        })
+        .expect("Expected changes");
+
+        let ids: Vec<&str> = resumed.iter().map(|change| change.id.as_str()).collect();
+        assert_eq!(ids, vec!["OL2W", "OL1W"]);
+        assert_eq!(decode_changes_cursor("offset:2:soon:3"), None);
+    }
+
+    #[test]
+    fn no_dedup_keeps_duplicate_records_and_images() {
+        let record = OpenLibraryBookRecord {
+            title: "The Hobbit".to_string(),
+            work_id: Some("OL45804W".to_string()),
+            cover_ids: vec![14625765],
+            ..Default::default()
+        };
+        let records = vec![record.clone(), record];
+        let options = LookupOptions {
+            no_dedup: true,
+            ..Default::default()
+        };
+
+        assert_eq!(finalize_records(records.clone(), &options).len(), 2);
         assert_eq!(
-            normalize_exact_isbn_search("978-0-14-032872-1"),
-            Some("9780140328721".to_string())
+            finalize_records(records.clone(), &LookupOptions::default()).len(),
+            1
         );
+        assert_eq!(ordered_images(&records, &options).len(), 2);
+        assert_eq!(ordered_images(&records, &LookupOptions::default()).len(), 1);
     }
 
     #[test]
-    fn normalize_exact_isbn_search_accepts_isbn10_with_x() {
+    fn builds_book_queries_from_loose_input() {
+        let book = |query: RsLookupQuery| match query {
+            RsLookupQuery::Book(book) => book,
+            _ => panic!("Expected a book query"),
+        };
+
+        let isbn = book(build_book_query("0-14-032872-6"));
         assert_eq!(
-            normalize_exact_isbn_search("0-684-84328-5"),
-            Some("0684843285".to_string())
+            isbn.ids.and_then(|ids| ids.isbn13).as_deref(),
+            Some("9780140328721")
         );
+        assert_eq!(isbn.name, None);
+
+        let mistyped = book(build_book_query("0-14-032872-8"));
+        assert_eq!(mistyped.ids, None);
+        assert_eq!(mistyped.name.as_deref(), Some("0-14-032872-8"));
+
+        let work = book(build_book_query("/works/OL45804W"));
         assert_eq!(
-            normalize_exact_isbn_search("0-8044-2957-x"),
-            Some("080442957X".to_string())
+            work.ids.and_then(|ids| ids.openlibrary_work_id).as_deref(),
+            Some("OL45804W")
+        );
+
+        let edition = book(build_book_query("OL7353617M"));
+        assert_eq!(
+            edition
+                .ids
+                .and_then(|ids| ids.openlibrary_edition_id)
+                .as_deref(),
+            Some("OL7353617M")
         );
+
+        let oclc = book(build_book_query("(OCoLC)28419896"));
+        assert_eq!(oclc.name.as_deref(), Some("oclc:28419896"));
+        assert!(oclc.ids.is_none());
+
+        let title = book(build_book_query("  The Hobbit "));
+        assert_eq!(title.name.as_deref(), Some("The Hobbit"));
+        assert!(title.ids.is_none());
+
+        let empty = book(build_book_query("   "));
+        assert_eq!(empty.name, None);
     }
 
     #[test]
-    fn normalize_exact_isbn_search_rejects_non_exact_values() {
+    fn debug_sources_expose_pre_merge_records() {
+        let work = OpenLibraryBookRecord {
+            title: "The Hobbit".to_string(),
+            work_id: Some("OL45804W".to_string()),
+            subjects: vec!["Fantasy".to_string()],
+            ..Default::default()
+        };
+        let edition = OpenLibraryBookRecord {
+            title: "The Hobbit, or There and Back Again".to_string(),
+            edition_id: Some("OL7353617M".to_string()),
+            isbn13: Some("9780261103344".to_string()),
+            description: Some("word ".repeat(100)),
+            ..Default::default()
+        };
+
+        let mut merged =
+            merge_work_with_edition(work.clone(), Some(edition.clone()), Default::default());
+        merged.debug_sources = Some(work_sources(&work, Some(&edition)));
+        let result = openlibrary_book_to_result(merged, &LookupOptions::default());
+
+        let RsLookupMetadataResult::Book(book) = result.metadata else {
+            panic!("Expected Book metadata");
+        };
+        let sources = &book.params.expect("Expected params")["_sources"];
+        assert_eq!(sources["work"]["workId"], "OL45804W");
+        assert_eq!(sources["work"]["subjects"][0], "Fantasy");
+        assert_eq!(sources["edition"]["editionId"], "OL7353617M");
         assert_eq!(
-            normalize_exact_isbn_search("The Hobbit 9780140328721"),
-            None
+            sources["edition"]["title"],
+            "The Hobbit, or There and Back Again"
         );
-        assert_eq!(normalize_exact_isbn_search("isbn 9780140328721"), None);
-        assert_eq!(normalize_exact_isbn_search(""), None);
+        assert!(
+            sources["edition"]["description"]
+                .as_str()
+                .unwrap()
+                .chars()
+                .count()
+                <= 201
+        );
+    }
+
+    #[test]
+    fn retries_only_idempotent_requests() {
+        let get = build_http_request(build_isbn_url("9780261103344"), None);
+        let mut attempts = 0;
+        let outcome = send_with_retries(
+            &get,
+            2,
+            |_| {
+                attempts += 1;
+                attempts
+            },
+            |attempt| *attempt < 2,
+        );
+        assert_eq!(outcome, 2);
+        assert_eq!(attempts, 2);
+
+        let post = HttpRequest {
+            method: Some("POST".into()),
+            ..get
+        };
+        let mut attempts = 0;
+        send_with_retries(
+            &post,
+            2,
+            |_| {
+                attempts += 1;
+            },
+            |_| true,
+        );
+        assert_eq!(attempts, 1);
+    }
+
+    #[test]
+    fn drops_blank_records() {
+        let blank: OpenLibraryEditionResponse = serde_json::from_value(serde_json::json!({
+            "title": "   "
+        }))
+        .expect("Expected valid edition json");
+        let untitled: OpenLibraryEditionResponse = serde_json::from_value(serde_json::json!({
+            "key": "/books/OL7353617M",
+            "title": ""
+        }))
+        .expect("Expected valid edition json");
+        let records = vec![
+            book_record_from_edition_response(&blank),
+            book_record_from_edition_response(&untitled),
+        ];
+
+        let kept = finalize_records(records, &LookupOptions::default());
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].edition_id.as_deref(), Some("OL7353617M"));
+    }
+
+    #[test]
+    fn ambiguous_works_follow_on_ambiguous() {
+        let record = OpenLibraryBookRecord {
+            title: "The Hobbit".to_string(),
+            edition_id: Some("OL7353617M".to_string()),
+            work_id: Some("OL45804W".to_string()),
+            work_ids: vec!["OL45804W".to_string(), "OL27482W".to_string()],
+            ..Default::default()
+        };
+
+        let no_fetch =
+            |_: &str| -> Option<OpenLibraryBookRecord> { panic!("No work lookup expected") };
+
+        let first = split_ambiguous_works(record.clone(), AmbiguityMode::First, no_fetch)
+            .expect("Expected records");
+        assert_eq!(first.len(), 1);
+        assert_eq!(first[0].work_id.as_deref(), Some("OL45804W"));
+
+        let error = split_ambiguous_works(record.clone(), AmbiguityMode::Error, no_fetch)
+            .expect_err("Expected a conflict");
+        assert_eq!(error.1, 409);
+        assert!(error.0.to_string().contains("OL27482W"));
+
+        let all = split_ambiguous_works(record.clone(), AmbiguityMode::All, |work_id| {
+            assert_eq!(work_id, "OL27482W");
+            Some(OpenLibraryBookRecord {
+                title: "The Lord of the Rings".to_string(),
+                work_id: Some(work_id.to_string()),
+                ..Default::default()
+            })
+        })
+        .expect("Expected records");
+        let work_ids: Vec<_> = all
+            .iter()
+            .map(|record| record.work_id.as_deref().unwrap())
+            .collect();
+        assert_eq!(work_ids, vec!["OL45804W", "OL27482W"]);
+        assert_eq!(all[0].title, "The Hobbit");
+        assert_eq!(all[1].title, "The Lord of the Rings");
+        assert_eq!(all[1].edition_id, None);
+        assert_eq!(finalize_records(all, &LookupOptions::default()).len(), 2);
+
+        let unavailable = split_ambiguous_works(record.clone(), AmbiguityMode::All, |_| None)
+            .expect("Expected records");
+        assert_eq!(unavailable.len(), 1);
+
+        let single = OpenLibraryBookRecord {
+            work_ids: vec!["OL45804W".to_string()],
+            ..record
+        };
+        assert_eq!(
+            split_ambiguous_works(single, AmbiguityMode::Error, no_fetch)
+                .expect("Expected records")
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn all_editions_dedup_keeps_each_edition_once() {
+        let edition = |edition_id: &str| OpenLibraryBookRecord {
+            title: "The Hobbit".to_string(),
+            work_id: Some("OL45804W".to_string()),
+            edition_id: Some(edition_id.to_string()),
+            ..Default::default()
+        };
+        let records = vec![edition("OL1M"), edition("OL2M"), edition("OL1M")];
+
+        let options = LookupOptions {
+            all_editions: true,
+            ..Default::default()
+        };
+        let kept: Vec<Option<String>> = finalize_records(records.clone(), &options)
+            .into_iter()
+            .map(|record| record.edition_id)
+            .collect();
+        assert_eq!(
+            kept,
+            vec![Some("OL1M".to_string()), Some("OL2M".to_string())]
+        );
+
+        assert_eq!(
+            finalize_records(records, &LookupOptions::default()).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn isbn_only_canonical_exclude_drops_records_without_isbn() {
+        let records = vec![
+            OpenLibraryBookRecord {
+                title: "The Hobbit".to_string(),
+                isbn13: Some("9780140328721".to_string()),
+                ..Default::default()
+            },
+            OpenLibraryBookRecord {
+                title: "The Silmarillion".to_string(),
+                work_id: Some("OL27495W".to_string()),
+                ..Default::default()
+            },
+        ];
+        let options = LookupOptions::from_params(Some(&HashMap::from([(
+            "isbn_only_canonical".to_string(),
+            "exclude".to_string(),
+        )])));
+
+        let kept = finalize_records(records.clone(), &options);
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].title, "The Hobbit");
+        assert_eq!(
+            finalize_records(records, &LookupOptions::default()).len(),
+            2
+        );
+    }
+
+    #[test]
+    fn require_description_drops_records_without_overview() {
+        let described = OpenLibraryBookRecord {
+            title: "The Hobbit".to_string(),
+            edition_id: Some("OL7353617M".to_string()),
+            description: Some("A hobbit goes on an adventure.".to_string()),
+            ..Default::default()
+        };
+        let bare = OpenLibraryBookRecord {
+            title: "The Silmarillion".to_string(),
+            edition_id: Some("OL1M".to_string()),
+            description: Some("  ".to_string()),
+            ..Default::default()
+        };
+        let mut kept = vec![described, bare];
+
+        // No work to fetch from, so the blank description stays blank.
+        fill_work_descriptions(&mut kept, |_| panic!("No work lookup expected"));
+        kept.retain(has_description);
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].edition_id.as_deref(), Some("OL7353617M"));
+    }
+
+    #[test]
+    fn search_hits_take_their_work_description() {
+        let mut records = vec![
+            book_record_from_search_doc(&search_doc("/works/OL45804W", "The Hobbit")).unwrap(),
+            book_record_from_search_doc(&search_doc("/works/OL1W", "Unknown")).unwrap(),
+            book_record_from_search_doc(&search_doc("/works/OL45804W", "The Hobbit")).unwrap(),
+        ];
+        assert!(records.iter().all(|record| record.description.is_none()));
+        let mut fetched = Vec::new();
+
+        fill_work_descriptions(&mut records, |work_id| {
+            fetched.push(work_id.to_string());
+            (work_id == "OL45804W").then(|| "Bilbo's journey".to_string())
+        });
+
+        assert_eq!(fetched, vec!["OL45804W", "OL1W"]);
+        assert_eq!(records[0].description.as_deref(), Some("Bilbo's journey"));
+        assert_eq!(records[1].description, None);
+        assert_eq!(records[2].description.as_deref(), Some("Bilbo's journey"));
+        records.retain(has_description);
+        assert_eq!(records.len(), 2);
+    }
+
+    #[test]
+    fn required_descriptions_make_no_work_request_when_enrichment_is_off() {
+        let described = OpenLibraryBookRecord {
+            title: "The Hobbit".to_string(),
+            work_id: Some("OL45804W".to_string()),
+            description: Some("Bilbo's journey".to_string()),
+            ..Default::default()
+        };
+        let bare = OpenLibraryBookRecord {
+            title: "Unknown".to_string(),
+            work_id: Some("OL1W".to_string()),
+            ..Default::default()
+        };
+        let options = LookupOptions {
+            require_description: true,
+            enrichment: EnrichmentLevel::Off,
+            ..Default::default()
+        };
+        let mut fetched = 0;
+
+        let records = retain_described(vec![described, bare], &options, |_| {
+            fetched += 1;
+            Some("Fetched".to_string())
+        });
+
+        assert_eq!(fetched, 0);
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].title, "The Hobbit");
+    }
+
+    #[test]
+    fn envelope_adds_meta_when_enabled() {
+        let record = OpenLibraryBookRecord {
+            title: "The Hobbit".to_string(),
+            ..Default::default()
+        };
+        let context = LookupContext::new(LookupOptions {
+            with_envelope: true,
+            ..Default::default()
+        });
+        context
+            .endpoint
+            .replace(Some(build_isbn_url("9780261103344")));
+
+        let results = records_to_results(vec![record.clone()], &context);
+        let RsLookupMetadataResult::Book(book) = &results[0].metadata else {
+            panic!("Expected Book metadata");
+        };
+        let meta = &book.params.as_ref().expect("Expected params")["_meta"];
+        assert!(meta.get("durationMs").is_none());
+        assert!(meta["rateLimitRemaining"].is_null());
+        assert_eq!(meta["baseUrl"], "https://openlibrary.org");
+        assert_eq!(
+            meta["endpoint"],
+            "https://openlibrary.org/isbn/9780261103344.json"
+        );
+
+        let results =
+            records_to_results(vec![record], &LookupContext::new(LookupOptions::default()));
+        let RsLookupMetadataResult::Book(book) = &results[0].metadata else {
+            panic!("Expected Book metadata");
+        };
+        assert!(book
+            .params
+            .as_ref()
+            .expect("Expected params")
+            .get("_meta")
+            .is_none());
+    }
+
+    #[test]
+    fn edition_authors_come_from_the_work() {
+        let work: OpenLibraryWorkResponse = serde_json::from_value(serde_json::json!({
+            "key": "/works/OL262758W",
+            "authors": [
+                {"author": {"key": "/authors/OL26320A"}},
+                {"author": {"key": "/authors/OL9999999A"}}
+            ]
+        }))
+        .expect("Expected valid work json");
+        let mut record = OpenLibraryBookRecord {
+            title: "The Hobbit".to_string(),
+            work_id: Some("OL262758W".to_string()),
+            ..Default::default()
+        };
+
+        attach_work_authors(&mut record, &work, |author_id| {
+            (author_id == "OL26320A").then(|| OpenLibraryAuthorResponse {
+                name: Some("J.R.R. Tolkien".to_string()),
+                ..Default::default()
+            })
+        });
+
+        assert_eq!(record.authors, vec!["J.R.R. Tolkien".to_string()]);
+        assert_eq!(record.author_keys, vec!["OL26320A".to_string()]);
+
+        attach_work_authors(&mut record, &work, |_| panic!("Authors already known"));
+    }
+
+    #[test]
+    fn work_lookup_authors_are_fetched_once_per_key() {
+        let work: OpenLibraryWorkResponse = serde_json::from_value(serde_json::json!({
+            "key": "/works/OL893415W",
+            "title": "Good Omens",
+            "authors": [
+                {"author": {"key": "/authors/OL25712A"}},
+                {"author": {"key": "/authors/OL53305A"}},
+                {"author": {"key": "/authors/OL25712A"}}
+            ]
+        }))
+        .expect("Expected valid work json");
+        let mut merged = merge_work_with_edition(
+            book_record_from_work_response(&work),
+            None,
+            options::CoverPreference::Work,
+        );
+        let mut fetched = Vec::new();
+
+        attach_work_authors(&mut merged, &work, |author_id| {
+            fetched.push(author_id.to_string());
+            Some(OpenLibraryAuthorResponse {
+                name: Some(format!("Author {author_id}")),
+                ..Default::default()
+            })
+        });
+
+        assert_eq!(fetched, vec!["OL25712A", "OL53305A"]);
+        assert_eq!(merged.authors, vec!["Author OL25712A", "Author OL53305A"]);
+        assert_eq!(merged.author_keys, vec!["OL25712A", "OL53305A"]);
+    }
+
+    #[test]
+    fn fuzzy_isbn_recovers_a_one_off_error() {
+        let mut attempts = Vec::new();
+        let records = recover_fuzzy_isbn("97801403287210", |candidate| {
+            attempts.push(candidate.to_string());
+            if candidate == "9780140328721" {
+                Ok(vec![OpenLibraryBookRecord {
+                    title: "Fantastic Mr Fox".to_string(),
+                    isbn13: Some(candidate.to_string()),
+                    ..Default::default()
+                }])
+            } else {
+                Err(WithReturnCode::new(
+                    extism_pdk::Error::msg("Not found"),
+                    404,
+                ))
+            }
+        })
+        .expect("Expected a recovered ISBN");
+
+        assert_eq!(records[0].isbn13.as_deref(), Some("9780140328721"));
+        assert_eq!(records[0].resolved_by, Some(ResolvedBy::FuzzyIsbn));
+        assert!(records[0].confidence.unwrap() < 1.0);
+        assert_eq!(attempts, vec!["9780140328721"]);
+    }
+
+    #[test]
+    fn fuzzy_isbn_recovers_a_missing_check_digit() {
+        let records = recover_fuzzy_isbn("978014032872", |candidate| {
+            if candidate == "9780140328721" {
+                Ok(vec![OpenLibraryBookRecord {
+                    title: "Fantastic Mr Fox".to_string(),
+                    isbn13: Some(candidate.to_string()),
+                    ..Default::default()
+                }])
+            } else {
+                Err(WithReturnCode::new(
+                    extism_pdk::Error::msg("Not found"),
+                    404,
+                ))
+            }
+        })
+        .expect("Expected a recovered ISBN");
+        assert_eq!(records[0].isbn13.as_deref(), Some("9780140328721"));
+    }
+
+    #[test]
+    fn empty_query_ok_returns_no_records() {
+        let options = LookupOptions {
+            empty_query_ok: true,
+            ..Default::default()
+        };
+        assert!(unsupported_query(None, &options)
+            .expect("Expected an empty result")
+            .is_empty());
+        assert!(unsupported_query(Some("  "), &options)
+            .expect("Expected an empty result")
+            .is_empty());
+        assert_eq!(unsupported_query(Some("a"), &options).unwrap_err().1, 404);
+
+        let error = unsupported_query(None, &LookupOptions::default())
+            .expect_err("Expected the default 404");
+        assert_eq!(error.1, 404);
+    }
+
+    #[test]
+    fn fuzzy_isbn_rejects_unrecoverable_input() {
+        let mut attempts = 0;
+        let error = recover_fuzzy_isbn("979000000000", |_| {
+            attempts += 1;
+            Err(WithReturnCode::new(
+                extism_pdk::Error::msg("Not found"),
+                404,
+            ))
+        })
+        .expect_err("Expected no recovery");
+        assert_eq!(error.1, 404);
+        assert_eq!(attempts, MAX_FUZZY_ISBN_LOOKUPS);
+
+        let error = recover_fuzzy_isbn("12345", |_| panic!("No candidate expected"))
+            .expect_err("Expected no recovery");
+        assert_eq!(error.1, 404);
+    }
+
+    #[test]
+    fn results_expose_their_lookup_path() {
+        let record = OpenLibraryBookRecord {
+            title: "The Hobbit".to_string(),
+            isbn13: Some("9780261103344".to_string()),
+            ..Default::default()
+        };
+
+        let by_isbn = resolved_exactly(record.clone(), ResolvedBy::Isbn);
+        let result = openlibrary_book_to_result(by_isbn, &LookupOptions::default());
+        let RsLookupMetadataResult::Book(book) = result.metadata else {
+            panic!("Expected Book metadata");
+        };
+        let params = book.params.expect("Expected params");
+        assert_eq!(params["resolvedBy"], "isbn");
+        assert_eq!(params["confidence"], 1.0);
+
+        let by_name = score_search_records(vec![record], "The Hobbit", true).remove(0);
+        let result = openlibrary_book_to_result(by_name, &LookupOptions::default());
+        let RsLookupMetadataResult::Book(book) = result.metadata else {
+            panic!("Expected Book metadata");
+        };
+        let params = book.params.expect("Expected params");
+        assert_eq!(params["resolvedBy"], "search");
+        assert!(params["confidence"].as_f64().is_some());
+    }
+
+    #[test]
+    fn require_all_tokens_drops_partial_title_matches() {
+        let records = vec![
+            OpenLibraryBookRecord {
+                title: "The Name of the Wind".to_string(),
+                ..Default::default()
+            },
+            OpenLibraryBookRecord {
+                title: "The Wind in the Willows".to_string(),
+                ..Default::default()
+            },
+        ];
+
+        assert_eq!(
+            retain_title_matches(records.clone(), "name of the wind", false).len(),
+            2
+        );
+        let kept = retain_title_matches(records, "name of the wind", true);
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].title, "The Name of the Wind");
+    }
+
+    #[test]
+    fn require_isbn_drops_records_without_isbn13() {
+        let records = vec![
+            OpenLibraryBookRecord {
+                title: "The Hobbit".to_string(),
+                isbn13: Some("9780261103344".to_string()),
+                ..Default::default()
+            },
+            OpenLibraryBookRecord {
+                title: "The Hobbit".to_string(),
+                edition_id: Some("OL1M".to_string()),
+                ..Default::default()
+            },
+        ];
+
+        assert_eq!(retain_isbn_records(records.clone(), false).len(), 2);
+
+        let kept = retain_isbn_records(records, true);
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].isbn13.as_deref(), Some("9780261103344"));
+    }
+
+    #[test]
+    fn cover_ranking_moves_largest_cover_first() {
+        let mut record = OpenLibraryBookRecord {
+            title: "The Hobbit".to_string(),
+            cover_id: Some(1),
+            cover_ids: vec![1, 2, 3, 4],
+            ..Default::default()
+        };
+
+        rank_record_covers(&mut record, |cover_id| match cover_id {
+            1 => Some(OpenLibraryCoverMetadata {
+                width: Some(180),
+                height: Some(270),
+                ..Default::default()
+            }),
+            2 => None,
+            3 => Some(OpenLibraryCoverMetadata {
+                width: Some(600),
+                height: Some(900),
+                ..Default::default()
+            }),
+            _ => Some(OpenLibraryCoverMetadata {
+                width: None,
+                height: Some(900),
+                ..Default::default()
+            }),
+        });
+
+        assert_eq!(record.cover_ids, vec![3, 1, 2, 4]);
+        assert_eq!(record.cover_id, Some(3));
+    }
+
+    #[test]
+    fn cover_source_comes_from_primary_metadata() {
+        let mut record = OpenLibraryBookRecord {
+            title: "The Hobbit".to_string(),
+            cover_id: Some(1),
+            cover_ids: vec![1, 2],
+            ..Default::default()
+        };
+
+        rank_record_covers(&mut record, |cover_id| {
+            Some(OpenLibraryCoverMetadata {
+                width: Some(100 * cover_id),
+                height: Some(100),
+                source: (cover_id == 2).then(|| "amazon".to_string()),
+            })
+        });
+        assert_eq!(record.cover_id, Some(2));
+        assert_eq!(record.cover_source.as_deref(), Some("amazon"));
+
+        let result = openlibrary_book_to_result(record, &LookupOptions::default());
+        if let RsLookupMetadataResult::Book(book) = result.metadata {
+            assert_eq!(
+                book.params.expect("Expected params")["coverSource"],
+                "amazon"
+            );
+        } else {
+            panic!("Expected Book metadata");
+        }
+
+        let mut unsourced = OpenLibraryBookRecord {
+            cover_ids: vec![7],
+            ..Default::default()
+        };
+        rank_record_covers(&mut unsourced, |_| {
+            Some(OpenLibraryCoverMetadata {
+                source: Some(" ".to_string()),
+                ..Default::default()
+            })
+        });
+        assert!(unsourced.cover_source.is_none());
     }
 
     #[test]
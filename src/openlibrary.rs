@@ -1,17 +1,26 @@
-use serde::Deserialize;
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize};
+use std::collections::HashMap;
 
-#[derive(Debug, Deserialize, Clone)]
+use crate::{
+    isbn::{canonical_isbn13, isbn13_checksum_valid},
+    options::{Binding, CoverPreference, CoverSize, EditionPreference},
+};
+
+#[derive(Debug, Deserialize, Clone, Default)]
 pub struct OpenLibrarySearchResponse {
     #[serde(default)]
     pub docs: Vec<OpenLibrarySearchDoc>,
+    #[serde(rename = "numFound")]
+    pub num_found: Option<u64>,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, Default)]
 pub struct OpenLibrarySearchDoc {
     #[serde(default)]
     pub key: String,
     #[serde(default)]
     pub title: String,
+    pub subtitle: Option<String>,
     #[serde(default)]
     pub edition_key: Vec<String>,
     #[serde(default)]
@@ -27,49 +36,315 @@ pub struct OpenLibrarySearchDoc {
     #[serde(default)]
     pub subject: Vec<String>,
     #[serde(default)]
+    pub subject_key: Vec<String>,
+    #[serde(default)]
     pub publisher: Vec<String>,
     pub number_of_pages_median: Option<i64>,
+    #[serde(default)]
+    pub lccn: Vec<String>,
+    #[serde(default)]
+    pub oclc: Vec<String>,
+    #[serde(default)]
+    pub id_goodreads: Vec<String>,
+    pub edition_count: Option<u64>,
+    /// Internet Archive identifiers of the work's scanned editions.
+    #[serde(default)]
+    pub ia: Vec<String>,
+    pub ebook_access: Option<String>,
+    /// Internet Archive identifier of the scan `ebook_access` describes.
+    pub lending_identifier_s: Option<String>,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, Default)]
 pub struct OpenLibraryWorkResponse {
     #[serde(default)]
     pub key: String,
     #[serde(default)]
     pub title: String,
+    pub subtitle: Option<String>,
     pub description: Option<OpenLibraryDescription>,
     #[serde(default)]
     pub covers: Vec<i64>,
     #[serde(default)]
     pub subjects: Vec<String>,
     pub first_publish_date: Option<String>,
+    #[serde(default)]
+    pub other_titles: Vec<String>,
+    pub last_modified: Option<OpenLibraryDescription>,
+    pub revision: Option<u64>,
+    #[serde(default)]
+    pub authors: Vec<OpenLibraryWorkAuthorRef>,
+    #[serde(default, deserialize_with = "lenient_entries")]
+    pub series: Vec<OpenLibraryWorkSeries>,
+}
+
+/// Reads a list entry by entry: nulls and entries of an unexpected shape are skipped, and
+/// a value that is not a list reads as empty, instead of failing the whole response.
+fn lenient_entries<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: DeserializeOwned,
+{
+    Ok(match serde_json::Value::deserialize(deserializer)? {
+        serde_json::Value::Array(entries) => entries
+            .into_iter()
+            .filter_map(|entry| serde_json::from_value(entry).ok())
+            .collect(),
+        _ => vec![],
+    })
 }
 
+/// Series membership listed on a work: either a catalogue string like the edition
+/// `series`, or structured `{"series": {"key", "name"}, "position"}` data (only the name
+/// is read from the series reference).
 #[derive(Debug, Deserialize, Clone)]
+#[serde(untagged)]
+pub enum OpenLibraryWorkSeries {
+    Text(String),
+    Structured {
+        name: Option<String>,
+        series: Option<OpenLibrarySeriesRef>,
+        /// Volume as a number or a string (`"3"`, `"Book 3"`).
+        position: Option<serde_json::Value>,
+    },
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct OpenLibrarySeriesRef {
+    pub name: Option<String>,
+}
+
+/// `{"author": {"key": "/authors/OL26320A"}, "type": ...}` entries of a work.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct OpenLibraryWorkAuthorRef {
+    pub author: Option<OpenLibraryKeyRef>,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
 pub struct OpenLibraryEditionResponse {
     #[serde(default)]
     pub key: String,
     #[serde(default)]
     pub title: String,
+    pub subtitle: Option<String>,
+    pub ocaid: Option<String>,
     pub description: Option<OpenLibraryDescription>,
     #[serde(default)]
     pub works: Vec<OpenLibraryKeyRef>,
     #[serde(default)]
     pub isbn_13: Vec<String>,
     #[serde(default)]
+    pub isbn_10: Vec<String>,
+    #[serde(default)]
     pub covers: Vec<i64>,
     pub number_of_pages: Option<i64>,
+    /// Free-text binding (`Hardcover`, `Mass Market Paperback`, ...).
+    pub physical_format: Option<String>,
+    /// Catalogued extent, e.g. `xii, 310 p.`.
+    pub pagination: Option<String>,
     pub publish_date: Option<String>,
     #[serde(default)]
-    pub languages: Vec<OpenLibraryKeyRef>,
+    pub languages: Vec<OpenLibraryLanguage>,
     #[serde(default)]
     pub publishers: Vec<String>,
+    #[serde(default)]
+    pub publish_places: Vec<String>,
+    pub publish_country: Option<String>,
+    #[serde(default)]
+    pub table_of_contents: Vec<serde_json::Value>,
+    #[serde(default)]
+    pub other_titles: Vec<String>,
+    #[serde(default)]
+    pub contributions: Vec<String>,
+    #[serde(default)]
+    pub contributors: Vec<OpenLibraryContributorRef>,
+    #[serde(default)]
+    pub series: Vec<String>,
+    #[serde(default)]
+    pub lccn: Vec<String>,
+    #[serde(default)]
+    pub oclc_numbers: Vec<String>,
+    pub last_modified: Option<OpenLibraryDescription>,
+    pub revision: Option<u64>,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct OpenLibraryContributorRef {
+    pub role: Option<String>,
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
 pub struct OpenLibraryWorkEditionsResponse {
     #[serde(default)]
     pub entries: Vec<OpenLibraryEditionResponse>,
+    /// Editions of the work in total, beyond the returned page.
+    pub size: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct OpenLibrarySearchInsideResponse {
+    #[serde(default)]
+    pub hits: OpenLibrarySearchInsideHits,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct OpenLibrarySearchInsideHits {
+    #[serde(default)]
+    pub hits: Vec<OpenLibrarySearchInsideHit>,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct OpenLibrarySearchInsideHit {
+    #[serde(default)]
+    pub highlight: OpenLibrarySearchInsideHighlight,
+    pub edition: Option<OpenLibraryEditionResponse>,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct OpenLibrarySearchInsideHighlight {
+    #[serde(default)]
+    pub text: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct OpenLibraryAuthorResponse {
+    /// `/type/redirect` for merged authors, whose `location` names the surviving record.
+    #[serde(rename = "type")]
+    pub kind: Option<OpenLibraryKeyRef>,
+    pub location: Option<String>,
+    pub name: Option<String>,
+    pub bio: Option<OpenLibraryDescription>,
+    pub birth_date: Option<String>,
+    pub death_date: Option<String>,
+    #[serde(default)]
+    pub alternate_names: Vec<String>,
+    /// Author photo ids in the covers store; `-1` marks a removed photo.
+    #[serde(default)]
+    pub photos: Vec<i64>,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct OpenLibrarySubjectResponse {
+    pub work_count: Option<u64>,
+    #[serde(default)]
+    pub works: Vec<OpenLibrarySubjectWork>,
+}
+
+/// An entry of the `/recentchanges.json` feed, newest first.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct OpenLibraryRecentChange {
+    /// Changeset id, a numeric string (older dumps use a number).
+    pub id: Option<serde_json::Value>,
+    /// `edit-book`, `add-book`, `merge-authors`, ...
+    pub kind: Option<String>,
+    pub timestamp: Option<String>,
+    #[serde(default)]
+    pub changes: Vec<OpenLibraryChangedKey>,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct OpenLibraryChangedKey {
+    pub key: String,
+    pub revision: Option<u64>,
+}
+
+/// Which record a recent change touched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ChangedRecordType {
+    Work,
+    Edition,
+}
+
+pub fn recent_change_id(change: &OpenLibraryRecentChange) -> Option<String> {
+    match change.id.as_ref()? {
+        serde_json::Value::String(id) => Some(id.trim().to_string()).filter(|id| !id.is_empty()),
+        serde_json::Value::Number(id) => Some(id.to_string()),
+        _ => None,
+    }
+}
+
+/// Works and editions touched by a change with their new revision, in feed order.
+/// Authors, lists and other pages in the same changeset are skipped.
+pub fn changed_records(
+    change: &OpenLibraryRecentChange,
+) -> Vec<(ChangedRecordType, String, Option<u64>)> {
+    change
+        .changes
+        .iter()
+        .filter_map(|changed| {
+            let key = changed.key.trim();
+            let (record_type, prefix) = if key.starts_with("/works/") {
+                (ChangedRecordType::Work, "works")
+            } else if key.starts_with("/books/") {
+                (ChangedRecordType::Edition, "books")
+            } else {
+                return None;
+            };
+            normalize_openlibrary_id(key, prefix).map(|id| (record_type, id, changed.revision))
+        })
+        .collect()
+}
+
+/// A `works` entry of `/subjects/{slug}.json`.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct OpenLibrarySubjectWork {
+    #[serde(default)]
+    pub key: String,
+    #[serde(default)]
+    pub title: String,
+    pub cover_id: Option<i64>,
+    pub cover_edition_key: Option<String>,
+    pub first_publish_year: Option<u16>,
+    pub edition_count: Option<u64>,
+    #[serde(default)]
+    pub authors: Vec<OpenLibrarySubjectAuthor>,
+    #[serde(default)]
+    pub subject: Vec<String>,
+    pub ia: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct OpenLibrarySubjectAuthor {
+    #[serde(default)]
+    pub key: String,
+    #[serde(default)]
+    pub name: String,
+}
+
+/// Cover store metadata from `/b/id/{id}.json`.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct OpenLibraryCoverMetadata {
+    pub width: Option<u64>,
+    pub height: Option<u64>,
+    /// Where the scan came from (`amazon`, `archive.org`, ...), when recorded.
+    pub source: Option<String>,
+}
+
+/// One bibkey's entry from `/api/books?jscmd=data`.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct OpenLibraryBooksDataEntry {
+    pub cover: Option<OpenLibraryDataCover>,
+}
+
+/// Cover URLs of a books data entry, one per cover store size.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct OpenLibraryDataCover {
+    pub small: Option<String>,
+    pub medium: Option<String>,
+    pub large: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct OpenLibraryRatingsResponse {
+    pub summary: Option<OpenLibraryRatingsSummary>,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct OpenLibraryRatingsSummary {
+    pub average: Option<f64>,
+    pub count: Option<u64>,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -77,6 +352,23 @@ pub struct OpenLibraryKeyRef {
     pub key: String,
 }
 
+/// Edition language: usually `{"key": "/languages/eng"}`, on some records a bare `"eng"`.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(untagged)]
+pub enum OpenLibraryLanguage {
+    Key(OpenLibraryKeyRef),
+    Code(String),
+}
+
+impl OpenLibraryLanguage {
+    pub fn code(&self) -> Option<String> {
+        match self {
+            OpenLibraryLanguage::Key(key) => language_from_key(&key.key),
+            OpenLibraryLanguage::Code(code) => language_from_key(code),
+        }
+    }
+}
+
 #[derive(Debug, Deserialize, Clone)]
 #[serde(untagged)]
 pub enum OpenLibraryDescription {
@@ -107,25 +399,171 @@ impl OpenLibraryDescription {
     }
 }
 
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct OpenLibraryTocEntry {
+    pub title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pagenum: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OpenLibraryContributor {
+    pub name: String,
+    pub role: String,
+}
+
+/// Extra data from `/authors/{id}.json`, keyed by the author id used in `author_keys`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OpenLibraryAuthorDetails {
+    pub key: String,
+    pub birth_date: Option<String>,
+    pub death_date: Option<String>,
+    pub alternate_names: Vec<String>,
+    pub photo_id: Option<u64>,
+    pub bio: Option<String>,
+    /// Signed years (negative for BC) parsed from the free-text dates.
+    pub birth_year: Option<i32>,
+    pub death_year: Option<i32>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, PartialEq)]
+pub struct OpenLibraryRating {
+    pub average: f64,
+    pub count: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OpenLibrarySeries {
+    pub name: String,
+    pub position: Option<f64>,
+}
+
+/// Search index `ebook_access`: how the archive.org scan can be read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EbookAccess {
+    /// Public domain or otherwise freely readable.
+    Public,
+    /// Lendable through Open Library's controlled digital lending.
+    Borrowable,
+    /// Restricted to print-disabled patrons.
+    PrintDisabled,
+    NoEbook,
+}
+
+impl EbookAccess {
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "public" => Some(Self::Public),
+            "borrowable" => Some(Self::Borrowable),
+            "printdisabled" => Some(Self::PrintDisabled),
+            "no_ebook" => Some(Self::NoEbook),
+            _ => None,
+        }
+    }
+}
+
+/// Lookup path that produced a record, emitted as `resolvedBy`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResolvedBy {
+    Isbn,
+    Edition,
+    Work,
+    Wikidata,
+    Goodreads,
+    /// A valid ISBN one edit away from the malformed input; may be a different book.
+    FuzzyIsbn,
+    Search,
+    Subject,
+}
+
+impl ResolvedBy {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Isbn => "isbn",
+            Self::Edition => "edition",
+            Self::Work => "work",
+            Self::Wikidata => "wikidata",
+            Self::Goodreads => "goodreads",
+            Self::FuzzyIsbn => "fuzzyIsbn",
+            Self::Search => "search",
+            Self::Subject => "subject",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct OpenLibraryBookRecord {
     pub title: String,
+    /// Kept apart from `title` (`Dune` / `The Graphic Novel`).
+    pub subtitle: Option<String>,
     pub edition_id: Option<String>,
     pub work_id: Option<String>,
+    pub work_ids: Vec<String>,
     pub isbn13: Option<String>,
     pub cover_ids: Vec<u64>,
     pub cover_id: Option<u64>,
     pub publish_year: Option<u16>,
+    /// Signed year for 3-digit and BC/BCE dates; `None` for ordinary 4-digit years.
+    pub historical_year: Option<i32>,
+    /// Catalog wording the year was parsed from (`publish_date`/`first_publish_date`).
+    pub publish_date_raw: Option<String>,
+    /// Signed year the work first appeared, kept apart from an edition's own year.
+    pub first_publish_year: Option<i32>,
     pub description: Option<String>,
     pub pages: Option<u32>,
+    /// Edition pagination as catalogued (`xii, 310 p.`), alongside the numeric `pages`.
+    pub pagination: Option<String>,
     pub language: Option<String>,
     pub authors: Vec<String>,
     pub author_keys: Vec<String>,
     pub subjects: Vec<String>,
+    /// Canonical `/subjects/{slug}` keys, when the source lists them (search docs).
+    pub subject_keys: Vec<String>,
+    /// Work counts from `/subjects/{slug}.json`, keyed by `openlibrary_subject_key`.
+    pub subject_work_counts: HashMap<String, u64>,
     pub publishers: Vec<String>,
+    /// Edition `publish_places`, parallel to `publishers` when the lengths match.
+    pub publish_places: Vec<String>,
+    pub table_of_contents: Vec<OpenLibraryTocEntry>,
+    pub other_titles: Vec<String>,
+    pub contributors: Vec<OpenLibraryContributor>,
+    pub confidence: Option<f64>,
+    pub edition_rating: Option<OpenLibraryRating>,
+    pub work_rating: Option<OpenLibraryRating>,
+    pub series: Option<OpenLibrarySeries>,
+    pub lccn: Option<String>,
+    pub oclc: Option<String>,
+    pub last_modified: Option<String>,
+    pub revision: Option<u64>,
+    pub match_snippet: Option<String>,
+    pub goodreads_id: Option<String>,
+    pub author_details: Vec<OpenLibraryAuthorDetails>,
+    pub resolved_by: Option<ResolvedBy>,
+    /// The source's covers were only `-1`, OpenLibrary's "known to have no cover".
+    pub no_cover: bool,
+    /// Pre-merge work and edition summaries attached by `debug_sources`.
+    pub debug_sources: Option<serde_json::Value>,
+    /// Provenance of the primary cover, from the covers metadata fetched by `rank_covers`.
+    pub cover_source: Option<String>,
+    /// Editions of the work, as counted by the search index or the work editions endpoint.
+    pub edition_count: Option<u64>,
+    /// Internet Archive identifier of the scanned book.
+    pub ocaid: Option<String>,
+    /// Only known for search docs; edition and work JSON carry no availability.
+    pub ebook_access: Option<EbookAccess>,
 }
 
 impl OpenLibraryBookRecord {
+    /// No title and no identifier: nothing a `Book` could be built from but a slug of "".
+    pub fn is_blank(&self) -> bool {
+        self.title.trim().is_empty()
+            && self.edition_id.is_none()
+            && self.work_id.is_none()
+            && self.isbn13.is_none()
+    }
+
     pub fn dedup_key(&self) -> String {
         if let Some(work_id) = &self.work_id {
             return format!("work:{work_id}");
@@ -138,6 +576,88 @@ impl OpenLibraryBookRecord {
         }
         format!("title:{}", self.title.to_ascii_lowercase())
     }
+
+    /// Like `dedup_key`, but editions of the same work stay apart.
+    pub fn edition_dedup_key(&self) -> String {
+        match &self.edition_id {
+            Some(edition_id) => format!("edition:{edition_id}"),
+            None => self.dedup_key(),
+        }
+    }
+}
+
+/// Words too common to make a title match distinctive.
+const TITLE_STOPWORDS: &[&str] = &[
+    "a", "an", "and", "at", "by", "for", "in", "of", "on", "or", "the", "to", "with",
+];
+
+/// Whether every significant token of `query` appears in `title`. A query made only of
+/// stopwords matches any title.
+pub fn title_contains_query_tokens(title: &str, query: &str) -> bool {
+    let title_tokens = normalized_tokens(title);
+    normalized_tokens(query)
+        .iter()
+        .filter(|token| !TITLE_STOPWORDS.contains(&token.as_str()))
+        .all(|token| title_tokens.contains(token))
+}
+
+fn normalized_tokens(value: &str) -> Vec<String> {
+    value
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|token| !token.is_empty())
+        .map(|token| token.to_lowercase())
+        .collect()
+}
+
+/// Scores how well a search record matches the free-text query, between 0.0 and 1.0.
+/// Title exactness dominates, then author overlap, then presence of OpenLibrary IDs.
+pub fn search_confidence(record: &OpenLibraryBookRecord, query: &str) -> f64 {
+    let query_tokens = normalized_tokens(query);
+    let title_tokens = normalized_tokens(&record.title);
+    if query_tokens.is_empty() || title_tokens.is_empty() {
+        return 0.0;
+    }
+
+    let title_score = if query_tokens == title_tokens {
+        0.6
+    } else {
+        let matched = query_tokens
+            .iter()
+            .filter(|token| title_tokens.contains(token))
+            .count();
+        0.5 * matched as f64 / query_tokens.len().max(title_tokens.len()) as f64
+    };
+
+    let author_score = if record.authors.iter().any(|author| {
+        normalized_tokens(author).iter().any(|token| {
+            token.len() > 1 && query_tokens.contains(token) && !title_tokens.contains(token)
+        })
+    }) {
+        0.2
+    } else {
+        0.0
+    };
+
+    let mut id_score = 0.0;
+    if record.isbn13.is_some() {
+        id_score += 0.1;
+    }
+    if record.edition_id.is_some() || record.work_id.is_some() {
+        id_score += 0.1;
+    }
+
+    ((title_score + author_score + id_score) * 100.0).round() / 100.0
+}
+
+/// Accepts `Q42` style Wikidata ids (any case), returning them uppercased.
+pub fn normalize_wikidata_id(value: &str) -> Option<String> {
+    let qid = value.trim().to_ascii_uppercase();
+    let digits = qid.strip_prefix('Q')?;
+    if !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit()) {
+        Some(qid)
+    } else {
+        None
+    }
 }
 
 pub fn normalize_openlibrary_id(value: &str, prefix: &str) -> Option<String> {
@@ -164,6 +684,59 @@ pub fn normalize_openlibrary_id(value: &str, prefix: &str) -> Option<String> {
     }
 }
 
+/// Converts an OpenLibrary `/type/datetime` value (`2021-10-04T04:05:33.147982`, UTC)
+/// into milliseconds since the Unix epoch.
+pub fn parse_datetime_millis(value: &str) -> Option<u64> {
+    let value = value.trim().trim_end_matches('Z');
+    let (date, time) = value.split_once('T').unwrap_or((value, "00:00:00"));
+
+    let mut date_parts = date.split('-').map(|part| part.parse::<i64>().ok());
+    let year = date_parts.next()??;
+    let month = date_parts.next()??;
+    let day = date_parts.next()??;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    let (clock, fraction) = time.split_once('.').unwrap_or((time, ""));
+    let mut clock_parts = clock.split(':').map(|part| part.parse::<i64>().ok());
+    let hours = clock_parts.next()??;
+    let minutes = clock_parts.next().unwrap_or(Some(0))?;
+    let seconds = clock_parts.next().unwrap_or(Some(0))?;
+    let millis = fraction
+        .chars()
+        .chain(std::iter::repeat('0'))
+        .take(3)
+        .collect::<String>()
+        .parse::<i64>()
+        .ok()?;
+
+    // Days from civil date, see http://howardhinnant.github.io/date_algorithms.html
+    let shifted_year = if month <= 2 { year - 1 } else { year };
+    let era = shifted_year.div_euclid(400);
+    let year_of_era = shifted_year - era * 400;
+    let month_index = (month + 9) % 12;
+    let day_of_year = (153 * month_index + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+
+    let total = ((days * 24 + hours) * 60 + minutes) * 60 + seconds;
+    u64::try_from(total * 1000 + millis).ok()
+}
+
+/// OpenLibrary's own subject slug (`str_to_key`): lowercased, URL-unsafe characters
+/// dropped and spaces turned into underscores.
+pub fn openlibrary_subject_key(subject: &str) -> String {
+    const DROPPED: &str = ";/?:@&=+$,<>#%\"{}|\\^[]`";
+    subject
+        .trim()
+        .to_lowercase()
+        .chars()
+        .filter(|c| !DROPPED.contains(*c))
+        .map(|c| if c == ' ' { '_' } else { c })
+        .collect()
+}
+
 pub fn extract_year_from_text(value: &str) -> Option<u16> {
     let bytes = value.as_bytes();
     for idx in 0..bytes.len().saturating_sub(3) {
@@ -179,7 +752,65 @@ pub fn extract_year_from_text(value: &str) -> Option<u16> {
     None
 }
 
+/// Years the 4-digit path gets wrong or skips: BC/BCE years of any length (`1200 BC`),
+/// 3-digit years (`800`) and years with an era marker (`AD 79`), signed so that BC/BCE
+/// years are negative. Bare 1-2 digit numbers are ignored, as they are usually days.
+pub fn extract_historical_year(value: &str) -> Option<i32> {
+    let tokens: Vec<String> = value
+        .split(|c: char| c.is_whitespace() || c == ',' || c == '(' || c == ')')
+        .filter(|token| !token.is_empty())
+        .map(|token| token.replace('.', "").to_ascii_uppercase())
+        .collect();
+    let before_common_era = tokens
+        .iter()
+        .any(|token| matches!(token.as_str(), "BC" | "BCE"));
+    if !before_common_era && extract_year_from_text(value).is_some() {
+        return None;
+    }
+
+    for (index, token) in tokens.iter().enumerate() {
+        if token.is_empty() || token.len() > 4 || !token.chars().all(|c| c.is_ascii_digit()) {
+            continue;
+        }
+        let Ok(year) = token.parse::<i32>() else {
+            continue;
+        };
+        if year == 0 {
+            continue;
+        }
+
+        let next = tokens.get(index + 1).map(String::as_str);
+        let previous = index.checked_sub(1).map(|index| tokens[index].as_str());
+        match (previous, next) {
+            (_, Some("BC" | "BCE")) => return Some(-year),
+            (Some("AD" | "CE"), _) | (_, Some("AD" | "CE")) => return Some(year),
+            _ if token.len() == 3 => return Some(year),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The `u16` year used for `Book.year` plus the signed historical year when only the
+/// historical path found one (BC years have no `u16` year).
+fn publish_years(value: Option<&str>) -> (Option<u16>, Option<i32>) {
+    let Some(value) = value else {
+        return (None, None);
+    };
+    match extract_historical_year(value) {
+        Some(historical) => (u16::try_from(historical).ok(), Some(historical)),
+        None => (extract_year_from_text(value), None),
+    }
+}
+
+/// Digits of `value` when they are 13 long and end in a valid ISBN-13 check digit.
 pub fn normalize_isbn13(value: &str) -> Option<String> {
+    normalize_isbn13_unchecked(value).filter(|digits| isbn13_checksum_valid(digits))
+}
+
+/// Digits of `value` when they are 13 long, check digit not verified. Used for ISBNs
+/// OpenLibrary records, which are kept as catalogued even when misprinted.
+pub fn normalize_isbn13_unchecked(value: &str) -> Option<String> {
     let digits: String = value.chars().filter(|c| c.is_ascii_digit()).collect();
     if digits.len() == 13 {
         Some(digits)
@@ -189,7 +820,9 @@ pub fn normalize_isbn13(value: &str) -> Option<String> {
 }
 
 pub fn first_isbn13(values: &[String]) -> Option<String> {
-    values.iter().find_map(|value| normalize_isbn13(value))
+    values
+        .iter()
+        .find_map(|value| normalize_isbn13_unchecked(value))
 }
 
 pub fn language_from_key(value: &str) -> Option<String> {
@@ -206,83 +839,576 @@ pub fn language_from_key(value: &str) -> Option<String> {
     }
 }
 
-pub fn encode_query_component(value: &str) -> String {
-    let mut encoded = String::with_capacity(value.len());
-    for b in value.as_bytes() {
-        match *b {
-            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
-                encoded.push(*b as char)
-            }
-            b' ' => encoded.push_str("%20"),
-            _ => encoded.push_str(&format!("%{:02X}", b)),
+/// Trims alternate titles and drops blanks, repeats and copies of the main title.
+pub fn collect_other_titles<'a>(
+    title: &str,
+    values: impl IntoIterator<Item = &'a String>,
+) -> Vec<String> {
+    let mut seen = vec![title.trim().to_lowercase()];
+    let mut other_titles = Vec::new();
+
+    for value in values {
+        let trimmed = value.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        let key = trimmed.to_lowercase();
+        if seen.contains(&key) {
+            continue;
         }
+        seen.push(key);
+        other_titles.push(trimmed.to_string());
     }
-    encoded
-}
 
-pub fn build_search_url(search: &str) -> String {
-    format!(
-        "https://openlibrary.org/search.json?q={query}&limit=25",
-        query = encode_query_component(search)
-    )
+    other_titles
 }
 
-pub fn build_isbn_url(isbn13: &str) -> String {
-    format!("https://openlibrary.org/isbn/{isbn13}.json")
+/// Maps free-form role wording ("Illustrated by", "trans.", "Editor") to a stable role.
+pub fn normalize_contributor_role(value: &str) -> String {
+    let lower = value.trim().to_lowercase();
+    let role = if lower.starts_with("ill") {
+        "illustrator"
+    } else if lower.starts_with("trans") || lower.starts_with("tr.") {
+        "translator"
+    } else if lower.starts_with("ed") {
+        "editor"
+    } else if lower.starts_with("narrat") || lower.starts_with("read") {
+        "narrator"
+    } else if lower.starts_with("intro")
+        || lower.starts_with("foreword")
+        || lower.starts_with("preface")
+        || lower.starts_with("afterword")
+    {
+        "introduction"
+    } else {
+        "contributor"
+    };
+    role.to_string()
 }
 
-pub fn build_edition_url(edition_id: &str) -> String {
-    format!("https://openlibrary.org/books/{edition_id}.json")
+fn role_suffix(value: &str) -> Option<String> {
+    let token = value.trim().trim_end_matches('.').to_ascii_lowercase();
+    match token.as_str() {
+        "ill" | "illus" | "illustrator" | "trans" | "tr" | "translator" | "ed" | "eds"
+        | "editor" | "narrator" => Some(normalize_contributor_role(&token)),
+        _ => None,
+    }
 }
 
-pub fn build_work_url(work_id: &str) -> String {
-    format!("https://openlibrary.org/works/{work_id}.json")
+/// Parses an edition `contributions` entry such as "Illustrated by Pauline Baynes",
+/// "Pauline Baynes (Illustrator)" or "Baynes, Pauline, ill.".
+pub fn parse_contribution(value: &str) -> Option<OpenLibraryContributor> {
+    let trimmed = value.trim().trim_end_matches('.').trim();
+    if trimmed.is_empty() {
+        return None;
+    }
+
+    let (name, role) = if let Some(index) = trimmed.to_ascii_lowercase().find(" by ") {
+        (
+            &trimmed[index + 4..],
+            normalize_contributor_role(&trimmed[..index]),
+        )
+    } else if let Some(open) = trimmed.rfind('(').filter(|_| trimmed.ends_with(')')) {
+        (
+            &trimmed[..open],
+            normalize_contributor_role(&trimmed[open + 1..trimmed.len() - 1]),
+        )
+    } else if let Some((head, role)) = trimmed
+        .rsplit_once(',')
+        .and_then(|(head, tail)| Some((head, role_suffix(tail)?)))
+    {
+        (head, role)
+    } else {
+        (trimmed, "contributor".to_string())
+    };
+
+    let name = name.trim().trim_end_matches(',').trim();
+    if name.is_empty() {
+        None
+    } else {
+        Some(OpenLibraryContributor {
+            name: name.to_string(),
+            role,
+        })
+    }
 }
 
-pub fn build_work_editions_url(work_id: &str) -> String {
-    format!("https://openlibrary.org/works/{work_id}/editions.json?limit=1")
+fn collect_contributors(response: &OpenLibraryEditionResponse) -> Vec<OpenLibraryContributor> {
+    let structured = response.contributors.iter().filter_map(|contributor| {
+        let name = contributor.name.as_deref()?.trim();
+        if name.is_empty() {
+            return None;
+        }
+        Some(OpenLibraryContributor {
+            name: name.to_string(),
+            role: normalize_contributor_role(contributor.role.as_deref().unwrap_or_default()),
+        })
+    });
+    let parsed = response
+        .contributions
+        .iter()
+        .filter_map(|value| parse_contribution(value));
+
+    let mut contributors = Vec::new();
+    for contributor in structured.chain(parsed) {
+        if !contributors.contains(&contributor) {
+            contributors.push(contributor);
+        }
+    }
+    contributors
 }
 
-pub fn build_cover_url_from_id(cover_id: u64) -> String {
-    format!("https://covers.openlibrary.org/b/id/{cover_id}-L.jpg")
+fn leading_number(value: &str) -> Option<f64> {
+    let trimmed = value.trim_start();
+    let end = trimmed
+        .char_indices()
+        .find(|(_, c)| !c.is_ascii_digit() && *c != '.')
+        .map(|(index, _)| index)
+        .unwrap_or(trimmed.len());
+    trimmed[..end].trim_end_matches('.').parse().ok()
 }
 
-pub fn build_cover_url_from_olid(olid: &str) -> String {
-    format!("https://covers.openlibrary.org/b/olid/{olid}-L.jpg")
+const VOLUME_MARKERS: &[&str] = &[
+    "book ", "bk. ", "bk ", "volume ", "vol. ", "vol ", "v. ", "no. ", "part ", "tome ", "#",
+];
+
+/// Finds a volume number in a title or series string ("Book 3", "Vol. 2", "#4").
+pub fn detect_volume_number(value: &str) -> Option<f64> {
+    let lower = value.to_ascii_lowercase();
+    VOLUME_MARKERS.iter().find_map(|marker| {
+        lower
+            .match_indices(marker)
+            .filter(|(index, _)| {
+                *index == 0
+                    || marker.starts_with('#')
+                    || !lower.as_bytes()[index - 1].is_ascii_alphanumeric()
+            })
+            .find_map(|(index, _)| leading_number(&lower[index + marker.len()..]))
+    })
 }
 
-pub fn book_record_from_search_doc(doc: &OpenLibrarySearchDoc) -> Option<OpenLibraryBookRecord> {
-    let title = doc.title.trim();
-    if title.is_empty() {
+/// Parses an edition `series` entry such as "Harry Potter ; 1", "Discworld #3"
+/// or "The Chronicles of Narnia, book 2" into a name and optional position.
+pub fn parse_series(value: &str) -> Option<OpenLibrarySeries> {
+    let trimmed = value
+        .trim()
+        .trim_start_matches('(')
+        .trim_end_matches(')')
+        .trim();
+    if trimmed.is_empty() {
         return None;
     }
 
-    let edition_id = doc
-        .edition_key
-        .first()
-        .and_then(|value| normalize_openlibrary_id(value, "books"));
+    let (name, position) = if let Some((name, rest)) = trimmed.split_once(';') {
+        (
+            name,
+            leading_number(rest).or_else(|| detect_volume_number(rest)),
+        )
+    } else if let Some((name, rest)) = trimmed.rsplit_once('#') {
+        (name, leading_number(rest))
+    } else if let Some((name, rest)) = trimmed
+        .rsplit_once(',')
+        .filter(|(_, rest)| detect_volume_number(rest).is_some())
+    {
+        (name, detect_volume_number(rest))
+    } else {
+        (trimmed, None)
+    };
 
-    let work_id = normalize_openlibrary_id(&doc.key, "works");
+    let name = name.trim().trim_end_matches(',').trim();
+    if name.is_empty() {
+        None
+    } else {
+        Some(OpenLibrarySeries {
+            name: name.to_string(),
+            position,
+        })
+    }
+}
 
-    Some(OpenLibraryBookRecord {
-        title: title.to_string(),
-        edition_id,
-        work_id,
-        isbn13: first_isbn13(&doc.isbn),
-        cover_ids: doc
-            .cover_i
-            .and_then(positive_cover_id)
-            .into_iter()
-            .collect(),
-        cover_id: doc.cover_i.and_then(positive_cover_id),
-        publish_year: doc.first_publish_year,
-        description: None,
-        pages: doc.number_of_pages_median.and_then(positive_u32),
+/// Series of a work: structured entries win over catalogue strings. Structured entries
+/// without a name (a bare `/series/` key) are skipped, as resolving them takes a request.
+pub fn work_series(entries: &[OpenLibraryWorkSeries]) -> Option<OpenLibrarySeries> {
+    let structured = entries.iter().find_map(|entry| {
+        let OpenLibraryWorkSeries::Structured {
+            name,
+            series,
+            position,
+        } = entry
+        else {
+            return None;
+        };
+        let name = non_blank(
+            name.as_deref()
+                .or_else(|| series.as_ref().and_then(|series| series.name.as_deref()))
+                .map(str::trim),
+        )?;
+        let position = match position {
+            Some(serde_json::Value::Number(number)) => number.as_f64(),
+            Some(serde_json::Value::String(text)) => {
+                leading_number(text).or_else(|| detect_volume_number(text))
+            }
+            _ => None,
+        };
+        Some(OpenLibrarySeries { name, position })
+    });
+
+    structured.or_else(|| {
+        entries.iter().find_map(|entry| match entry {
+            OpenLibraryWorkSeries::Text(text) => parse_series(text),
+            OpenLibraryWorkSeries::Structured { .. } => None,
+        })
+    })
+}
+
+fn toc_text(value: Option<&serde_json::Value>) -> Option<String> {
+    let text = match value? {
+        serde_json::Value::String(text) => text.trim().to_string(),
+        serde_json::Value::Number(number) => number.to_string(),
+        _ => return None,
+    };
+    if text.is_empty() {
+        None
+    } else {
+        Some(text)
+    }
+}
+
+/// Keeps table of contents entries that carry a title; anything else is skipped.
+pub fn toc_entries_from_values(values: &[serde_json::Value]) -> Vec<OpenLibraryTocEntry> {
+    values
+        .iter()
+        .filter_map(|value| {
+            let entry = value.as_object()?;
+            Some(OpenLibraryTocEntry {
+                title: toc_text(entry.get("title"))?,
+                pagenum: toc_text(entry.get("pagenum")),
+                label: toc_text(entry.get("label")),
+            })
+        })
+        .collect()
+}
+
+pub fn encode_query_component(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for b in value.as_bytes() {
+        match *b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(*b as char)
+            }
+            b' ' => encoded.push_str("%20"),
+            _ => encoded.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    encoded
+}
+
+pub fn build_search_url(search: &str, limit: u32, page: u32) -> String {
+    let mut url = format!(
+        "https://openlibrary.org/search.json?q={query}&limit={limit}",
+        query = encode_query_component(search)
+    );
+    if page > 1 {
+        url.push_str(&format!("&page={page}"));
+    }
+    url
+}
+
+/// Fielded `title` + `author` search, used when the caller passes an `author` hint.
+pub fn build_fielded_search_url(title: &str, author: &str, limit: u32, page: u32) -> String {
+    let mut url = format!(
+        "https://openlibrary.org/search.json?title={title}&author={author}&limit={limit}",
+        title = encode_query_component(title),
+        author = encode_query_component(author)
+    );
+    if page > 1 {
+        url.push_str(&format!("&page={page}"));
+    }
+    url
+}
+
+pub fn build_search_inside_url(search: &str) -> String {
+    format!(
+        "https://openlibrary.org/search/inside.json?q={query}",
+        query = encode_query_component(search)
+    )
+}
+
+pub fn build_author_url(author_id: &str) -> String {
+    format!("https://openlibrary.org/authors/{author_id}.json")
+}
+
+/// Page size of the recent changes feed, which accepts up to 1000.
+pub const RECENT_CHANGES_LIMIT: u32 = 100;
+
+pub fn build_recent_changes_url(limit: u32, offset: u64) -> String {
+    let mut url = format!("https://openlibrary.org/recentchanges.json?limit={limit}");
+    if offset > 0 {
+        url.push_str(&format!("&offset={offset}"));
+    }
+    url
+}
+
+/// Default and maximum `limit` of `lookup_subject`.
+pub const SUBJECT_WORKS_LIMIT: u32 = 12;
+pub const MAX_SUBJECT_WORKS_LIMIT: u32 = 100;
+
+/// Works of a subject, with the `ebooks` and `published_in` (`1500-1600`) facets.
+pub fn build_subject_works_url(
+    subject_key: &str,
+    limit: u32,
+    ebooks: bool,
+    published_in: Option<&str>,
+) -> String {
+    let mut url = format!(
+        "https://openlibrary.org/subjects/{slug}.json?limit={limit}",
+        slug = encode_query_component(subject_key)
+    );
+    if ebooks {
+        url.push_str("&ebooks=true");
+    }
+    if let Some(published_in) = published_in {
+        url.push_str(&format!(
+            "&published_in={}",
+            encode_query_component(published_in)
+        ));
+    }
+    url
+}
+
+pub fn build_subject_url(subject_key: &str) -> String {
+    format!(
+        "https://openlibrary.org/subjects/{slug}.json?limit=0",
+        slug = encode_query_component(subject_key)
+    )
+}
+
+pub fn build_wikidata_search_url(qid: &str) -> String {
+    format!(
+        "https://openlibrary.org/search.json?q=id_wikidata:{qid}&limit=1",
+        qid = encode_query_component(qid)
+    )
+}
+
+pub fn build_goodreads_search_url(goodreads_id: &str) -> String {
+    format!(
+        "https://openlibrary.org/search.json?q=id_goodreads:{goodreads_id}&limit=1",
+        goodreads_id = encode_query_component(goodreads_id)
+    )
+}
+
+/// Offset based variant of `build_fielded_search_url`, used by cursor pagination.
+pub fn build_fielded_search_url_at_offset(
+    title: &str,
+    author: &str,
+    limit: u32,
+    offset: u64,
+) -> String {
+    let mut url = format!(
+        "https://openlibrary.org/search.json?title={title}&author={author}&limit={limit}",
+        title = encode_query_component(title),
+        author = encode_query_component(author)
+    );
+    if offset > 0 {
+        url.push_str(&format!("&offset={offset}"));
+    }
+    url
+}
+
+/// Offset based variant of `build_search_url`, used by cursor pagination.
+pub fn build_search_url_at_offset(search: &str, limit: u32, offset: u64) -> String {
+    let mut url = format!(
+        "https://openlibrary.org/search.json?q={query}&limit={limit}",
+        query = encode_query_component(search)
+    );
+    if offset > 0 {
+        url.push_str(&format!("&offset={offset}"));
+    }
+    url
+}
+
+/// Public scans open directly in the archive.org reader.
+pub fn build_read_url(ocaid: &str) -> String {
+    format!("https://archive.org/details/{ocaid}")
+}
+
+/// Lending goes through Open Library, which checks the loan out before opening the book.
+pub fn build_borrow_url(ocaid: &str) -> String {
+    format!("https://openlibrary.org/borrow/ia/{ocaid}")
+}
+
+/// Books data endpoint for one ISBN; the response is keyed by `ISBN:{isbn13}`.
+pub fn build_books_data_url(isbn13: &str) -> String {
+    format!("https://openlibrary.org/api/books?bibkeys=ISBN:{isbn13}&jscmd=data&format=json")
+}
+
+pub fn build_isbn_url(isbn13: &str) -> String {
+    format!("https://openlibrary.org/isbn/{isbn13}.json")
+}
+
+pub const OPENLIBRARY_BASE_URL: &str = "https://openlibrary.org";
+
+pub fn build_edition_url(edition_id: &str) -> String {
+    format!("https://openlibrary.org/books/{edition_id}.json")
+}
+
+pub fn build_work_url(work_id: &str) -> String {
+    format!("https://openlibrary.org/works/{work_id}.json")
+}
+
+/// Human-facing work page, as opposed to the `.json` API document.
+pub fn build_work_page_url(work_id: &str) -> String {
+    format!("https://openlibrary.org/works/{work_id}")
+}
+
+/// Editions considered when an `edition_preference` other than `first`, a language chain,
+/// a preferred binding or a preferred country is set.
+pub const EDITION_CANDIDATE_LIMIT: u32 = 50;
+
+pub fn build_work_editions_url(work_id: &str, limit: u32) -> String {
+    format!("https://openlibrary.org/works/{work_id}/editions.json?limit={limit}")
+}
+
+pub fn build_work_ratings_url(work_id: &str) -> String {
+    format!("https://openlibrary.org/works/{work_id}/ratings.json")
+}
+
+pub fn build_edition_ratings_url(edition_id: &str) -> String {
+    format!("https://openlibrary.org/books/{edition_id}/ratings.json")
+}
+
+/// Ratings without any vote are treated as absent.
+pub fn rating_from_response(response: &OpenLibraryRatingsResponse) -> Option<OpenLibraryRating> {
+    let summary = response.summary.as_ref()?;
+    let count = summary.count.filter(|count| *count > 0)?;
+    Some(OpenLibraryRating {
+        average: summary.average?,
+        count,
+    })
+}
+
+pub fn build_sized_cover_url_from_id(cover_id: u64, size: CoverSize) -> String {
+    format!(
+        "https://covers.openlibrary.org/b/id/{cover_id}-{size}.jpg",
+        size = size.suffix()
+    )
+}
+
+pub fn build_author_photo_url(photo_id: u64) -> String {
+    format!("https://covers.openlibrary.org/a/id/{photo_id}-L.jpg")
+}
+
+pub fn build_cover_metadata_url(cover_id: u64) -> String {
+    format!("https://covers.openlibrary.org/b/id/{cover_id}.json")
+}
+
+pub fn build_cover_url_from_olid(olid: &str, size: CoverSize) -> String {
+    format!(
+        "https://covers.openlibrary.org/b/olid/{olid}-{size}.jpg",
+        size = size.suffix()
+    )
+}
+
+pub fn build_cover_url_from_lccn(lccn: &str, size: CoverSize) -> String {
+    format!(
+        "https://covers.openlibrary.org/b/lccn/{lccn}-{size}.jpg",
+        lccn = encode_query_component(lccn),
+        size = size.suffix()
+    )
+}
+
+pub fn build_cover_url_from_oclc(oclc: &str, size: CoverSize) -> String {
+    format!(
+        "https://covers.openlibrary.org/b/oclc/{oclc}-{size}.jpg",
+        oclc = encode_query_component(oclc),
+        size = size.suffix()
+    )
+}
+
+fn first_identifier(values: &[String]) -> Option<String> {
+    values
+        .iter()
+        .map(|value| value.trim())
+        .find(|value| !value.is_empty())
+        .map(|value| value.to_string())
+}
+
+pub fn book_record_from_search_doc(doc: &OpenLibrarySearchDoc) -> Option<OpenLibraryBookRecord> {
+    let title = doc.title.trim();
+    if title.is_empty() {
+        return None;
+    }
+
+    let edition_id = doc
+        .edition_key
+        .first()
+        .and_then(|value| normalize_openlibrary_id(value, "books"));
+
+    let work_id = normalize_openlibrary_id(&doc.key, "works");
+    // `ebook_access` is about one scan, named by `lending_identifier_s`; any `ia` entry
+    // may be another scan, so a readable or borrowable access needs that identifier.
+    let lending_identifier = non_blank(doc.lending_identifier_s.as_deref().map(str::trim));
+    let ebook_access = doc
+        .ebook_access
+        .as_deref()
+        .and_then(EbookAccess::parse)
+        .filter(|access| {
+            lending_identifier.is_some()
+                || !matches!(access, EbookAccess::Public | EbookAccess::Borrowable)
+        });
+
+    Some(OpenLibraryBookRecord {
+        title: title.to_string(),
+        subtitle: non_blank(doc.subtitle.as_deref().map(str::trim)),
+        edition_id,
+        work_ids: work_id.iter().cloned().collect(),
+        work_id,
+        isbn13: first_isbn13(&doc.isbn),
+        cover_ids: doc
+            .cover_i
+            .and_then(positive_cover_id)
+            .into_iter()
+            .collect(),
+        cover_id: doc.cover_i.and_then(positive_cover_id),
+        publish_year: doc.first_publish_year,
+        historical_year: None,
+        publish_date_raw: None,
+        first_publish_year: doc.first_publish_year.map(i32::from),
+        description: None,
+        pages: doc.number_of_pages_median.and_then(positive_u32),
+        pagination: None,
         language: doc.language.first().cloned(),
         authors: doc.author_name.clone(),
         author_keys: doc.author_key.clone(),
         subjects: doc.subject.clone(),
+        subject_keys: doc.subject_key.clone(),
+        subject_work_counts: HashMap::new(),
         publishers: doc.publisher.clone(),
+        // Search doc places are a facet list, not aligned with `publisher`.
+        publish_places: vec![],
+        table_of_contents: vec![],
+        other_titles: vec![],
+        contributors: vec![],
+        confidence: None,
+        edition_rating: None,
+        work_rating: None,
+        series: None,
+        lccn: first_identifier(&doc.lccn),
+        oclc: first_identifier(&doc.oclc),
+        goodreads_id: first_identifier(&doc.id_goodreads),
+        edition_count: doc.edition_count,
+        ocaid: lending_identifier
+            .clone()
+            .or_else(|| first_identifier(&doc.ia)),
+        ebook_access,
+        last_modified: None,
+        revision: None,
+        match_snippet: None,
+        author_details: vec![],
+        resolved_by: None,
+        debug_sources: None,
+        cover_source: None,
+        no_cover: false,
     })
 }
 
@@ -294,153 +1420,1440 @@ pub fn book_record_from_edition_response(
         .as_ref()
         .and_then(OpenLibraryDescription::as_text);
 
-    let publish_year = response
-        .publish_date
-        .as_deref()
-        .and_then(extract_year_from_text);
+    let (publish_year, historical_year) = publish_years(response.publish_date.as_deref());
 
     let cover_ids = extract_cover_ids(&response.covers);
 
+    let mut work_ids: Vec<String> = Vec::new();
+    for work_id in response
+        .works
+        .iter()
+        .filter_map(|work| normalize_openlibrary_id(&work.key, "works"))
+    {
+        if !work_ids.contains(&work_id) {
+            work_ids.push(work_id);
+        }
+    }
+
     OpenLibraryBookRecord {
         title: response.title.trim().to_string(),
+        subtitle: non_blank(response.subtitle.as_deref().map(str::trim)),
         edition_id: normalize_openlibrary_id(&response.key, "books"),
-        work_id: response
-            .works
-            .first()
-            .and_then(|work| normalize_openlibrary_id(&work.key, "works")),
+        work_id: work_ids.first().cloned(),
+        work_ids,
         isbn13: first_isbn13(&response.isbn_13),
         cover_id: cover_ids.first().copied(),
         cover_ids,
         publish_year,
+        historical_year,
+        publish_date_raw: non_blank(response.publish_date.as_deref()),
+        first_publish_year: None,
         description,
         pages: response.number_of_pages.and_then(positive_u32),
+        pagination: non_blank(response.pagination.as_deref().map(str::trim)),
         language: response
             .languages
-            .first()
-            .and_then(|language| language_from_key(&language.key)),
+            .iter()
+            .find_map(OpenLibraryLanguage::code),
         authors: vec![],
         author_keys: vec![],
         subjects: vec![],
+        subject_keys: vec![],
+        subject_work_counts: HashMap::new(),
         publishers: response.publishers.clone(),
+        publish_places: response.publish_places.clone(),
+        table_of_contents: toc_entries_from_values(&response.table_of_contents),
+        other_titles: collect_other_titles(&response.title, &response.other_titles),
+        contributors: collect_contributors(response),
+        confidence: None,
+        edition_rating: None,
+        work_rating: None,
+        series: response.series.iter().find_map(|value| parse_series(value)),
+        lccn: first_identifier(&response.lccn),
+        oclc: first_identifier(&response.oclc_numbers),
+        goodreads_id: None,
+        last_modified: response
+            .last_modified
+            .as_ref()
+            .and_then(OpenLibraryDescription::as_text),
+        revision: response.revision,
+        match_snippet: None,
+        author_details: vec![],
+        resolved_by: None,
+        debug_sources: None,
+        cover_source: None,
+        edition_count: None,
+        ocaid: non_blank(response.ocaid.as_deref()),
+        ebook_access: None,
+        no_cover: has_no_cover_marker(&response.covers),
     }
 }
 
 pub fn book_record_from_work_response(response: &OpenLibraryWorkResponse) -> OpenLibraryBookRecord {
     let cover_ids = extract_cover_ids(&response.covers);
+    let work_id = normalize_openlibrary_id(&response.key, "works");
+    let (publish_year, historical_year) = publish_years(response.first_publish_date.as_deref());
 
     OpenLibraryBookRecord {
         title: response.title.trim().to_string(),
+        subtitle: non_blank(response.subtitle.as_deref().map(str::trim)),
         edition_id: None,
-        work_id: normalize_openlibrary_id(&response.key, "works"),
+        work_ids: work_id.iter().cloned().collect(),
+        work_id,
         isbn13: None,
         cover_id: cover_ids.first().copied(),
         cover_ids,
-        publish_year: response
-            .first_publish_date
-            .as_deref()
-            .and_then(extract_year_from_text),
+        publish_year,
+        historical_year,
+        publish_date_raw: non_blank(response.first_publish_date.as_deref()),
+        first_publish_year: publish_year.map(i32::from).or(historical_year),
         description: response
             .description
             .as_ref()
             .and_then(OpenLibraryDescription::as_text),
         pages: None,
+        pagination: None,
         language: None,
         authors: vec![],
         author_keys: vec![],
         subjects: response.subjects.clone(),
+        subject_keys: vec![],
+        subject_work_counts: HashMap::new(),
         publishers: vec![],
+        publish_places: vec![],
+        table_of_contents: vec![],
+        other_titles: collect_other_titles(&response.title, &response.other_titles),
+        contributors: vec![],
+        confidence: None,
+        edition_rating: None,
+        work_rating: None,
+        series: work_series(&response.series),
+        lccn: None,
+        oclc: None,
+        goodreads_id: None,
+        last_modified: response
+            .last_modified
+            .as_ref()
+            .and_then(OpenLibraryDescription::as_text),
+        revision: response.revision,
+        match_snippet: None,
+        author_details: vec![],
+        resolved_by: None,
+        debug_sources: None,
+        cover_source: None,
+        edition_count: None,
+        ocaid: None,
+        ebook_access: None,
+        no_cover: has_no_cover_marker(&response.covers),
+    }
+}
+
+/// Decodes the HTML entities search-inside leaves in highlights (`&amp;`, `&#39;`, ...).
+pub fn decode_html_entities(value: &str) -> String {
+    let mut decoded = String::with_capacity(value.len());
+    let mut rest = value;
+
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let entity = rest[1..]
+            .find(';')
+            .filter(|end| *end <= 10)
+            .map(|end| &rest[1..end + 1]);
+        let replacement = entity.and_then(|entity| match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some(' '),
+            _ => entity
+                .strip_prefix("#x")
+                .or_else(|| entity.strip_prefix("#X"))
+                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                .or_else(|| entity.strip_prefix('#').and_then(|dec| dec.parse().ok()))
+                .and_then(char::from_u32),
+        });
+
+        match (entity, replacement) {
+            (Some(entity), Some(character)) => {
+                decoded.push(character);
+                rest = &rest[entity.len() + 2..];
+            }
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    decoded.push_str(rest);
+    decoded
+}
+
+/// Turns a search-inside highlight into plain text: match markers (`{{{` / `}}}`) are
+/// dropped, entities decoded and whitespace collapsed.
+pub fn clean_match_snippet(highlight: &str) -> Option<String> {
+    let unmarked = highlight.replace("{{{", "").replace("}}}", "");
+    let decoded = decode_html_entities(&unmarked);
+    let snippet = decoded.split_whitespace().collect::<Vec<_>>().join(" ");
+    if snippet.is_empty() {
+        None
+    } else {
+        Some(snippet)
+    }
+}
+
+/// Subject works carry a subset of the search doc fields; map them through one.
+pub fn book_record_from_subject_work(
+    work: &OpenLibrarySubjectWork,
+) -> Option<OpenLibraryBookRecord> {
+    let doc = OpenLibrarySearchDoc {
+        key: work.key.clone(),
+        title: work.title.clone(),
+        edition_key: work.cover_edition_key.iter().cloned().collect(),
+        cover_i: work.cover_id,
+        first_publish_year: work.first_publish_year,
+        author_name: work
+            .authors
+            .iter()
+            .map(|author| author.name.clone())
+            .collect(),
+        author_key: work
+            .authors
+            .iter()
+            .filter_map(|author| normalize_openlibrary_id(&author.key, "authors"))
+            .collect(),
+        subject: work.subject.clone(),
+        edition_count: work.edition_count,
+        ia: work.ia.iter().cloned().collect(),
+        ..Default::default()
+    };
+    book_record_from_search_doc(&doc)
+}
+
+pub fn book_record_from_search_inside_hit(
+    hit: &OpenLibrarySearchInsideHit,
+) -> Option<OpenLibraryBookRecord> {
+    let mut record = book_record_from_edition_response(hit.edition.as_ref()?);
+    if record.title.is_empty() {
+        return None;
+    }
+    record.match_snippet = hit
+        .highlight
+        .text
+        .iter()
+        .find_map(|text| clean_match_snippet(text));
+    Some(record)
+}
+
+pub fn author_details_from_response(
+    author_key: &str,
+    response: &OpenLibraryAuthorResponse,
+) -> OpenLibraryAuthorDetails {
+    let non_empty = |value: &Option<String>| {
+        value
+            .as_deref()
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(str::to_string)
+    };
+
+    OpenLibraryAuthorDetails {
+        key: author_key.to_string(),
+        birth_date: non_empty(&response.birth_date),
+        death_date: non_empty(&response.death_date),
+        alternate_names: response
+            .alternate_names
+            .iter()
+            .map(|name| name.trim())
+            .filter(|name| !name.is_empty())
+            .map(str::to_string)
+            .collect(),
+        photo_id: response
+            .photos
+            .iter()
+            .find(|photo_id| **photo_id > 0)
+            .map(|photo_id| *photo_id as u64),
+        bio: response.bio.as_ref().and_then(|bio| bio.as_text()),
+        birth_year: signed_year(response.birth_date.as_deref()),
+        death_year: signed_year(response.death_date.as_deref()),
+    }
+}
+
+fn signed_year(value: Option<&str>) -> Option<i32> {
+    let value = value?;
+    extract_historical_year(value).or_else(|| extract_year_from_text(value).map(i32::from))
+}
+
+/// Author id a `/type/redirect` record points at, `None` for a regular author.
+pub fn author_redirect_target(response: &OpenLibraryAuthorResponse) -> Option<String> {
+    let is_redirect = response
+        .kind
+        .as_ref()
+        .is_some_and(|kind| kind.key == "/type/redirect");
+    if !is_redirect {
+        return None;
+    }
+    normalize_openlibrary_id(response.location.as_deref()?, "authors")
+}
+
+/// Fills work-level data an edition usually lacks (subjects, description, publish year,
+/// series) without overriding anything the edition already provides. The work's first
+/// publish year is always kept, as `first_publish_year`.
+pub fn enrich_edition_with_work(
+    mut edition: OpenLibraryBookRecord,
+    work: &OpenLibraryWorkResponse,
+) -> OpenLibraryBookRecord {
+    let work = book_record_from_work_response(work);
+
+    if edition.subjects.is_empty() {
+        edition.subjects = work.subjects;
+    }
+    if edition.description.is_none() {
+        edition.description = work.description;
+    }
+    if edition.publish_year.is_none() && edition.historical_year.is_none() {
+        edition.publish_year = work.publish_year;
+        edition.historical_year = work.historical_year;
+    }
+    if edition.publish_date_raw.is_none() {
+        edition.publish_date_raw = work.publish_date_raw;
+    }
+    if edition.series.is_none() {
+        edition.series = work.series;
+    }
+    edition.first_publish_year = work.first_publish_year.or(edition.first_publish_year);
+    for work_id in work.work_ids {
+        if !edition.work_ids.contains(&work_id) {
+            edition.work_ids.push(work_id);
+        }
+    }
+    if edition.work_id.is_none() {
+        edition.work_id = edition.work_ids.first().cloned();
+    }
+
+    edition
+}
+
+/// Whether the edition literally lists `isbn` (ISBN-10 or 13) among its ISBNs.
+pub fn edition_has_isbn(response: &OpenLibraryEditionResponse, isbn: &str) -> bool {
+    let Some(query) = canonical_isbn13(isbn) else {
+        return false;
+    };
+    response
+        .isbn_13
+        .iter()
+        .chain(response.isbn_10.iter())
+        .filter_map(|value| canonical_isbn13(value))
+        .any(|value| value == query)
+}
+
+/// Bare author ids (`OL26320A`) of a work, in the work's order.
+pub fn work_author_ids(work: &OpenLibraryWorkResponse) -> Vec<String> {
+    let mut author_ids: Vec<String> = Vec::new();
+    for author_id in work
+        .authors
+        .iter()
+        .filter_map(|entry| entry.author.as_ref())
+        .filter_map(|author| normalize_openlibrary_id(&author.key, "authors"))
+    {
+        if !author_ids.contains(&author_id) {
+            author_ids.push(author_id);
+        }
+    }
+    author_ids
+}
+
+/// Completeness score used to rank editions: identifiers and covers weigh most.
+pub fn edition_score(record: &OpenLibraryBookRecord) -> u32 {
+    let mut score = 0;
+    if record.isbn13.is_some() {
+        score += 4;
+    }
+    if !record.cover_ids.is_empty() {
+        score += 4;
+    }
+    if record.pages.is_some() {
+        score += 2;
+    }
+    if !record.publishers.is_empty() {
+        score += 1;
+    }
+    if record.publish_year.is_some() {
+        score += 1;
+    }
+    if record.language.is_some() {
+        score += 1;
+    }
+    score
+}
+
+/// Highest scoring edition; ties go to a local edition (the `bool`), then keep
+/// OpenLibrary's order.
+fn best_record(
+    records: impl Iterator<Item = (OpenLibraryBookRecord, bool)>,
+) -> Option<OpenLibraryBookRecord> {
+    let rank = |(record, local): &(OpenLibraryBookRecord, bool)| (edition_score(record), *local);
+    records
+        .fold(
+            None,
+            |best: Option<(OpenLibraryBookRecord, bool)>, candidate| match best {
+                Some(best) if rank(&best) >= rank(&candidate) => Some(best),
+                _ => Some(candidate),
+            },
+        )
+        .map(|(record, _)| record)
+}
+
+/// Swaps an edition without ISBN-13 for the first of the narrowed `candidates` listing
+/// one (a local one first), so `Best` work lookups still get retail links. The whole
+/// edition is swapped, keeping its id, covers and ISBN consistent; with no candidate
+/// listing an ISBN-13 the pick is kept.
+fn with_isbn13_edition(
+    edition: Option<OpenLibraryBookRecord>,
+    candidates: &[(OpenLibraryBookRecord, bool)],
+) -> Option<OpenLibraryBookRecord> {
+    if edition
+        .as_ref()
+        .is_some_and(|edition| edition.isbn13.is_some())
+    {
+        return edition;
+    }
+    let mut with_isbn13 = candidates
+        .iter()
+        .filter(|(candidate, _)| candidate.isbn13.is_some());
+    with_isbn13
+        .clone()
+        .find(|(_, local)| *local)
+        .or_else(|| with_isbn13.next())
+        .map(|(candidate, _)| candidate.clone())
+        .or(edition)
+}
+
+fn narrow_editions<'a>(
+    entries: &[&'a OpenLibraryEditionResponse],
+    predicate: impl Fn(&OpenLibraryEditionResponse) -> bool,
+) -> Vec<&'a OpenLibraryEditionResponse> {
+    entries
+        .iter()
+        .copied()
+        .filter(|edition| predicate(edition))
+        .collect()
+}
+
+/// Picks the edition merged into a work lookup, see `EditionPreference`.
+///
+/// The pool is narrowed first by `language_chain` (MARC language codes, e.g. `fre`,
+/// `eng`): the editions in the first language any edition is in. Then, with a
+/// `preferred_binding`, to the editions in that `physical_format`. A step matching
+/// nothing keeps the pool as is, so some edition is always picked.
+///
+/// `preferred_country` (MARC code, e.g. `enk`) is a tie-breaker, not a filter: among
+/// equally scored editions (every edition, for `First`) one published there wins.
+pub fn select_edition_from_work_editions(
+    response: &OpenLibraryWorkEditionsResponse,
+    preference: EditionPreference,
+    first_publish_year: Option<u16>,
+    language_chain: &[String],
+    preferred_country: Option<&str>,
+    preferred_binding: Option<Binding>,
+) -> Option<OpenLibraryBookRecord> {
+    let mut entries: Vec<&OpenLibraryEditionResponse> = response.entries.iter().collect();
+    for language in language_chain {
+        let matching = narrow_editions(&entries, |edition| {
+            edition.languages.iter().any(|entry| {
+                entry
+                    .code()
+                    .is_some_and(|code| code.eq_ignore_ascii_case(language))
+            })
+        });
+        if !matching.is_empty() {
+            entries = matching;
+            break;
+        }
+    }
+    if let Some(binding) = preferred_binding {
+        let bound = narrow_editions(&entries, |edition| {
+            edition
+                .physical_format
+                .as_deref()
+                .is_some_and(|format| binding.matches(format))
+        });
+        if !bound.is_empty() {
+            entries = bound;
+        }
+    }
+    let is_local = |edition: &OpenLibraryEditionResponse| {
+        preferred_country.is_some_and(|country| {
+            edition
+                .publish_country
+                .as_deref()
+                .is_some_and(|published| published.trim().eq_ignore_ascii_case(country))
+        })
+    };
+    let records = entries.iter().map(|edition| {
+        (
+            book_record_from_edition_response(edition),
+            is_local(edition),
+        )
+    });
+
+    match preference {
+        EditionPreference::First => entries
+            .iter()
+            .find(|edition| is_local(edition))
+            .or(entries.first())
+            .map(|edition| book_record_from_edition_response(edition)),
+        EditionPreference::Best => {
+            let records: Vec<(OpenLibraryBookRecord, bool)> = records.collect();
+            with_isbn13_edition(best_record(records.iter().cloned()), &records)
+        }
+        EditionPreference::Original => {
+            let records: Vec<(OpenLibraryBookRecord, bool)> = records.collect();
+            let original = first_publish_year.and_then(|year| {
+                best_record(
+                    records
+                        .iter()
+                        .filter(|(record, _)| record.publish_year == Some(year))
+                        .cloned(),
+                )
+            });
+            original.or_else(|| best_record(records.into_iter()))
+        }
+    }
+}
+
+/// Total editions reported by the work editions endpoint; negative counts are dropped.
+pub fn work_edition_total(response: &OpenLibraryWorkEditionsResponse) -> Option<u64> {
+    response.size.and_then(|size| u64::try_from(size).ok())
+}
+
+/// Appends every edition's covers to a work record, deduped and capped at `max_covers`.
+pub fn merge_edition_covers(
+    record: &mut OpenLibraryBookRecord,
+    response: &OpenLibraryWorkEditionsResponse,
+    max_covers: usize,
+) {
+    let edition_covers = response
+        .entries
+        .iter()
+        .flat_map(|edition| extract_cover_ids(&edition.covers));
+    for cover_id in edition_covers {
+        if record.cover_ids.len() >= max_covers {
+            break;
+        }
+        if !record.cover_ids.contains(&cover_id) {
+            record.cover_ids.push(cover_id);
+        }
+    }
+    if record.cover_id.is_none() {
+        record.cover_id = record.cover_ids.first().copied();
+    }
+}
+
+/// A source's covers with its primary first.
+fn primary_first_covers(record: &OpenLibraryBookRecord) -> impl Iterator<Item = u64> + '_ {
+    record
+        .cover_id
+        .into_iter()
+        .chain(record.cover_ids.iter().copied())
+}
+
+/// One record per edition of a work, each merged with the work record. A work without
+/// editions yields the work record alone.
+pub fn records_from_work_editions(
+    work: &OpenLibraryBookRecord,
+    response: &OpenLibraryWorkEditionsResponse,
+    cover_preference: CoverPreference,
+) -> Vec<OpenLibraryBookRecord> {
+    if response.entries.is_empty() {
+        return vec![work.clone()];
+    }
+    response
+        .entries
+        .iter()
+        .map(|edition| {
+            merge_work_with_edition(
+                work.clone(),
+                Some(book_record_from_edition_response(edition)),
+                cover_preference,
+            )
+        })
+        .collect()
+}
+
+pub fn merge_work_with_edition(
+    work: OpenLibraryBookRecord,
+    edition: Option<OpenLibraryBookRecord>,
+    cover_preference: CoverPreference,
+) -> OpenLibraryBookRecord {
+    let Some(edition) = edition else {
+        return work;
+    };
+
+    let (preferred, other) = match cover_preference {
+        CoverPreference::Work => (&work, &edition),
+        CoverPreference::Edition => (&edition, &work),
+    };
+    let mut cover_ids: Vec<u64> = Vec::new();
+    for cover_id in primary_first_covers(preferred).chain(primary_first_covers(other)) {
+        if !cover_ids.contains(&cover_id) {
+            cover_ids.push(cover_id);
+        }
+    }
+
+    let title = if work.title.is_empty() {
+        edition.title
+    } else {
+        work.title
+    };
+    let other_titles = collect_other_titles(
+        &title,
+        work.other_titles.iter().chain(edition.other_titles.iter()),
+    );
+
+    let mut work_ids = work.work_ids.clone();
+    for work_id in edition.work_ids.iter() {
+        if !work_ids.contains(work_id) {
+            work_ids.push(work_id.clone());
+        }
+    }
+
+    OpenLibraryBookRecord {
+        title,
+        subtitle: edition.subtitle.or(work.subtitle),
+        edition_id: edition.edition_id.or(work.edition_id),
+        work_id: work.work_id.or(edition.work_id),
+        work_ids,
+        isbn13: edition.isbn13.or(work.isbn13),
+        cover_id: cover_ids.first().copied(),
+        cover_ids,
+        publish_year: edition.publish_year.or(work.publish_year),
+        historical_year: if edition.publish_year.is_some() {
+            edition.historical_year
+        } else {
+            edition.historical_year.or(work.historical_year)
+        },
+        publish_date_raw: edition.publish_date_raw.or(work.publish_date_raw),
+        first_publish_year: work.first_publish_year.or(edition.first_publish_year),
+        description: work.description.or(edition.description),
+        pages: edition.pages.or(work.pages),
+        pagination: edition.pagination.or(work.pagination),
+        language: edition.language.or(work.language),
+        authors: if work.authors.is_empty() {
+            edition.authors
+        } else {
+            work.authors
+        },
+        author_keys: if work.author_keys.is_empty() {
+            edition.author_keys
+        } else {
+            work.author_keys
+        },
+        subject_keys: if work.subjects.is_empty() {
+            edition.subject_keys
+        } else {
+            work.subject_keys
+        },
+        subject_work_counts: if work.subject_work_counts.is_empty() {
+            edition.subject_work_counts
+        } else {
+            work.subject_work_counts
+        },
+        subjects: if work.subjects.is_empty() {
+            edition.subjects
+        } else {
+            work.subjects
+        },
+        // Places follow the publishers' source so the pairing survives the merge.
+        publish_places: if edition.publishers.is_empty() {
+            work.publish_places
+        } else {
+            edition.publish_places
+        },
+        publishers: if edition.publishers.is_empty() {
+            work.publishers
+        } else {
+            edition.publishers
+        },
+        table_of_contents: if edition.table_of_contents.is_empty() {
+            work.table_of_contents
+        } else {
+            edition.table_of_contents
+        },
+        other_titles,
+        contributors: if edition.contributors.is_empty() {
+            work.contributors
+        } else {
+            edition.contributors
+        },
+        confidence: work.confidence.or(edition.confidence),
+        edition_rating: edition.edition_rating,
+        work_rating: work.work_rating.or(edition.work_rating),
+        series: work.series.or(edition.series),
+        lccn: edition.lccn.or(work.lccn),
+        oclc: edition.oclc.or(work.oclc),
+        goodreads_id: edition.goodreads_id.or(work.goodreads_id),
+        // The merged record is stale as soon as either document changes.
+        last_modified: work.last_modified.max(edition.last_modified),
+        revision: edition.revision.or(work.revision),
+        match_snippet: edition.match_snippet.or(work.match_snippet),
+        author_details: if work.author_details.is_empty() {
+            edition.author_details
+        } else {
+            work.author_details
+        },
+        resolved_by: work.resolved_by.or(edition.resolved_by),
+        no_cover: edition.no_cover || work.no_cover,
+        edition_count: work.edition_count.or(edition.edition_count),
+        ocaid: edition.ocaid.or(work.ocaid),
+        ebook_access: edition.ebook_access.or(work.ebook_access),
+        debug_sources: None,
+        cover_source: None,
+    }
+}
+
+fn positive_cover_id(value: i64) -> Option<u64> {
+    if value > 0 {
+        Some(value as u64)
+    } else {
+        None
+    }
+}
+
+fn non_blank(value: Option<&str>) -> Option<String> {
+    value
+        .filter(|value| !value.trim().is_empty())
+        .map(str::to_string)
+}
+
+/// `covers: [-1]` affirms there is no cover, unlike an empty or missing array.
+fn has_no_cover_marker(values: &[i64]) -> bool {
+    !values.is_empty() && values.iter().all(|value| *value == -1)
+}
+
+fn extract_cover_ids(values: &[i64]) -> Vec<u64> {
+    let mut cover_ids = Vec::new();
+    for value in values {
+        if let Some(cover_id) = positive_cover_id(*value) {
+            if !cover_ids.contains(&cover_id) {
+                cover_ids.push(cover_id);
+            }
+        }
+    }
+    cover_ids
+}
+
+fn positive_u32(value: i64) -> Option<u32> {
+    if value > 0 && value <= u32::MAX as i64 {
+        Some(value as u32)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_ids_from_paths() {
+        assert_eq!(
+            normalize_openlibrary_id("/works/OL45804W", "works"),
+            Some("OL45804W".to_string())
+        );
+        assert_eq!(
+            normalize_openlibrary_id("books/OL7353617M", "books"),
+            Some("OL7353617M".to_string())
+        );
+    }
+
+    #[test]
+    fn parses_recent_changes_into_work_and_edition_keys() {
+        let changes: Vec<OpenLibraryRecentChange> = serde_json::from_str(
+            r#"[
+                {
+                    "id": "183400154",
+                    "kind": "edit-book",
+                    "timestamp": "2024-05-02T10:15:30.123456",
+                    "comment": "Add cover",
+                    "changes": [
+                        {"key": "/books/OL7353617M", "revision": 12},
+                        {"key": "/works/OL45804W", "revision": 8},
+                        {"key": "/authors/OL26320A", "revision": 3}
+                    ],
+                    "author": {"key": "/people/example"}
+                },
+                {"kind": "merge-authors", "timestamp": "2024-05-02T10:14:00"}
+            ]"#,
+        )
+        .unwrap();
+
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].kind.as_deref(), Some("edit-book"));
+        assert_eq!(
+            changed_records(&changes[0]),
+            vec![
+                (
+                    ChangedRecordType::Edition,
+                    "OL7353617M".to_string(),
+                    Some(12)
+                ),
+                (ChangedRecordType::Work, "OL45804W".to_string(), Some(8)),
+            ]
+        );
+        assert!(changed_records(&changes[1]).is_empty());
+        assert_eq!(
+            build_recent_changes_url(100, 200),
+            "https://openlibrary.org/recentchanges.json?limit=100&offset=200"
+        );
+    }
+
+    #[test]
+    fn encode_query_component_encodes_spaces() {
+        assert_eq!(encode_query_component("The Hobbit"), "The%20Hobbit");
+    }
+
+    #[test]
+    fn search_url_only_adds_page_after_first() {
+        assert_eq!(
+            build_search_url("The Hobbit", 25, 1),
+            "https://openlibrary.org/search.json?q=The%20Hobbit&limit=25"
+        );
+        assert_eq!(
+            build_search_url("The Hobbit", 25, 3),
+            "https://openlibrary.org/search.json?q=The%20Hobbit&limit=25&page=3"
+        );
+    }
+
+    #[test]
+    fn fielded_search_url_encodes_title_and_author() {
+        assert_eq!(
+            build_fielded_search_url("The Hobbit", "J.R.R. Tolkien", 25, 2),
+            "https://openlibrary.org/search.json?title=The%20Hobbit&author=J.R.R.%20Tolkien&limit=25&page=2"
+        );
+        assert_eq!(
+            build_fielded_search_url_at_offset("Astérix & Obélix", "Goscinny", 10, 20),
+            "https://openlibrary.org/search.json?title=Ast%C3%A9rix%20%26%20Ob%C3%A9lix&author=Goscinny&limit=10&offset=20"
+        );
+        assert_eq!(
+            build_fielded_search_url_at_offset("Dune", "Frank Herbert", 25, 0),
+            "https://openlibrary.org/search.json?title=Dune&author=Frank%20Herbert&limit=25"
+        );
+    }
+
+    #[test]
+    fn search_inside_hit_maps_decoded_snippet() {
+        let response: OpenLibrarySearchInsideResponse = serde_json::from_str(
+            r#"{
+                "hits": {
+                    "hits": [
+                        {
+                            "highlight": {
+                                "text": ["In a hole in the ground there lived a {{{hobbit}}}. Not a nasty, dirty, wet hole &amp; that&#39;s  that"]
+                            },
+                            "edition": {
+                                "key": "/books/OL7353617M",
+                                "title": "The Hobbit",
+                                "works": [{"key": "/works/OL45804W"}]
+                            }
+                        },
+                        {"highlight": {"text": ["no edition"]}}
+                    ]
+                }
+            }"#,
+        )
+        .expect("Expected search inside response to deserialize");
+
+        let records: Vec<_> = response
+            .hits
+            .hits
+            .iter()
+            .filter_map(book_record_from_search_inside_hit)
+            .collect();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].work_id.as_deref(), Some("OL45804W"));
+        assert_eq!(
+            records[0].match_snippet.as_deref(),
+            Some("In a hole in the ground there lived a hobbit. Not a nasty, dirty, wet hole & that's that")
+        );
+        assert_eq!(
+            build_search_inside_url("lived a hobbit"),
+            "https://openlibrary.org/search/inside.json?q=lived%20a%20hobbit"
+        );
+    }
+
+    #[test]
+    fn decode_html_entities_keeps_unknown_ampersands() {
+        assert_eq!(
+            decode_html_entities("Tom &amp; Jerry &copy &#x41;&bogus;"),
+            "Tom & Jerry &copy A&bogus;"
+        );
+    }
+
+    #[test]
+    fn enrich_edition_with_work_adds_missing_work_fields() {
+        let edition = OpenLibraryBookRecord {
+            title: "The Hobbit, or There and Back Again".to_string(),
+            edition_id: Some("OL7353617M".to_string()),
+            work_id: Some("OL45804W".to_string()),
+            work_ids: vec!["OL45804W".to_string()],
+            publish_year: Some(1966),
+            ..Default::default()
+        };
+        let work = OpenLibraryWorkResponse {
+            key: "/works/OL45804W".to_string(),
+            title: "The Hobbit".to_string(),
+            description: Some(OpenLibraryDescription::Text("Bilbo's journey".to_string())),
+            subjects: vec!["Fantasy".to_string(), "Dragons".to_string()],
+            first_publish_date: Some("1937".to_string()),
+            ..Default::default()
+        };
+
+        let enriched = enrich_edition_with_work(edition, &work);
+        assert_eq!(enriched.title, "The Hobbit, or There and Back Again");
+        assert_eq!(
+            enriched.subjects,
+            vec!["Fantasy".to_string(), "Dragons".to_string()]
+        );
+        assert_eq!(enriched.description.as_deref(), Some("Bilbo's journey"));
+        assert_eq!(enriched.publish_year, Some(1966));
+        assert_eq!(enriched.first_publish_year, Some(1937));
+        assert_eq!(enriched.work_ids, vec!["OL45804W".to_string()]);
+    }
+
+    fn edition_entry(
+        key: &str,
+        publish_date: &str,
+        isbn_13: &[&str],
+    ) -> OpenLibraryEditionResponse {
+        OpenLibraryEditionResponse {
+            key: key.to_string(),
+            title: "The Hobbit".to_string(),
+            publish_date: Some(publish_date.to_string()),
+            isbn_13: isbn_13.iter().map(|isbn| isbn.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn original_edition_preference_matches_first_publish_year() {
+        let response = OpenLibraryWorkEditionsResponse {
+            size: None,
+            entries: vec![
+                edition_entry("/books/OL1M", "2012", &["9780547928227"]),
+                edition_entry("/books/OL2M", "1937", &[]),
+                edition_entry("/books/OL3M", "1966", &["9780395071229"]),
+            ],
+        };
+
+        let original = select_edition_from_work_editions(
+            &response,
+            EditionPreference::Original,
+            Some(1937),
+            &[],
+            None,
+            None,
+        )
+        .expect("Expected an edition");
+        assert_eq!(original.edition_id.as_deref(), Some("OL2M"));
+
+        let fallback = select_edition_from_work_editions(
+            &response,
+            EditionPreference::Original,
+            Some(1900),
+            &[],
+            None,
+            None,
+        )
+        .expect("Expected an edition");
+        assert_eq!(fallback.edition_id.as_deref(), Some("OL1M"));
+
+        let first = select_edition_from_work_editions(
+            &response,
+            EditionPreference::First,
+            None,
+            &[],
+            None,
+            None,
+        )
+        .expect("Expected an edition");
+        assert_eq!(first.edition_id.as_deref(), Some("OL1M"));
+    }
+
+    #[test]
+    fn language_chain_falls_through_to_next_language() {
+        let edition = |key: &str, language: &str| {
+            let mut edition = edition_entry(key, "1937", &[]);
+            edition.languages = vec![OpenLibraryLanguage::Key(OpenLibraryKeyRef {
+                key: format!("/languages/{language}"),
+            })];
+            edition
+        };
+        let response = OpenLibraryWorkEditionsResponse {
+            size: None,
+            entries: vec![
+                edition("/books/OL1M", "ger"),
+                edition("/books/OL2M", "eng"),
+                edition("/books/OL3M", "spa"),
+            ],
+        };
+        let chain = vec!["fre".to_string(), "eng".to_string()];
+
+        let selected = select_edition_from_work_editions(
+            &response,
+            EditionPreference::First,
+            None,
+            &chain,
+            None,
+            None,
+        )
+        .expect("Expected an edition");
+        assert_eq!(selected.edition_id.as_deref(), Some("OL2M"));
+
+        let any = select_edition_from_work_editions(
+            &response,
+            EditionPreference::First,
+            None,
+            &["fre".to_string()],
+            None,
+            None,
+        )
+        .expect("Expected an edition");
+        assert_eq!(any.edition_id.as_deref(), Some("OL1M"));
+    }
+
+    #[test]
+    fn best_work_lookup_surfaces_isbn_from_a_later_edition() {
+        let mut illustrated = edition_entry("/books/OL1M", "1937", &[]);
+        illustrated.covers = vec![42];
+        illustrated.number_of_pages = Some(310);
+        let response = OpenLibraryWorkEditionsResponse {
+            size: None,
+            entries: vec![
+                illustrated,
+                edition_entry("/books/OL2M", "1966", &["9780395071229"]),
+            ],
+        };
+        let work = OpenLibraryBookRecord {
+            title: "The Hobbit".to_string(),
+            work_id: Some("OL45804W".to_string()),
+            publish_year: Some(1937),
+            ..Default::default()
+        };
+        let select = |preference| {
+            select_edition_from_work_editions(&response, preference, Some(1937), &[], None, None)
+        };
+
+        let merged =
+            merge_work_with_edition(work, select(EditionPreference::Best), CoverPreference::Work);
+        assert_eq!(merged.edition_id.as_deref(), Some("OL2M"));
+        assert_eq!(merged.isbn13.as_deref(), Some("9780395071229"));
+
+        // The original printing is kept even without an ISBN-13.
+        let original = select(EditionPreference::Original);
+        assert_eq!(
+            original.and_then(|edition| edition.edition_id),
+            Some("OL1M".to_string())
+        );
+    }
+
+    #[test]
+    fn language_filtered_work_lookup_never_swaps_in_another_language() {
+        let edition = |key: &str, language: &str, isbn_13: &[&str]| {
+            let mut edition = edition_entry(key, "1937", isbn_13);
+            edition.languages = vec![OpenLibraryLanguage::Key(OpenLibraryKeyRef {
+                key: format!("/languages/{language}"),
+            })];
+            edition
+        };
+        let mut illustrated = edition("/books/OL2M", "fre", &[]);
+        illustrated.covers = vec![42];
+        illustrated.number_of_pages = Some(310);
+        let mut response = OpenLibraryWorkEditionsResponse {
+            size: None,
+            entries: vec![
+                edition("/books/OL1M", "eng", &["9780547928227"]),
+                illustrated,
+                edition("/books/OL3M", "fre", &["9782253049418"]),
+            ],
+        };
+        let select = |response: &OpenLibraryWorkEditionsResponse| {
+            select_edition_from_work_editions(
+                response,
+                EditionPreference::Best,
+                None,
+                &["fre".to_string()],
+                None,
+                None,
+            )
+            .and_then(|edition| edition.edition_id)
+        };
+
+        assert_eq!(select(&response).as_deref(), Some("OL3M"));
+
+        response.entries.pop();
+        assert_eq!(select(&response).as_deref(), Some("OL2M"));
+    }
+
+    #[test]
+    fn preferred_binding_favours_matching_format() {
+        let mut paperback = edition_entry("/books/OL1M", "1938", &["9780395071229"]);
+        paperback.physical_format = Some("Mass Market Paperback".to_string());
+        paperback.covers = vec![42];
+        let mut hardcover = edition_entry("/books/OL2M", "1937", &[]);
+        hardcover.physical_format = Some("Hardcover".to_string());
+        let unknown = edition_entry("/books/OL3M", "1937", &[]);
+        let response = OpenLibraryWorkEditionsResponse {
+            size: None,
+            entries: vec![paperback, hardcover, unknown],
+        };
+        let select = |preference, binding| {
+            select_edition_from_work_editions(&response, preference, None, &[], None, binding)
+                .and_then(|edition| edition.edition_id)
+        };
+
+        assert_eq!(
+            select(EditionPreference::Best, Some(Binding::Hardcover)).as_deref(),
+            Some("OL2M")
+        );
+        assert_eq!(
+            select(EditionPreference::First, Some(Binding::Paperback)).as_deref(),
+            Some("OL1M")
+        );
+        assert_eq!(
+            select(EditionPreference::Best, None).as_deref(),
+            Some("OL1M")
+        );
+
+        let unbound = OpenLibraryWorkEditionsResponse {
+            size: None,
+            entries: vec![edition_entry("/books/OL3M", "1937", &[])],
+        };
+        let fallback = select_edition_from_work_editions(
+            &unbound,
+            EditionPreference::First,
+            None,
+            &[],
+            None,
+            Some(Binding::Hardcover),
+        )
+        .expect("Expected an edition");
+        assert_eq!(fallback.edition_id.as_deref(), Some("OL3M"));
+    }
+
+    #[test]
+    fn preferred_country_favours_local_editions() {
+        let mut us = edition_entry("/books/OL1M", "1938", &["9780395071229"]);
+        us.publish_country = Some("mau".to_string());
+        us.covers = vec![42];
+        let mut uk = edition_entry("/books/OL2M", "1937", &[]);
+        uk.publish_country = Some("enk ".to_string());
+        let mut us_tied = uk.clone();
+        us_tied.publish_country = Some("mau".to_string());
+        let uk_tied = uk.clone();
+        let response = OpenLibraryWorkEditionsResponse {
+            size: None,
+            entries: vec![us, uk],
+        };
+
+        // A tie-breaker only: the more complete US edition still wins under `Best`.
+        let best = select_edition_from_work_editions(
+            &response,
+            EditionPreference::Best,
+            None,
+            &[],
+            Some("enk"),
+            None,
+        )
+        .expect("Expected an edition");
+        assert_eq!(best.edition_id.as_deref(), Some("OL1M"));
+
+        let first = select_edition_from_work_editions(
+            &response,
+            EditionPreference::First,
+            None,
+            &[],
+            Some("enk"),
+            None,
+        )
+        .expect("Expected an edition");
+        assert_eq!(first.edition_id.as_deref(), Some("OL2M"));
+
+        let mut tied_uk = uk_tied.clone();
+        tied_uk.key = "/books/OL3M".to_string();
+        let tied = OpenLibraryWorkEditionsResponse {
+            size: None,
+            entries: vec![us_tied, tied_uk],
+        };
+        let best = select_edition_from_work_editions(
+            &tied,
+            EditionPreference::Best,
+            None,
+            &[],
+            Some("enk"),
+            None,
+        )
+        .expect("Expected an edition");
+        assert_eq!(best.edition_id.as_deref(), Some("OL3M"));
+
+        let fallback = select_edition_from_work_editions(
+            &response,
+            EditionPreference::First,
+            None,
+            &[],
+            Some("fr"),
+            None,
+        )
+        .expect("Expected an edition");
+        assert_eq!(fallback.edition_id.as_deref(), Some("OL1M"));
+    }
+
+    #[test]
+    fn best_edition_preference_favours_complete_editions() {
+        let mut complete = edition_entry("/books/OL3M", "1966", &["9780395071229"]);
+        complete.covers = vec![42];
+        let response = OpenLibraryWorkEditionsResponse {
+            size: None,
+            entries: vec![
+                edition_entry("/books/OL1M", "2012", &[]),
+                edition_entry("/books/OL2M", "1937", &["9780547928227"]),
+                complete,
+            ],
+        };
+
+        let best = select_edition_from_work_editions(
+            &response,
+            EditionPreference::Best,
+            None,
+            &[],
+            None,
+            None,
+        )
+        .expect("Expected an edition");
+        assert_eq!(best.edition_id.as_deref(), Some("OL3M"));
+    }
+
+    #[test]
+    fn edition_isbn_match_is_literal() {
+        let response: OpenLibraryEditionResponse = serde_json::from_value(serde_json::json!({
+            "key": "/books/OL7353617M",
+            "isbn_13": ["978-0-14-032872-1"],
+            "isbn_10": ["0261103342"]
+        }))
+        .expect("Expected valid edition json");
+
+        assert!(edition_has_isbn(&response, "9780140328721"));
+        assert!(edition_has_isbn(&response, "0140328726"));
+        assert!(!edition_has_isbn(&response, "0140328728"));
+        assert!(edition_has_isbn(&response, "9780261103344"));
+        assert!(!edition_has_isbn(&response, "9780547928227"));
+    }
+
+    #[test]
+    fn work_author_ids_are_bare_and_deduped() {
+        let work: OpenLibraryWorkResponse = serde_json::from_value(serde_json::json!({
+            "key": "/works/OL262758W",
+            "authors": [
+                {"author": {"key": "/authors/OL26320A"}, "type": {"key": "/type/author_role"}},
+                {"type": {"key": "/type/author_role"}},
+                {"author": {"key": "/authors/OL26320A"}}
+            ]
+        }))
+        .expect("Expected valid work json");
+
+        assert_eq!(work_author_ids(&work), vec!["OL26320A".to_string()]);
+    }
+
+    #[test]
+    fn merges_covers_from_all_editions() {
+        let mut first = edition_entry("/books/OL1M", "2012", &[]);
+        first.covers = vec![10, 11];
+        let mut second = edition_entry("/books/OL2M", "1937", &[]);
+        second.covers = vec![11, -1, 20];
+        let mut third = edition_entry("/books/OL3M", "1966", &[]);
+        third.covers = vec![30];
+        let response = OpenLibraryWorkEditionsResponse {
+            size: None,
+            entries: vec![first, second, third],
+        };
+        let mut record = OpenLibraryBookRecord {
+            title: "The Hobbit".to_string(),
+            cover_ids: vec![10],
+            ..Default::default()
+        };
+
+        merge_edition_covers(&mut record, &response, 3);
+
+        assert_eq!(record.cover_ids, vec![10, 11, 20]);
+        assert_eq!(record.cover_id, Some(10));
+    }
+
+    #[test]
+    fn search_url_at_offset_omits_zero_offset() {
+        assert_eq!(
+            build_search_url_at_offset("The Hobbit", 25, 0),
+            "https://openlibrary.org/search.json?q=The%20Hobbit&limit=25"
+        );
+        assert_eq!(
+            build_search_url_at_offset("The Hobbit", 5, 50),
+            "https://openlibrary.org/search.json?q=The%20Hobbit&limit=5&offset=50"
+        );
+    }
+
+    #[test]
+    fn edition_response_maps_last_modified_and_revision() {
+        let response: OpenLibraryEditionResponse = serde_json::from_str(
+            r#"{
+                "key": "/books/OL7353617M",
+                "title": "The Hobbit",
+                "revision": 14,
+                "last_modified": {"type": "/type/datetime", "value": "2021-10-04T04:05:33.147982"}
+            }"#,
+        )
+        .expect("Expected edition to deserialize");
+
+        let record = book_record_from_edition_response(&response);
+        assert_eq!(
+            record.last_modified.as_deref(),
+            Some("2021-10-04T04:05:33.147982")
+        );
+        assert_eq!(record.revision, Some(14));
+        assert_eq!(
+            parse_datetime_millis("2021-10-04T04:05:33.147982"),
+            Some(1_633_320_333_147)
+        );
+        assert_eq!(parse_datetime_millis("1970-01-01"), Some(0));
+        assert_eq!(parse_datetime_millis("not a date"), None);
+    }
+
+    #[test]
+    fn wikidata_search_url_uses_identifier_field() {
+        assert_eq!(
+            build_wikidata_search_url("Q74287"),
+            "https://openlibrary.org/search.json?q=id_wikidata:Q74287&limit=1"
+        );
+        assert_eq!(normalize_wikidata_id("q42"), Some("Q42".to_string()));
+        assert_eq!(normalize_wikidata_id("Q"), None);
+        assert_eq!(normalize_wikidata_id("L42"), None);
     }
-}
-
-pub fn first_record_from_work_editions(
-    response: &OpenLibraryWorkEditionsResponse,
-) -> Option<OpenLibraryBookRecord> {
-    response
-        .entries
-        .first()
-        .map(book_record_from_edition_response)
-}
 
-pub fn merge_work_with_edition(
-    work: OpenLibraryBookRecord,
-    edition: Option<OpenLibraryBookRecord>,
-) -> OpenLibraryBookRecord {
-    let Some(edition) = edition else {
-        return work;
-    };
+    #[test]
+    fn goodreads_search_url_and_search_doc_id() {
+        assert_eq!(
+            build_goodreads_search_url("5907"),
+            "https://openlibrary.org/search.json?q=id_goodreads:5907&limit=1"
+        );
 
-    let mut cover_ids = work.cover_ids.clone();
-    for cover_id in edition.cover_ids.iter().copied() {
-        if !cover_ids.contains(&cover_id) {
-            cover_ids.push(cover_id);
-        }
-    }
-    if cover_ids.is_empty() {
-        cover_ids.extend(work.cover_id);
-        cover_ids.extend(edition.cover_id);
+        let doc = OpenLibrarySearchDoc {
+            key: "/works/OL45804W".to_string(),
+            title: "The Hobbit".to_string(),
+            id_goodreads: vec!["5907".to_string()],
+            ..Default::default()
+        };
+        let record = book_record_from_search_doc(&doc).expect("Expected mapped record");
+        assert_eq!(record.goodreads_id.as_deref(), Some("5907"));
     }
 
-    OpenLibraryBookRecord {
-        title: if work.title.is_empty() {
-            edition.title
-        } else {
-            work.title
-        },
-        edition_id: edition.edition_id.or(work.edition_id),
-        work_id: work.work_id.or(edition.work_id),
-        isbn13: edition.isbn13.or(work.isbn13),
-        cover_id: cover_ids
-            .first()
-            .copied()
-            .or(edition.cover_id)
-            .or(work.cover_id),
-        cover_ids,
-        publish_year: edition.publish_year.or(work.publish_year),
-        description: work.description.or(edition.description),
-        pages: edition.pages.or(work.pages),
-        language: edition.language.or(work.language),
-        authors: if work.authors.is_empty() {
-            edition.authors
-        } else {
-            work.authors
-        },
-        author_keys: if work.author_keys.is_empty() {
-            edition.author_keys
-        } else {
-            work.author_keys
-        },
-        subjects: if work.subjects.is_empty() {
-            edition.subjects
-        } else {
-            work.subjects
-        },
-        publishers: if edition.publishers.is_empty() {
-            work.publishers
-        } else {
-            edition.publishers
-        },
+    #[test]
+    fn search_doc_maps_ocaid_and_ebook_access() {
+        let doc = OpenLibrarySearchDoc {
+            key: "/works/OL45804W".to_string(),
+            title: "The Hobbit".to_string(),
+            ia: vec!["hobbit1966tolk".to_string(), "hobbit00tolk".to_string()],
+            ebook_access: Some("borrowable".to_string()),
+            lending_identifier_s: Some("hobbit00tolk".to_string()),
+            ..Default::default()
+        };
+        let record = book_record_from_search_doc(&doc).expect("Expected mapped record");
+        assert_eq!(record.ocaid.as_deref(), Some("hobbit00tolk"));
+        assert_eq!(record.ebook_access, Some(EbookAccess::Borrowable));
+
+        let unconfirmed = OpenLibrarySearchDoc {
+            lending_identifier_s: None,
+            ..doc
+        };
+        let record = book_record_from_search_doc(&unconfirmed).expect("Expected mapped record");
+        assert_eq!(record.ocaid.as_deref(), Some("hobbit1966tolk"));
+        assert_eq!(record.ebook_access, None);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn subject_works_map_to_records() {
+        assert_eq!(
+            build_subject_works_url("science_fiction", 5, true, Some("1950-1960")),
+            "https://openlibrary.org/subjects/science_fiction.json?limit=5&ebooks=true&published_in=1950-1960"
+        );
+
+        let response: OpenLibrarySubjectResponse = serde_json::from_str(
+            r#"{
+                "work_count": 1,
+                "works": [{
+                    "key": "/works/OL46125W",
+                    "title": "Dune",
+                    "cover_id": 11481354,
+                    "cover_edition_key": "OL26242482M",
+                    "first_publish_year": 1965,
+                    "edition_count": 88,
+                    "authors": [{"key": "/authors/OL79034A", "name": "Frank Herbert"}]
+                }]
+            }"#,
+        )
+        .expect("Expected valid subject json");
+        let record =
+            book_record_from_subject_work(&response.works[0]).expect("Expected mapped record");
+        assert_eq!(record.work_id.as_deref(), Some("OL46125W"));
+        assert_eq!(record.edition_id.as_deref(), Some("OL26242482M"));
+        assert_eq!(record.cover_id, Some(11481354));
+        assert_eq!(record.authors, vec!["Frank Herbert"]);
+        assert_eq!(record.author_keys, vec!["OL79034A"]);
+        assert_eq!(record.edition_count, Some(88));
+    }
 
     #[test]
-    fn normalize_ids_from_paths() {
+    fn author_details_carry_bio_years_and_redirects() {
+        let response: OpenLibraryAuthorResponse = serde_json::from_str(
+            r#"{
+                "name": "J.R.R. Tolkien",
+                "bio": {"type": "/type/text", "value": "English writer and philologist."},
+                "birth_date": "3 January 1892",
+                "death_date": "2 September 1973",
+                "photos": [-1]
+            }"#,
+        )
+        .expect("Expected valid author json");
+        let details = author_details_from_response("OL26320A", &response);
         assert_eq!(
-            normalize_openlibrary_id("/works/OL45804W", "works"),
-            Some("OL45804W".to_string())
+            details.bio.as_deref(),
+            Some("English writer and philologist.")
         );
+        assert_eq!(details.birth_year, Some(1892));
+        assert_eq!(details.death_year, Some(1973));
+        assert_eq!(details.photo_id, None);
+        assert_eq!(author_redirect_target(&response), None);
+
+        let redirect: OpenLibraryAuthorResponse = serde_json::from_str(
+            r#"{"type": {"key": "/type/redirect"}, "location": "/authors/OL26320A"}"#,
+        )
+        .expect("Expected valid redirect json");
         assert_eq!(
-            normalize_openlibrary_id("books/OL7353617M", "books"),
-            Some("OL7353617M".to_string())
+            author_redirect_target(&redirect).as_deref(),
+            Some("OL26320A")
         );
     }
 
     #[test]
-    fn encode_query_component_encodes_spaces() {
-        assert_eq!(encode_query_component("The Hobbit"), "The%20Hobbit");
+    fn cover_urls_from_lccn_and_oclc() {
+        assert_eq!(
+            build_cover_url_from_lccn("93005405", CoverSize::Large),
+            "https://covers.openlibrary.org/b/lccn/93005405-L.jpg"
+        );
+        assert_eq!(
+            build_cover_url_from_oclc("28419896", CoverSize::Small),
+            "https://covers.openlibrary.org/b/oclc/28419896-S.jpg"
+        );
     }
 
     #[test]
@@ -448,12 +2861,45 @@ mod tests {
         assert_eq!(extract_year_from_text("September 21, 1937"), Some(1937));
     }
 
+    #[test]
+    fn extracts_historical_years() {
+        assert_eq!(extract_historical_year("800"), Some(800));
+        assert_eq!(extract_historical_year("45 BC"), Some(-45));
+        assert_eq!(extract_historical_year("c. 380 B.C.E."), Some(-380));
+        assert_eq!(extract_historical_year("AD 79"), Some(79));
+        assert_eq!(extract_historical_year("1200 BC"), Some(-1200));
+        assert_eq!(extract_historical_year("ca. 2100 B.C.E."), Some(-2100));
+        assert_eq!(extract_historical_year("September 21, 1937"), None);
+        assert_eq!(extract_historical_year("March 21"), None);
+
+        assert_eq!(publish_years(Some("1937")), (Some(1937), None));
+        assert_eq!(publish_years(Some("1200 BC")), (None, Some(-1200)));
+        assert_eq!(publish_years(Some("800")), (Some(800), Some(800)));
+        assert_eq!(publish_years(Some("45 BC")), (None, Some(-45)));
+    }
+
     #[test]
     fn first_isbn13_prefers_normalized_13_digit() {
         let values = vec!["978-0-14-032872-1".to_string(), "0140328726".to_string()];
         assert_eq!(first_isbn13(&values), Some("9780140328721".to_string()));
     }
 
+    #[test]
+    fn normalize_isbn13_verifies_check_digit() {
+        assert_eq!(
+            normalize_isbn13("978-0-14-032872-1"),
+            Some("9780140328721".to_string())
+        );
+        assert_eq!(normalize_isbn13("9780140328720"), None);
+        // A 13 digit phone number is not an ISBN.
+        assert_eq!(normalize_isbn13("+33 6 12 34 56 78 90"), None);
+        assert_eq!(
+            normalize_isbn13_unchecked("9780140328720"),
+            Some("9780140328720".to_string())
+        );
+        assert_eq!(normalize_isbn13_unchecked("978014032872"), None);
+    }
+
     #[test]
     fn search_doc_maps_author_keys() {
         let doc = OpenLibrarySearchDoc {
@@ -469,6 +2915,7 @@ mod tests {
             subject: vec!["Fantasy".to_string()],
             publisher: vec!["Allen & Unwin".to_string()],
             number_of_pages_median: None,
+            ..Default::default()
         };
 
         let record = book_record_from_search_doc(&doc).expect("Expected mapped record");
@@ -481,14 +2928,8 @@ mod tests {
         let response = OpenLibraryEditionResponse {
             key: "/books/OL7353617M".to_string(),
             title: "The Hobbit".to_string(),
-            description: None,
-            works: vec![],
-            isbn_13: vec![],
             covers: vec![12345, 0, -1, 67890, 12345],
-            number_of_pages: None,
-            publish_date: None,
-            languages: vec![],
-            publishers: vec![],
+            ..Default::default()
         };
 
         let record = book_record_from_edition_response(&response);
@@ -496,6 +2937,347 @@ mod tests {
         assert_eq!(record.cover_id, Some(12345));
     }
 
+    #[test]
+    fn edition_languages_accept_keys_and_bare_codes() {
+        let language = |languages: serde_json::Value| {
+            let response: OpenLibraryEditionResponse = serde_json::from_value(serde_json::json!({
+                "key": "/books/OL7353617M",
+                "title": "The Hobbit",
+                "languages": languages
+            }))
+            .expect("Expected valid edition json");
+            let codes: Vec<String> = response
+                .languages
+                .iter()
+                .filter_map(OpenLibraryLanguage::code)
+                .collect();
+            (codes, book_record_from_edition_response(&response).language)
+        };
+
+        assert_eq!(
+            language(serde_json::json!([{"key": "/languages/eng"}])),
+            (vec!["eng".to_string()], Some("eng".to_string()))
+        );
+        assert_eq!(
+            language(serde_json::json!(["fre"])),
+            (vec!["fre".to_string()], Some("fre".to_string()))
+        );
+        assert_eq!(
+            language(serde_json::json!(["", {"key": "/languages/ger"}, "eng"])),
+            (
+                vec!["ger".to_string(), "eng".to_string()],
+                Some("ger".to_string())
+            )
+        );
+    }
+
+    #[test]
+    fn edition_response_maps_all_linked_works() {
+        let response = OpenLibraryEditionResponse {
+            key: "/books/OL1234567M".to_string(),
+            title: "Collected Stories".to_string(),
+            works: vec![
+                OpenLibraryKeyRef {
+                    key: "/works/OL100W".to_string(),
+                },
+                OpenLibraryKeyRef {
+                    key: "/works/OL200W".to_string(),
+                },
+                OpenLibraryKeyRef {
+                    key: "/works/OL100W".to_string(),
+                },
+            ],
+            ..Default::default()
+        };
+
+        let record = book_record_from_edition_response(&response);
+        assert_eq!(record.work_id, Some("OL100W".to_string()));
+        assert_eq!(
+            record.work_ids,
+            vec!["OL100W".to_string(), "OL200W".to_string()]
+        );
+    }
+
+    #[test]
+    fn edition_response_maps_table_of_contents() {
+        let response: OpenLibraryEditionResponse = serde_json::from_value(serde_json::json!({
+            "key": "/books/OL1M",
+            "title": "Reference Work",
+            "table_of_contents": [
+                { "level": 0, "label": "1", "title": "Introduction", "pagenum": "1" },
+                { "level": 0, "title": "  ", "pagenum": "5" },
+                "Not an entry",
+                { "title": "Appendix", "pagenum": 312 }
+            ]
+        }))
+        .expect("Expected edition response");
+
+        let record = book_record_from_edition_response(&response);
+        assert_eq!(
+            record.table_of_contents,
+            vec![
+                OpenLibraryTocEntry {
+                    title: "Introduction".to_string(),
+                    pagenum: Some("1".to_string()),
+                    label: Some("1".to_string()),
+                },
+                OpenLibraryTocEntry {
+                    title: "Appendix".to_string(),
+                    pagenum: Some("312".to_string()),
+                    label: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn edition_response_keeps_alternate_script_titles() {
+        let response = OpenLibraryEditionResponse {
+            key: "/books/OL2M".to_string(),
+            title: "Noruwei no Mori".to_string(),
+            other_titles: vec![
+                "ノルウェイの森".to_string(),
+                "noruwei no mori".to_string(),
+                " ".to_string(),
+                "ノルウェイの森".to_string(),
+            ],
+            ..Default::default()
+        };
+
+        let record = book_record_from_edition_response(&response);
+        assert_eq!(record.other_titles, vec!["ノルウェイの森".to_string()]);
+    }
+
+    #[test]
+    fn parse_contribution_detects_roles() {
+        assert_eq!(
+            parse_contribution("Illustrated by Pauline Baynes."),
+            Some(OpenLibraryContributor {
+                name: "Pauline Baynes".to_string(),
+                role: "illustrator".to_string(),
+            })
+        );
+        assert_eq!(
+            parse_contribution("Anthea Bell (Translator)"),
+            Some(OpenLibraryContributor {
+                name: "Anthea Bell".to_string(),
+                role: "translator".to_string(),
+            })
+        );
+        assert_eq!(
+            parse_contribution("Tolkien, Christopher, ed."),
+            Some(OpenLibraryContributor {
+                name: "Tolkien, Christopher".to_string(),
+                role: "editor".to_string(),
+            })
+        );
+        assert_eq!(
+            parse_contribution("Tolkien, Edith"),
+            Some(OpenLibraryContributor {
+                name: "Tolkien, Edith".to_string(),
+                role: "contributor".to_string(),
+            })
+        );
+        assert_eq!(
+            parse_contribution("Alan Lee"),
+            Some(OpenLibraryContributor {
+                name: "Alan Lee".to_string(),
+                role: "contributor".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn edition_response_maps_contributors() {
+        let response: OpenLibraryEditionResponse = serde_json::from_value(serde_json::json!({
+            "key": "/books/OL1M",
+            "title": "The Hobbit",
+            "contributors": [{ "role": "Illustrator", "name": "Alan Lee" }],
+            "contributions": ["Illustrated by Alan Lee", "Translated by Francis Ledoux"]
+        }))
+        .expect("Expected edition response");
+
+        let record = book_record_from_edition_response(&response);
+        assert_eq!(
+            record.contributors,
+            vec![
+                OpenLibraryContributor {
+                    name: "Alan Lee".to_string(),
+                    role: "illustrator".to_string(),
+                },
+                OpenLibraryContributor {
+                    name: "Francis Ledoux".to_string(),
+                    role: "translator".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn title_must_contain_significant_query_tokens() {
+        assert!(title_contains_query_tokens(
+            "The Lord of the Rings: The Two Towers",
+            "lord of the rings two towers"
+        ));
+        assert!(!title_contains_query_tokens(
+            "The Lord of the Rings",
+            "lord of the rings two towers"
+        ));
+        assert!(title_contains_query_tokens("Anything", "the of"));
+    }
+
+    #[test]
+    fn search_confidence_prefers_exact_title_matches() {
+        let exact = OpenLibraryBookRecord {
+            title: "The Hobbit".to_string(),
+            work_id: Some("OL45804W".to_string()),
+            ..Default::default()
+        };
+        let partial = OpenLibraryBookRecord {
+            title: "The Hobbit Companion".to_string(),
+            work_id: Some("OL1W".to_string()),
+            ..Default::default()
+        };
+
+        let exact_score = search_confidence(&exact, "the hobbit");
+        let partial_score = search_confidence(&partial, "the hobbit");
+        assert!(exact_score > partial_score);
+        assert!(exact_score <= 1.0);
+        assert_eq!(search_confidence(&exact, "!!!"), 0.0);
+    }
+
+    #[test]
+    fn search_confidence_rewards_author_match() {
+        let record = OpenLibraryBookRecord {
+            title: "The Hobbit".to_string(),
+            authors: vec!["J.R.R. Tolkien".to_string()],
+            ..Default::default()
+        };
+
+        assert!(
+            search_confidence(&record, "The Hobbit Tolkien")
+                > search_confidence(&record, "The Hobbit Lewis")
+        );
+    }
+
+    #[test]
+    fn ratings_response_without_votes_is_absent() {
+        let rated: OpenLibraryRatingsResponse = serde_json::from_value(serde_json::json!({
+            "summary": { "average": 4.25, "count": 12 },
+            "counts": { "1": 0, "2": 1, "3": 1, "4": 4, "5": 6 }
+        }))
+        .expect("Expected ratings response");
+        assert_eq!(
+            rating_from_response(&rated),
+            Some(OpenLibraryRating {
+                average: 4.25,
+                count: 12
+            })
+        );
+
+        let unrated: OpenLibraryRatingsResponse = serde_json::from_value(serde_json::json!({
+            "summary": { "average": null, "count": 0 }
+        }))
+        .expect("Expected ratings response");
+        assert_eq!(rating_from_response(&unrated), None);
+    }
+
+    #[test]
+    fn parse_series_reads_name_and_position() {
+        assert_eq!(
+            parse_series("Harry Potter ; 1"),
+            Some(OpenLibrarySeries {
+                name: "Harry Potter".to_string(),
+                position: Some(1.0),
+            })
+        );
+        assert_eq!(
+            parse_series("(Discworld #3)"),
+            Some(OpenLibrarySeries {
+                name: "Discworld".to_string(),
+                position: Some(3.0),
+            })
+        );
+        assert_eq!(
+            parse_series("The Chronicles of Narnia, book 2"),
+            Some(OpenLibrarySeries {
+                name: "The Chronicles of Narnia".to_string(),
+                position: Some(2.0),
+            })
+        );
+        assert_eq!(
+            parse_series("Penguin Modern Classics"),
+            Some(OpenLibrarySeries {
+                name: "Penguin Modern Classics".to_string(),
+                position: None,
+            })
+        );
+        assert_eq!(parse_series("  "), None);
+    }
+
+    #[test]
+    fn detect_volume_number_from_titles() {
+        assert_eq!(detect_volume_number("Dune Chronicles, Book 3"), Some(3.0));
+        assert_eq!(detect_volume_number("One Piece, Vol. 12"), Some(12.0));
+        assert_eq!(detect_volume_number("Discworld #4.5"), Some(4.5));
+        assert_eq!(detect_volume_number("The Notebook"), None);
+    }
+
+    #[test]
+    fn work_editions_total_comes_from_size() {
+        let response: OpenLibraryWorkEditionsResponse =
+            serde_json::from_value(serde_json::json!({"size": 12, "entries": []})).unwrap();
+        assert_eq!(work_edition_total(&response), Some(12));
+
+        let without_size: OpenLibraryWorkEditionsResponse =
+            serde_json::from_value(serde_json::json!({"entries": []})).unwrap();
+        assert_eq!(work_edition_total(&without_size), None);
+        let negative = OpenLibraryWorkEditionsResponse {
+            size: Some(-1),
+            ..Default::default()
+        };
+        assert_eq!(work_edition_total(&negative), None);
+    }
+
+    #[test]
+    fn work_editions_become_one_merged_record_each() {
+        let work = OpenLibraryBookRecord {
+            title: "The Hobbit".to_string(),
+            work_id: Some("OL45804W".to_string()),
+            authors: vec!["J.R.R. Tolkien".to_string()],
+            ..Default::default()
+        };
+        let editions: OpenLibraryWorkEditionsResponse = serde_json::from_value(serde_json::json!({
+            "entries": [
+                {"key": "/books/OL1M", "title": "The Hobbit", "isbn_13": ["9780261103344"],
+                 "physical_format": "Hardcover"},
+                {"key": "/books/OL2M", "title": "The Hobbit", "isbn_13": ["9780547928227"]}
+            ]
+        }))
+        .unwrap();
+
+        let records = records_from_work_editions(&work, &editions, CoverPreference::Work);
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].edition_id.as_deref(), Some("OL1M"));
+        assert_eq!(records[0].isbn13.as_deref(), Some("9780261103344"));
+        assert_eq!(records[1].edition_id.as_deref(), Some("OL2M"));
+        assert_eq!(records[1].isbn13.as_deref(), Some("9780547928227"));
+        assert!(records.iter().all(|record| {
+            record.work_id.as_deref() == Some("OL45804W")
+                && record.authors == vec!["J.R.R. Tolkien".to_string()]
+        }));
+        assert_ne!(
+            records[0].edition_dedup_key(),
+            records[1].edition_dedup_key()
+        );
+        assert_eq!(records[0].dedup_key(), records[1].dedup_key());
+
+        let empty = OpenLibraryWorkEditionsResponse::default();
+        let records = records_from_work_editions(&work, &empty, CoverPreference::Work);
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].edition_id, None);
+    }
+
     #[test]
     fn merge_work_with_edition_keeps_all_cover_ids() {
         let work = OpenLibraryBookRecord {
@@ -514,35 +3296,35 @@ mod tests {
             ..Default::default()
         };
 
-        let merged = merge_work_with_edition(work, Some(edition));
+        let merged = merge_work_with_edition(work, Some(edition), CoverPreference::Work);
         assert_eq!(merged.cover_ids, vec![2701529, 2701530, 6307679, 9999999]);
         assert_eq!(merged.cover_id, Some(2701529));
     }
-}
-fn positive_cover_id(value: i64) -> Option<u64> {
-    if value > 0 {
-        Some(value as u64)
-    } else {
-        None
-    }
-}
 
-fn extract_cover_ids(values: &[i64]) -> Vec<u64> {
-    let mut cover_ids = Vec::new();
-    for value in values {
-        if let Some(cover_id) = positive_cover_id(*value) {
-            if !cover_ids.contains(&cover_id) {
-                cover_ids.push(cover_id);
-            }
-        }
-    }
-    cover_ids
-}
+    #[test]
+    fn merge_work_with_edition_leads_with_preferred_primary() {
+        let work = OpenLibraryBookRecord {
+            title: "The Hobbit".to_string(),
+            work_id: Some("OL45804W".to_string()),
+            cover_ids: vec![6307679, 2701529],
+            cover_id: Some(2701529),
+            ..Default::default()
+        };
+        let edition = OpenLibraryBookRecord {
+            title: "The Hobbit".to_string(),
+            edition_id: Some("OL7353617M".to_string()),
+            cover_ids: vec![9999999, 2701530],
+            cover_id: Some(2701530),
+            ..Default::default()
+        };
 
-fn positive_u32(value: i64) -> Option<u32> {
-    if value > 0 && value <= u32::MAX as i64 {
-        Some(value as u32)
-    } else {
-        None
+        let merged =
+            merge_work_with_edition(work.clone(), Some(edition.clone()), CoverPreference::Work);
+        assert_eq!(merged.cover_ids, vec![2701529, 6307679, 2701530, 9999999]);
+        assert_eq!(merged.cover_id, Some(2701529));
+
+        let merged = merge_work_with_edition(work, Some(edition), CoverPreference::Edition);
+        assert_eq!(merged.cover_ids, vec![2701530, 9999999, 2701529, 6307679]);
+        assert_eq!(merged.cover_id, Some(2701530));
     }
 }
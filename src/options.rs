@@ -0,0 +1,759 @@
+use std::collections::HashMap;
+
+/// Page counts above this are treated as bad data unless `max_pages` says otherwise.
+pub const DEFAULT_MAX_PAGES: u32 = 20_000;
+
+/// Search results requested per page unless `search_limit` says otherwise.
+pub const DEFAULT_SEARCH_LIMIT: u32 = 25;
+/// Largest `search_limit` honoured; OpenLibrary pages beyond this get slow.
+pub const MAX_SEARCH_LIMIT: u32 = 100;
+
+/// Which identifier `Book.id` is built from first; the others remain fallbacks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CanonicalPreference {
+    #[default]
+    Isbn,
+    Edition,
+    Work,
+}
+
+impl CanonicalPreference {
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "isbn" | "isbn13" => Some(Self::Isbn),
+            "edition" => Some(Self::Edition),
+            "work" => Some(Self::Work),
+            _ => None,
+        }
+    }
+}
+
+/// `isbn_only_canonical`: key every `Book.id` on the ISBN-13 whatever the
+/// `canonical_preference`, and decide what happens to records without one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum IsbnOnlyCanonical {
+    #[default]
+    Off,
+    /// Drop records without an ISBN-13 (`true` or `exclude`).
+    Exclude,
+    /// Keep them with `params.missingIsbn` set (`flag`).
+    Flag,
+}
+
+impl IsbnOnlyCanonical {
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "false" | "off" => Some(Self::Off),
+            "true" | "exclude" => Some(Self::Exclude),
+            "flag" => Some(Self::Flag),
+            _ => None,
+        }
+    }
+}
+
+/// How a work lookup picks the edition merged into its record.
+///
+/// `Original` only considers editions published in the work's first publish year and
+/// falls back to `Best` when none match (or the work has no first publish date).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EditionPreference {
+    /// OpenLibrary's first listed edition (a single edition is fetched).
+    #[default]
+    First,
+    /// The most complete edition among the first candidates; one without an ISBN-13 gives
+    /// way to a narrowed candidate that has one.
+    Best,
+    /// The original printing, matched on the first publish year.
+    Original,
+}
+
+impl EditionPreference {
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "first" => Some(Self::First),
+            "best" => Some(Self::Best),
+            "original" => Some(Self::Original),
+            _ => None,
+        }
+    }
+}
+
+/// Covers store size suffix: `S`, `M` or `L` (default).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CoverSize {
+    Small,
+    Medium,
+    #[default]
+    Large,
+}
+
+impl CoverSize {
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "s" | "small" => Some(Self::Small),
+            "m" | "medium" => Some(Self::Medium),
+            "l" | "large" => Some(Self::Large),
+            _ => None,
+        }
+    }
+
+    pub fn suffix(self) -> &'static str {
+        match self {
+            Self::Small => "S",
+            Self::Medium => "M",
+            Self::Large => "L",
+        }
+    }
+}
+
+/// Whose covers lead when a work lookup merges the work with an edition.
+///
+/// Each source's primary is its first listed cover. The merged list is the preferred
+/// source's primary, its remaining covers, then the other source's primary and covers,
+/// deduped; the merged primary is the first entry.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CoverPreference {
+    #[default]
+    Work,
+    Edition,
+}
+
+impl CoverPreference {
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "work" => Some(Self::Work),
+            "edition" => Some(Self::Edition),
+            _ => None,
+        }
+    }
+}
+
+/// Binding a work lookup favours when picking its edition, from `prefer_hardcover` or
+/// `prefer_paperback` (hardcover wins when both are set).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Binding {
+    Hardcover,
+    Paperback,
+}
+
+impl Binding {
+    /// Whether an edition's free-text `physical_format` is this binding.
+    pub fn matches(self, physical_format: &str) -> bool {
+        let format = physical_format.to_ascii_lowercase();
+        let words: &[&str] = match self {
+            Self::Hardcover => &["hardcover", "hardback", "hard cover", "library binding"],
+            Self::Paperback => &["paperback", "softcover", "soft cover", "mass market"],
+        };
+        words.iter().any(|word| format.contains(word))
+    }
+}
+
+/// Scheme of emitted cover and photo URLs. `http` only exists for intranet proxies that
+/// serve the covers store over plain HTTP; anything but `http` keeps `https`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CoverScheme {
+    #[default]
+    Https,
+    Http,
+}
+
+impl CoverScheme {
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "https" => Some(Self::Https),
+            "http" => Some(Self::Http),
+            _ => None,
+        }
+    }
+
+    /// Rewrites a URL built with `https://` to this scheme.
+    pub fn apply(self, url: String) -> String {
+        match (self, url.strip_prefix("https://")) {
+            (Self::Http, Some(rest)) => format!("http://{rest}"),
+            _ => url,
+        }
+    }
+}
+
+/// Order of search results.
+///
+/// - `relevance`: as ranked by the search API.
+/// - `editions_desc`: works with the most editions first; ties and records without a
+///   count keep their relevance order, uncounted records last.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SortOrder {
+    #[default]
+    Relevance,
+    EditionsDesc,
+}
+
+impl SortOrder {
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "relevance" => Some(Self::Relevance),
+            "editions_desc" => Some(Self::EditionsDesc),
+            _ => None,
+        }
+    }
+}
+
+/// What an ISBN or edition lookup returns when the edition links more than one work.
+///
+/// - `first`: the edition with its first work.
+/// - `error`: a 409 listing the candidate works.
+/// - `all`: the edition with its first work, then a record per other work, built from
+///   that work.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AmbiguityMode {
+    #[default]
+    First,
+    Error,
+    All,
+}
+
+impl AmbiguityMode {
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "first" => Some(Self::First),
+            "error" => Some(Self::Error),
+            "all" => Some(Self::All),
+            _ => None,
+        }
+    }
+}
+
+/// Master switch over the optional enrichment fetches; individual flags still decide
+/// which of the allowed enrichments run. A flag the level does not allow is turned off
+/// even when set explicitly.
+///
+/// - `off`: no enrichment request beyond the lookup itself.
+/// - `conservative`: only enrichments costing a request or two per record
+///   (`enrich_from_work`, `include_ratings`, `authors_from_work`).
+/// - `full`: every enrichment, including per-author, per-subject and per-cover fan-out.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EnrichmentLevel {
+    Off,
+    Conservative,
+    #[default]
+    Full,
+}
+
+impl EnrichmentLevel {
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "off" | "none" => Some(Self::Off),
+            "conservative" => Some(Self::Conservative),
+            "full" => Some(Self::Full),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LookupOptions {
+    /// Send `If-None-Match` with the last stored ETag and reuse the stored body on a 304.
+    pub caching: bool,
+    /// Emit the edition table of contents as `tableOfContents`.
+    pub include_toc: bool,
+    /// Keep fetching search pages until this many results survive dedup.
+    pub min_results: Option<usize>,
+    /// Mark authors that look like corporate bodies (`United States. Congress.`) with
+    /// `Person.kind` `organization` instead of `author`. Heuristic, so opt-in.
+    pub exclude_org_authors: bool,
+    /// Cap on subject tags, applied after cleaning and dedup. Uncapped by default so
+    /// existing payloads are unchanged.
+    pub max_subjects: Option<usize>,
+    /// Cap on people (authors, then contributors), applied after dedup; uncapped by default.
+    pub max_people: Option<usize>,
+    /// `lookup_subject` facet: only works with an ebook.
+    pub ebooks: bool,
+    /// `lookup_subject` facet: a publication year range such as `1500-1600`.
+    pub published_in: Option<String>,
+    /// Author hint turning name searches into fielded `title` + `author` searches.
+    pub author: Option<String>,
+    /// Merge in the plain name search when the fielded search keeps fewer than
+    /// `min_results` (or no) records.
+    pub broaden_if_few: bool,
+    /// Fetch edition ratings, falling back to work ratings.
+    pub include_ratings: bool,
+    /// Upper bound on HTTP requests per lookup; optional enrichment stops once reached.
+    pub max_requests: Option<u32>,
+    /// Largest plausible page count; higher values are dropped.
+    pub max_pages: Option<u32>,
+    /// Search results per page, see `search_limit()`.
+    pub search_limit: Option<u32>,
+    /// Display language for subject tags (e.g. `fr`); ids stay on the English key.
+    pub locale: Option<String>,
+    /// Append a truncated, sanitized upstream body to HTTP error messages.
+    pub verbose_errors: bool,
+    /// Move trailing parenthetical segments out of the book name into `titleNote`.
+    pub strip_parentheticals: bool,
+    /// Identifier tried first when building the canonical `Book.id`; read it through
+    /// `canonical_preference()`, which applies `isbn_only_canonical`.
+    pub canonical_preference: CanonicalPreference,
+    /// Key `Book.id` on the ISBN-13 only: `exclude` (or `true`) drops records without one,
+    /// `flag` keeps them with `missingIsbn`, `off` (default) leaves ids alone.
+    pub isbn_only_canonical: IsbnOnlyCanonical,
+    /// Run text searches against book contents (`search/inside.json`) instead of metadata.
+    pub search_inside: bool,
+    /// Fetch `/authors/{id}.json` for every author key to add dates and alternate names.
+    pub resolve_authors: bool,
+    /// Only emit these `Book.params` keys; `None` emits everything.
+    pub result_fields: Option<Vec<String>>,
+    /// After an edition or ISBN fetch, also fetch the linked work for subjects and description.
+    pub enrich_from_work: bool,
+    /// Edition merged into work lookups.
+    pub edition_preference: EditionPreference,
+    /// Display people as `First Last` with spaced initials; the raw name goes to `rawName`.
+    pub normalize_authors: bool,
+    /// Fetch `/subjects/{slug}.json` for this many leading subjects to add work counts;
+    /// at most 10 distinct subjects per lookup, each counted against `max_requests`.
+    pub subject_enrichment: Option<usize>,
+    /// Search `book.name` when a work id 404s; on unless set to false.
+    pub work_fallback_to_name: Option<bool>,
+    /// Fetch each image result to drop missing covers and fill in width and height.
+    pub verify_covers: bool,
+    /// Truncate `Book.overview` at a word boundary past this many characters.
+    pub max_description_chars: Option<usize>,
+    /// When the overview is truncated, keep the full text in `descriptionFull`.
+    pub keep_full_description: bool,
+    /// Return records and images as fetched, without dedup.
+    pub no_dedup: bool,
+    /// Work lookups return one record per edition (each merged with the work) instead of
+    /// the single selected edition; dedup then keeps one record per edition.
+    pub all_editions: bool,
+    /// Reorder covers by their stored size (largest first) using the covers metadata API.
+    pub rank_covers: bool,
+    /// Drop search results without an ISBN-13.
+    pub require_isbn: bool,
+    /// Gather covers from every edition of a work into the image lookup.
+    pub all_edition_covers: bool,
+    /// Fall back to OLID, LCCN or OCLC cover URLs when no cover id is known; on unless
+    /// set to false, as those URLs are often placeholders.
+    pub olid_cover_fallback: Option<bool>,
+    /// Recover an ISBN-13 one digit edit away from a malformed 12, 13 or 14 digit value.
+    pub fuzzy_isbn: bool,
+    /// `Book.lang` for records whose source lists no language (e.g. `eng`).
+    pub default_language: Option<String>,
+    /// Fill an edition, ISBN or work lookup without authors from the work's author
+    /// references, one author fetch each; on unless set to false.
+    pub authors_from_work: Option<bool>,
+    /// Add `parentWorkId`/`parentWorkUrl` to edition records that link a work.
+    pub include_work_link_for_editions: bool,
+    /// Add a `_meta` object (endpoint, base URL, request count) to each result's params.
+    pub with_envelope: bool,
+    /// Extra attempts for GETs that fail in transport, with a 429 or with a 5xx. Responses
+    /// carrying `Retry-After` are returned as is, since the plugin cannot wait them out.
+    pub max_retries: Option<u32>,
+    /// Treat an ISBN lookup as not found when the edition does not list the queried ISBN.
+    pub strict_isbn_match: bool,
+    /// Source whose covers lead merged work records.
+    pub cover_preference: CoverPreference,
+    /// Handling of an edition linking several works, see `AmbiguityMode`.
+    pub on_ambiguous: AmbiguityMode,
+    /// Search result order: `relevance` keeps the API ranking, `editions_desc` puts the
+    /// works with the most editions first.
+    pub sort: SortOrder,
+    /// Enrichments allowed at all, see `EnrichmentLevel`.
+    pub enrichment: EnrichmentLevel,
+    /// Return only the cover at this position (primary first); out of range picks the primary.
+    pub cover_index: Option<usize>,
+    /// Size of every cover image URL (`S`/`M`/`L`); unknown values fall back to `L`.
+    pub cover_size: CoverSize,
+    /// Scheme of cover and photo URLs: `https` (default) or `http` for plain-HTTP proxies.
+    pub cover_scheme: CoverScheme,
+    /// Attach the pre-merge work and edition of work lookups under `_sources`.
+    pub debug_sources: bool,
+    /// Drop search results whose title lacks a significant (non-stopword) query token.
+    pub require_all_tokens: bool,
+    /// Drop records without a description once enriched; search hits borrow their work's
+    /// unless `enrichment` is off.
+    pub require_description: bool,
+    /// Answer a query with no id and no name with `[]` instead of a 404.
+    pub empty_query_ok: bool,
+    /// MARC country code (`enk`, `nyu`) breaking ties between equally ranked editions in
+    /// work lookups; never excludes an edition.
+    pub preferred_country: Option<String>,
+    /// Binding whose editions work lookups pick first; a preference only.
+    pub preferred_binding: Option<Binding>,
+    /// MARC language codes tried in order when a work lookup picks its edition; no match
+    /// falls back to any language. A preference only: no edition is ever dropped.
+    pub language_fallback_chain: Vec<String>,
+}
+
+impl LookupOptions {
+    pub fn from_params(params: Option<&HashMap<String, String>>) -> Self {
+        let Some(params) = params else {
+            return Self::default();
+        };
+
+        let options = Self {
+            caching: parse_bool(params.get("caching")).unwrap_or(false),
+            include_toc: parse_bool(params.get("include_toc")).unwrap_or(false),
+            min_results: parse_number(params.get("min_results")),
+            exclude_org_authors: parse_bool(params.get("exclude_org_authors")).unwrap_or(false),
+            max_subjects: parse_number(params.get("max_subjects")),
+            max_people: parse_number(params.get("max_people")),
+            ebooks: parse_bool(params.get("ebooks")).unwrap_or(false),
+            published_in: params
+                .get("published_in")
+                .map(|range| range.trim().to_string())
+                .filter(|range| !range.is_empty()),
+            author: params
+                .get("author")
+                .map(|author| author.trim().to_string())
+                .filter(|author| !author.is_empty()),
+            broaden_if_few: parse_bool(params.get("broaden_if_few")).unwrap_or(false),
+            include_ratings: parse_bool(params.get("include_ratings")).unwrap_or(false),
+            max_requests: parse_number(params.get("max_requests")),
+            max_pages: parse_number(params.get("max_pages")),
+            search_limit: parse_number(params.get("search_limit")),
+            verbose_errors: parse_bool(params.get("verbose_errors")).unwrap_or(false),
+            strip_parentheticals: parse_bool(params.get("strip_parentheticals")).unwrap_or(false),
+            search_inside: parse_bool(params.get("search_inside")).unwrap_or(false),
+            resolve_authors: parse_bool(params.get("resolve_authors")).unwrap_or(false),
+            result_fields: parse_list(params.get("result_fields")),
+            enrich_from_work: parse_bool(params.get("enrich_from_work")).unwrap_or(false),
+            normalize_authors: parse_bool(params.get("normalize_authors")).unwrap_or(false),
+            subject_enrichment: parse_number(params.get("subject_enrichment")),
+            work_fallback_to_name: parse_bool(params.get("work_fallback_to_name")),
+            verify_covers: parse_bool(params.get("verify_covers")).unwrap_or(false),
+            max_description_chars: parse_number(params.get("max_description_chars")),
+            no_dedup: parse_bool(params.get("no_dedup")).unwrap_or(false),
+            all_editions: parse_bool(params.get("all_editions")).unwrap_or(false),
+            rank_covers: parse_bool(params.get("rank_covers")).unwrap_or(false),
+            require_isbn: parse_bool(params.get("require_isbn")).unwrap_or(false),
+            all_edition_covers: parse_bool(params.get("all_edition_covers")).unwrap_or(false),
+            olid_cover_fallback: parse_bool(params.get("olid_cover_fallback")),
+            fuzzy_isbn: parse_bool(params.get("fuzzy_isbn")).unwrap_or(false),
+            authors_from_work: parse_bool(params.get("authors_from_work")),
+            with_envelope: parse_bool(params.get("with_envelope")).unwrap_or(false),
+            max_retries: parse_number(params.get("max_retries")),
+            strict_isbn_match: parse_bool(params.get("strict_isbn_match")).unwrap_or(false),
+            include_work_link_for_editions: parse_bool(
+                params.get("include_work_link_for_editions"),
+            )
+            .unwrap_or(false),
+            keep_full_description: parse_bool(params.get("keep_full_description")).unwrap_or(false),
+            cover_index: parse_number(params.get("cover_index")),
+            debug_sources: parse_bool(params.get("debug_sources")).unwrap_or(false),
+            require_all_tokens: parse_bool(params.get("require_all_tokens")).unwrap_or(false),
+            require_description: parse_bool(params.get("require_description")).unwrap_or(false),
+            empty_query_ok: parse_bool(params.get("empty_query_ok")).unwrap_or(false),
+            cover_size: params
+                .get("cover_size")
+                .and_then(|value| CoverSize::parse(value))
+                .unwrap_or_default(),
+            cover_scheme: params
+                .get("cover_scheme")
+                .and_then(|value| CoverScheme::parse(value))
+                .unwrap_or_default(),
+            cover_preference: params
+                .get("cover_preference")
+                .and_then(|value| CoverPreference::parse(value))
+                .unwrap_or_default(),
+            sort: params
+                .get("sort")
+                .and_then(|value| SortOrder::parse(value))
+                .unwrap_or_default(),
+            on_ambiguous: params
+                .get("on_ambiguous")
+                .and_then(|value| AmbiguityMode::parse(value))
+                .unwrap_or_default(),
+            edition_preference: params
+                .get("edition_preference")
+                .and_then(|value| EditionPreference::parse(value))
+                .unwrap_or_default(),
+            canonical_preference: params
+                .get("canonical_preference")
+                .and_then(|value| CanonicalPreference::parse(value))
+                .unwrap_or_default(),
+            isbn_only_canonical: params
+                .get("isbn_only_canonical")
+                .and_then(|value| IsbnOnlyCanonical::parse(value))
+                .unwrap_or_default(),
+            language_fallback_chain: parse_list(params.get("language_fallback_chain"))
+                .unwrap_or_default()
+                .into_iter()
+                .map(|language| language.to_ascii_lowercase())
+                .collect(),
+            preferred_country: params
+                .get("preferred_country")
+                .map(|country| country.trim().to_ascii_lowercase())
+                .filter(|country| !country.is_empty()),
+            preferred_binding: if parse_bool(params.get("prefer_hardcover")).unwrap_or(false) {
+                Some(Binding::Hardcover)
+            } else if parse_bool(params.get("prefer_paperback")).unwrap_or(false) {
+                Some(Binding::Paperback)
+            } else {
+                None
+            },
+            default_language: params
+                .get("default_language")
+                .map(|language| language.trim().to_ascii_lowercase())
+                .filter(|language| !language.is_empty()),
+            locale: params
+                .get("locale")
+                .and_then(|locale| locale.trim().split(['-', '_']).next())
+                .filter(|locale| !locale.is_empty())
+                .map(|locale| locale.to_ascii_lowercase()),
+            enrichment: params
+                .get("enrichment")
+                .and_then(|value| EnrichmentLevel::parse(value))
+                .unwrap_or_default(),
+        };
+        options.with_enrichment_level()
+    }
+
+    /// Turns off the enrichment flags the configured level does not allow.
+    fn with_enrichment_level(mut self) -> Self {
+        if self.enrichment == EnrichmentLevel::Full {
+            return self;
+        }
+
+        self.resolve_authors = false;
+        self.subject_enrichment = None;
+        self.rank_covers = false;
+        self.all_edition_covers = false;
+        self.verify_covers = false;
+        if self.enrichment == EnrichmentLevel::Off {
+            self.enrich_from_work = false;
+            self.include_ratings = false;
+            self.authors_from_work = Some(false);
+        }
+        self
+    }
+
+    /// `canonical_preference`, forced to `Isbn` by `isbn_only_canonical`.
+    pub fn canonical_preference(&self) -> CanonicalPreference {
+        match self.isbn_only_canonical {
+            IsbnOnlyCanonical::Off => self.canonical_preference,
+            IsbnOnlyCanonical::Exclude | IsbnOnlyCanonical::Flag => CanonicalPreference::Isbn,
+        }
+    }
+
+    /// True when an option picks or reorders covers. The books data endpoint knows a
+    /// single cover per ISBN, so it can only stand in for the default cover pipeline.
+    pub fn selects_covers(&self) -> bool {
+        self.all_edition_covers
+            || self.rank_covers
+            || self.cover_index.is_some()
+            || self.cover_preference != CoverPreference::default()
+    }
+
+    pub fn max_pages(&self) -> u32 {
+        self.max_pages.unwrap_or(DEFAULT_MAX_PAGES)
+    }
+
+    /// `search_limit` clamped to `1..=MAX_SEARCH_LIMIT`, `DEFAULT_SEARCH_LIMIT` when unset.
+    pub fn search_limit(&self) -> u32 {
+        self.search_limit.map_or(DEFAULT_SEARCH_LIMIT, |limit| {
+            limit.clamp(1, MAX_SEARCH_LIMIT)
+        })
+    }
+
+    pub fn work_fallback_to_name(&self) -> bool {
+        self.work_fallback_to_name.unwrap_or(true)
+    }
+
+    pub fn olid_cover_fallback(&self) -> bool {
+        self.olid_cover_fallback.unwrap_or(true)
+    }
+
+    pub fn authors_from_work(&self) -> bool {
+        self.authors_from_work.unwrap_or(true)
+    }
+}
+
+fn parse_bool(value: Option<&String>) -> Option<bool> {
+    match value?.trim().to_ascii_lowercase().as_str() {
+        "true" | "1" | "yes" | "on" => Some(true),
+        "false" | "0" | "no" | "off" => Some(false),
+        _ => None,
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(value: Option<&String>) -> Option<T> {
+    value?.trim().parse().ok()
+}
+
+fn parse_list(value: Option<&String>) -> Option<Vec<String>> {
+    let values: Vec<String> = value?
+        .split(',')
+        .map(|item| item.trim())
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect();
+    if values.is_empty() {
+        None
+    } else {
+        Some(values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(entries: &[(&str, &str)]) -> HashMap<String, String> {
+        entries
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn defaults_when_params_are_missing() {
+        let options = LookupOptions::from_params(None);
+        assert!(!options.caching);
+        assert_eq!(options.min_results, None);
+    }
+
+    #[test]
+    fn locale_keeps_primary_language() {
+        let options = LookupOptions::from_params(Some(&params(&[("locale", "fr-FR")])));
+        assert_eq!(options.locale, Some("fr".to_string()));
+    }
+
+    #[test]
+    fn parses_numbers_and_ignores_garbage() {
+        let options = LookupOptions::from_params(Some(&params(&[("min_results", " 5 ")])));
+        assert_eq!(options.min_results, Some(5));
+
+        let options = LookupOptions::from_params(Some(&params(&[("min_results", "five")])));
+        assert_eq!(options.min_results, None);
+    }
+
+    #[test]
+    fn search_limit_is_clamped_into_the_url() {
+        let url = |limit: &str| {
+            let options = LookupOptions::from_params(Some(&params(&[("search_limit", limit)])));
+            crate::openlibrary::build_search_url("The Hobbit", options.search_limit(), 1)
+        };
+        assert!(url("500").ends_with("&limit=100"));
+        assert!(url("0").ends_with("&limit=1"));
+        assert!(url("5").ends_with("&limit=5"));
+        assert!(url("lots").ends_with("&limit=25"));
+        assert_eq!(
+            LookupOptions::default().search_limit(),
+            DEFAULT_SEARCH_LIMIT
+        );
+    }
+
+    #[test]
+    fn parses_canonical_preference() {
+        let options =
+            LookupOptions::from_params(Some(&params(&[("canonical_preference", "Work")])));
+        assert_eq!(options.canonical_preference, CanonicalPreference::Work);
+
+        let options =
+            LookupOptions::from_params(Some(&params(&[("canonical_preference", "asin")])));
+        assert_eq!(options.canonical_preference, CanonicalPreference::Isbn);
+    }
+
+    #[test]
+    fn parses_comma_separated_lists() {
+        let options = LookupOptions::from_params(Some(&params(&[(
+            "result_fields",
+            "authors, openlibraryWorkId,,",
+        )])));
+        assert_eq!(
+            options.result_fields,
+            Some(vec!["authors".to_string(), "openlibraryWorkId".to_string()])
+        );
+
+        let options = LookupOptions::from_params(Some(&params(&[("result_fields", " , ")])));
+        assert_eq!(options.result_fields, None);
+    }
+
+    #[test]
+    fn parses_edition_preference() {
+        let options =
+            LookupOptions::from_params(Some(&params(&[("edition_preference", "original")])));
+        assert_eq!(options.edition_preference, EditionPreference::Original);
+
+        let options = LookupOptions::from_params(None);
+        assert_eq!(options.edition_preference, EditionPreference::First);
+    }
+
+    #[test]
+    fn authors_from_work_is_on_by_default() {
+        assert!(LookupOptions::from_params(None).authors_from_work());
+        let options = LookupOptions::from_params(Some(&params(&[("authors_from_work", "false")])));
+        assert!(!options.authors_from_work());
+    }
+
+    #[test]
+    fn cover_scheme_defaults_to_https() {
+        let options = LookupOptions::from_params(Some(&params(&[("cover_scheme", "HTTP")])));
+        assert_eq!(options.cover_scheme, CoverScheme::Http);
+
+        let options = LookupOptions::from_params(Some(&params(&[("cover_scheme", "ftp")])));
+        assert_eq!(options.cover_scheme, CoverScheme::Https);
+        assert_eq!(
+            CoverScheme::Https.apply("https://covers.openlibrary.org/b/id/1-L.jpg".to_string()),
+            "https://covers.openlibrary.org/b/id/1-L.jpg"
+        );
+    }
+
+    #[test]
+    fn enrichment_level_caps_individual_flags() {
+        let flags = [
+            ("include_ratings", "true"),
+            ("enrich_from_work", "true"),
+            ("resolve_authors", "true"),
+            ("authors_from_work", "true"),
+            ("subject_enrichment", "3"),
+            ("rank_covers", "true"),
+            ("all_edition_covers", "true"),
+            ("verify_covers", "true"),
+        ];
+
+        let off = LookupOptions::from_params(Some(&params(
+            &[&flags[..], &[("enrichment", "off")]].concat(),
+        )));
+        assert!(!off.include_ratings && !off.enrich_from_work);
+        assert!(!off.resolve_authors && !off.authors_from_work());
+        assert_eq!(off.subject_enrichment, None);
+        assert!(!off.rank_covers && !off.all_edition_covers && !off.verify_covers);
+
+        let conservative = LookupOptions::from_params(Some(&params(
+            &[&flags[..], &[("enrichment", "conservative")]].concat(),
+        )));
+        assert!(conservative.include_ratings && conservative.enrich_from_work);
+        assert!(!conservative.resolve_authors);
+        assert!(conservative.authors_from_work());
+        let conservative_default =
+            LookupOptions::from_params(Some(&params(&[("enrichment", "conservative")])));
+        assert!(conservative_default.authors_from_work());
+        assert_eq!(conservative.subject_enrichment, None);
+
+        let full = LookupOptions::from_params(Some(&params(&flags)));
+        assert_eq!(full.enrichment, EnrichmentLevel::Full);
+        assert!(full.resolve_authors && full.verify_covers);
+        assert_eq!(full.subject_enrichment, Some(3));
+    }
+
+    #[test]
+    fn parses_boolean_flags() {
+        let options = LookupOptions::from_params(Some(&params(&[("caching", "TRUE")])));
+        assert!(options.caching);
+
+        let options = LookupOptions::from_params(Some(&params(&[("caching", "maybe")])));
+        assert!(!options.caching);
+    }
+
+    #[test]
+    fn cover_options_select_covers() {
+        assert!(!LookupOptions::from_params(None).selects_covers());
+        for (key, value) in [
+            ("all_edition_covers", "true"),
+            ("rank_covers", "true"),
+            ("cover_index", "1"),
+            ("cover_preference", "edition"),
+        ] {
+            let options = LookupOptions::from_params(Some(&params(&[(key, value)])));
+            assert!(options.selects_covers(), "{key}");
+        }
+    }
+}